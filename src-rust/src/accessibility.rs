@@ -0,0 +1,102 @@
+//! UI Automation provider for the toast window.
+//!
+//! The toast is custom-drawn, so without a provider screen readers see an
+//! empty window. The provider exposes the title as the element name and the
+//! message as its help text, marks the toast as a live region, and announces
+//! it through a UIA notification event when it appears.
+
+use windows::core::*;
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Variant::*;
+use windows::Win32::UI::Accessibility::*;
+
+#[implement(IRawElementProviderSimple)]
+struct ToastProvider {
+    hwnd: HWND,
+    title: String,
+    message: String,
+    input_mode: bool,
+}
+
+impl IRawElementProviderSimple_Impl for ToastProvider_Impl {
+    fn ProviderOptions(&self) -> Result<ProviderOptions> {
+        Ok(ProviderOptions_ServerSideProvider)
+    }
+
+    fn GetPatternProvider(&self, _pattern_id: UIA_PATTERN_ID) -> Result<IUnknown> {
+        // No patterns; clicks and keys are handled by the window itself
+        Err(Error::empty())
+    }
+
+    fn GetPropertyValue(&self, property_id: UIA_PROPERTY_ID) -> Result<VARIANT> {
+        Ok(match property_id {
+            id if id == UIA_NamePropertyId => VARIANT::from(self.title.as_str()),
+            id if id == UIA_HelpTextPropertyId => VARIANT::from(self.message.as_str()),
+            id if id == UIA_ControlTypePropertyId => VARIANT::from(UIA_WindowControlTypeId.0),
+            id if id == UIA_LiveSettingPropertyId => {
+                let setting = if self.input_mode { Assertive } else { Polite };
+                VARIANT::from(setting.0)
+            }
+            id if id == UIA_AutomationIdPropertyId => VARIANT::from("ClaudeCodeToast"),
+            _ => VARIANT::default(),
+        })
+    }
+
+    fn HostRawElementProvider(&self) -> Result<IRawElementProviderSimple> {
+        unsafe { UiaHostProviderFromHwnd(self.hwnd) }
+    }
+}
+
+/// Create the provider for a toast window.
+pub fn create_provider(hwnd: HWND, title: &str, message: &str, input_mode: bool) -> IRawElementProviderSimple {
+    ToastProvider {
+        hwnd,
+        title: title.to_string(),
+        message: message.to_string(),
+        input_mode,
+    }
+    .into()
+}
+
+/// Announce a newly shown toast to screen readers. "Input required" toasts
+/// interrupt whatever is being read; completions wait their turn.
+pub fn announce(provider: &IRawElementProviderSimple, title: &str, message: &str, input_mode: bool) {
+    unsafe {
+        if !UiaClientsAreListening().as_bool() {
+            return;
+        }
+        let _ = UiaRaiseAutomationEvent(provider, UIA_LiveRegionChangedEventId);
+
+        let processing = if input_mode {
+            NotificationProcessing_ImportantAll
+        } else {
+            NotificationProcessing_All
+        };
+        let text = BSTR::from(format!("{}. {}", title, message));
+        let activity = BSTR::from("ClaudeCodeNotify");
+        if let Err(e) = UiaRaiseNotificationEvent(provider, NotificationKind_Other, processing, &text, &activity) {
+            crate::debug_log!("UiaRaiseNotificationEvent failed: {:?}", e);
+        }
+    }
+}
+
+/// Answer WM_GETOBJECT. Returns None for requests that aren't for the UIA root.
+pub fn handle_get_object(
+    hwnd: HWND,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    provider: &IRawElementProviderSimple,
+) -> Option<LRESULT> {
+    if lparam.0 as i32 != UiaRootObjectId {
+        return None;
+    }
+    Some(unsafe { UiaReturnRawElementProvider(hwnd, wparam, lparam, provider) })
+}
+
+/// Release UIA's references when the window goes away.
+pub fn disconnect(hwnd: HWND, provider: &IRawElementProviderSimple) {
+    unsafe {
+        UiaReturnRawElementProvider(hwnd, WPARAM(0), LPARAM(0), None);
+        let _ = UiaDisconnectProvider(provider);
+    }
+}
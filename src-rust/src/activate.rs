@@ -1,63 +1,392 @@
 //! Window activation with focus-stealing workaround.
 //!
-//! Implements the ALT key trick and full 12-step activation sequence
-//! for both regular windows and Windows Terminal tabs.
+//! Tries a plain SetForegroundWindow first, then escalates through the full
+//! 12-step activation sequence, the ALT key trick, and a minimize/restore
+//! until the window is verified to be in front. Works for both regular
+//! windows and Windows Terminal tabs.
 
+use windows::core::PCWSTR;
 use windows::Win32::Foundation::*;
-use windows::Win32::System::Threading::{AttachThreadInput, GetCurrentThreadId};
+use windows::Win32::Graphics::Gdi::{
+    GetMonitorInfoW, MonitorFromPoint, MonitorFromWindow, HMONITOR, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::System::Threading::{AttachThreadInput, GetCurrentProcessId, GetCurrentThreadId};
 use windows::Win32::UI::Input::KeyboardAndMouse::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
+use crate::config::{ActivationMode, QuakeMode};
 use crate::uiautomation;
 
-/// Activate the saved window. If it's a WT window with a saved RuntimeId,
-/// switch to the correct tab.
-pub fn activate_window(
-    target: HWND,
-    wt_hwnd: HWND,
-    wt_runtime_id: &str,
-) {
-    if !wt_hwnd.is_invalid()
-        && wt_hwnd != HWND::default()
-        && !wt_runtime_id.is_empty()
-    {
+const WT_CLASS: &str = "CASCADIA_HOSTING_WINDOW_CLASS";
+
+/// Where a session's prompt was submitted: its window and, in terminals and
+/// editors with tabs, which tab.
+#[derive(Clone, Default)]
+pub struct Target {
+    pub hwnd: HWND,
+    /// Same as `hwnd` when that's a Windows Terminal window.
+    pub wt_hwnd: HWND,
+    pub wt_runtime_id: String,
+    pub wt_tab_name: String,
+    /// VS Code integrated-terminal tab title.
+    pub terminal_tab: String,
+    /// ConEmu console server PID.
+    pub conemu_console: String,
+    /// WezTerm pane id.
+    pub wezterm_pane: String,
+    /// tmux pane, for sessions inside tmux in WSL.
+    pub tmux: Option<crate::tmux::Pane>,
+}
+
+impl Target {
+    /// The top-level window to bring forward.
+    pub fn window(&self) -> HWND {
+        if self.wt_hwnd != HWND::default() { self.wt_hwnd } else { self.hwnd }
+    }
+}
+
+/// Activate the saved window. If it's a WT window with a saved RuntimeId
+/// (or tab title), switch to the correct tab; likewise for VS Code terminal
+/// tabs, ConEmu consoles, WezTerm panes and tmux windows. With
+/// `[activation] mode = "flash"`, or when the window is elevated and we
+/// aren't, its taskbar button flashes instead.
+/// Returns whether the window was verified to be in front.
+pub fn activate_window(t: &Target) -> bool {
+    let (target, wt_hwnd) = (t.hwnd, t.wt_hwnd);
+    let (wt_runtime_id, wt_tab_name) = (t.wt_runtime_id.as_str(), t.wt_tab_name.as_str());
+    let window = t.window();
+    if crate::config::get().activation.mode == ActivationMode::Flash || blocked_by_uipi(window) {
+        if wt_hwnd != HWND::default() {
+            // Selecting the tab doesn't take the focus, so it's ready when the user switches
+            let _ = uiautomation::select_tab(wt_hwnd, wt_runtime_id, wt_tab_name);
+            flash_window(wt_hwnd, 0);
+        } else {
+            flash_window(target, 0);
+        }
+        return false;
+    }
+    // Inside the terminal's tab; tmux doesn't need the window in front
+    if let Some(pane) = &t.tmux {
+        let _ = crate::tmux::select_pane(pane);
+    }
+    // A WT tab is searched for even when its window is gone: it may have been dragged out
+    if !(wt_runtime_id.is_empty() && wt_tab_name.is_empty()) {
         crate::debug_log!("Activating WT window with tab switch");
-        switch_to_wt_tab(wt_hwnd, wt_runtime_id);
+        switch_to_wt_tab(wt_hwnd, wt_runtime_id, wt_tab_name)
     } else if !target.is_invalid()
         && target != HWND::default()
         && unsafe { IsWindow(Some(target)).as_bool() }
     {
         crate::debug_log!("Activating regular window: {:?}", target);
-        activate_hwnd(target);
+        if !t.conemu_console.is_empty() {
+            let _ = crate::conemu::select_console(target, &t.conemu_console);
+        }
+        if !t.wezterm_pane.is_empty() {
+            let _ = crate::wezterm::activate_pane(target, &t.wezterm_pane);
+        }
+        let activated = activate_hwnd(target);
+        // The editor has to be in front for the click to focus the terminal
+        if activated && !t.terminal_tab.is_empty() {
+            let found = uiautomation::select_vscode_terminal_tab(target, &t.terminal_tab);
+            crate::debug_log!("Terminal tab {:?} selected: {}", t.terminal_tab, found);
+        }
+        activated
     } else {
         crate::debug_log!("No valid target window to activate");
+        false
+    }
+}
+
+/// Activate the window (and tab) saved for a session. Returns false if the
+/// session has no state or its windows are gone.
+pub fn activate_session(session_id: &str) -> bool {
+    let st = crate::state::load_state(session_id);
+    if st.target_hwnd == HWND::default() && st.wt_hwnd == HWND::default() {
+        crate::debug_log!("Session {} has no live window", session_id);
+        return false;
     }
+    crate::debug_log!("Activating session {}", session_id);
+    let _ = activate_window(&st.target());
+    true
 }
 
-fn switch_to_wt_tab(wt_hwnd: HWND, runtime_id: &str) {
-    if !unsafe { IsWindow(Some(wt_hwnd)).as_bool() } {
+/// Top-level Windows Terminal windows, front to back.
+fn wt_windows() -> Vec<HWND> {
+    let class = crate::util::encode_wide(WT_CLASS);
+    let mut windows = Vec::new();
+    let mut after = None;
+    while let Ok(hwnd) = unsafe { FindWindowExW(None, after, PCWSTR(class.as_ptr()), None) } {
+        if hwnd.is_invalid() {
+            break;
+        }
+        windows.push(hwnd);
+        after = Some(hwnd);
+    }
+    windows
+}
+
+fn switch_to_wt_tab(wt_hwnd: HWND, runtime_id: &str, tab_name: &str) -> bool {
+    let saved = unsafe { IsWindow(Some(wt_hwnd)).as_bool() };
+    if !saved {
         crate::debug_log!("WT window no longer valid");
-        return;
     }
 
+    // Switch to the correct tab via UI Automation: in the saved window, or
+    // in another WT window if the tab was dragged out into one
+    let host = if saved && uiautomation::select_tab(wt_hwnd, runtime_id, tab_name) {
+        Some(wt_hwnd)
+    } else {
+        wt_windows()
+            .into_iter()
+            .filter(|&hwnd| hwnd != wt_hwnd)
+            .find(|&hwnd| uiautomation::select_tab(hwnd, runtime_id, tab_name))
+    };
+    let host = match host {
+        Some(host) => {
+            crate::debug_log!("WT tab selected successfully in {:?}", host);
+            host
+        }
+        None if saved => {
+            crate::debug_log!("WT tab not found (may have been closed)");
+            wt_hwnd
+        }
+        None => {
+            crate::debug_log!("WT tab not found in any window");
+            return false;
+        }
+    };
+
     // Restore if minimized
-    if unsafe { IsIconic(wt_hwnd).as_bool() } {
-        unsafe { let _ = ShowWindow(wt_hwnd, SW_RESTORE); }
+    if unsafe { IsIconic(host).as_bool() } {
+        unsafe { let _ = ShowWindow(host, SW_RESTORE); }
+    }
+
+    // A hidden WT window is the quake window, put away
+    if !unsafe { IsWindowVisible(host).as_bool() } {
+        summon_quake(host);
     }
 
     // Bring WT window to foreground
-    activate_hwnd(wt_hwnd);
+    activate_hwnd(host)
+}
 
-    // Switch to the correct tab via UI Automation
-    if uiautomation::select_tab_by_runtime_id(wt_hwnd, runtime_id) {
-        crate::debug_log!("WT tab selected successfully");
-    } else {
-        crate::debug_log!("WT tab not found (may have been closed)");
+/// Bring back the hidden quake-mode window: by pressing WT's summon hotkey,
+/// which WT handles like the user pressing it, or by showing it directly.
+fn summon_quake(hwnd: HWND) {
+    let cfg = &crate::config::get().activation;
+    crate::debug_log!("Summoning the quake window ({:?})", cfg.quake);
+    if cfg.quake == QuakeMode::Hotkey {
+        if let Some((modifiers, vk)) = crate::tray::parse_hotkey(&cfg.quake_hotkey) {
+            send_chord(modifiers, VIRTUAL_KEY(vk as u16));
+            // Wait for the window to slide in, up to half a second
+            for _ in 0..10 {
+                if unsafe { IsWindowVisible(hwnd).as_bool() } {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            crate::debug_log!("Quake hotkey didn't show the window, showing it directly");
+        } else {
+            crate::debug_log!("Invalid quake_hotkey: {}", cfg.quake_hotkey);
+        }
+    }
+    unsafe {
+        let _ = ShowWindow(hwnd, SW_SHOW);
     }
 }
 
-/// Full 12-step activation sequence (SPEC 7.2).
-fn activate_hwnd(target: HWND) {
+/// Press and release a key with modifiers held.
+fn send_chord(modifiers: HOT_KEY_MODIFIERS, vk: VIRTUAL_KEY) {
+    let key = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT { wVk: vk, dwFlags: flags, ..Default::default() },
+        },
+    };
+    let held: Vec<VIRTUAL_KEY> = [(MOD_WIN, VK_LWIN), (MOD_CONTROL, VK_CONTROL), (MOD_ALT, VK_MENU), (MOD_SHIFT, VK_SHIFT)]
+        .into_iter()
+        .filter(|(m, _)| modifiers.contains(*m))
+        .map(|(_, vk)| vk)
+        .collect();
+    let mut inputs: Vec<INPUT> = held.iter().map(|&m| key(m, KEYBD_EVENT_FLAGS(0))).collect();
+    inputs.push(key(vk, KEYBD_EVENT_FLAGS(0)));
+    inputs.push(key(vk, KEYEVENTF_KEYUP));
+    inputs.extend(held.iter().rev().map(|&m| key(m, KEYEVENTF_KEYUP)));
+    unsafe {
+        SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+}
+
+/// Parse a key sequence such as "1<Enter>": characters are typed as is,
+/// names in angle brackets press that key. "<<" types a literal "<".
+fn parse_keys(text: &str) -> Option<Vec<INPUT>> {
+    let key = |vk: VIRTUAL_KEY, scan: u16, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT { wVk: vk, wScan: scan, dwFlags: flags, ..Default::default() },
+        },
+    };
+    let mut inputs = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '<' && chars.peek() != Some(&'<') {
+            let name: String = chars.by_ref().take_while(|&c| c != '>').collect();
+            let vk = match name.to_ascii_lowercase().as_str() {
+                "enter" | "return" => VK_RETURN,
+                "tab" => VK_TAB,
+                "esc" | "escape" => VK_ESCAPE,
+                "space" => VK_SPACE,
+                "backspace" | "bs" => VK_BACK,
+                "up" => VK_UP,
+                "down" => VK_DOWN,
+                "left" => VK_LEFT,
+                "right" => VK_RIGHT,
+                _ => {
+                    crate::debug_log!("Unknown key <{}> in keys", name);
+                    return None;
+                }
+            };
+            inputs.push(key(vk, 0, KEYBD_EVENT_FLAGS(0)));
+            inputs.push(key(vk, 0, KEYEVENTF_KEYUP));
+            continue;
+        }
+        if c == '<' {
+            chars.next();
+        }
+        let mut units = [0u16; 2];
+        for &unit in c.encode_utf16(&mut units).iter() {
+            inputs.push(key(VIRTUAL_KEY(0), unit, KEYEVENTF_UNICODE));
+            inputs.push(key(VIRTUAL_KEY(0), unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP));
+        }
+    }
+    Some(inputs)
+}
+
+/// Type `keys` (see `parse_keys`) into `target` once it's in front, e.g.
+/// to answer a pending prompt. Nothing is sent if another window has the focus.
+pub fn send_keys(target: HWND, keys: &str) {
+    if keys.is_empty() {
+        return;
+    }
+    let Some(inputs) = parse_keys(keys) else { return };
+    // Let the terminal finish switching tabs and take the focus
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    if !is_foreground(target) {
+        crate::debug_log!("Target lost the focus, not sending keys");
+        return;
+    }
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    crate::debug_log!("Sent {} of {} key events", sent, inputs.len());
+}
+
+/// Whether UIPI keeps us from activating `hwnd`: its process runs at a
+/// higher integrity level, typically an elevated terminal while we aren't.
+/// The other way round (we're elevated) activation works and is only logged.
+pub fn blocked_by_uipi(hwnd: HWND) -> bool {
+    if hwnd == HWND::default() {
+        return false;
+    }
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    let ours = crate::process::integrity_level(unsafe { GetCurrentProcessId() });
+    let theirs = crate::process::integrity_level(pid);
+    match (ours, theirs) {
+        (Some(ours), Some(theirs)) if theirs > ours => {
+            crate::debug_log!("Target runs at integrity {:#x}, above ours ({:#x})", theirs, ours);
+            true
+        }
+        (Some(ours), Some(theirs)) if theirs < ours => {
+            crate::debug_log!("Target runs at integrity {:#x}, below ours ({:#x})", theirs, ours);
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Flash a window's taskbar button without touching the focus: `count`
+/// times, or until the window comes to the front when 0.
+pub fn flash_window(hwnd: HWND, count: u32) {
+    if hwnd == HWND::default() || !unsafe { IsWindow(Some(hwnd)).as_bool() } {
+        crate::debug_log!("No valid window to flash");
+        return;
+    }
+    let info = FLASHWINFO {
+        cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+        hwnd,
+        dwFlags: if count == 0 { FLASHW_TRAY | FLASHW_TIMERNOFG } else { FLASHW_TRAY },
+        uCount: count,
+        dwTimeout: 0,
+    };
+    crate::debug_log!("Flashing {:?}", hwnd);
+    unsafe {
+        let _ = FlashWindowEx(&info);
+    }
+}
+
+/// Ways to bring a window to the front, from gentlest to most forceful.
+/// Each is tried only if the previous one left another window in front.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Strategy {
+    /// SetForegroundWindow alone; often allowed since the user just clicked the toast.
+    Plain,
+    /// The 12-step sequence without the ALT trick.
+    Sequence,
+    /// The 12-step sequence with the ALT trick.
+    AltSequence,
+    /// Minimize and restore the window, then the sequence: a restored
+    /// window is usually let into the foreground.
+    MinimizeRestore,
+}
+
+/// Move a window onto the monitor under the cursor (where the toast was
+/// clicked), at the same place relative to the work area and the same size,
+/// shrunk if it doesn't fit. A maximized window ends up maximized there.
+fn move_to_cursor_monitor(hwnd: HWND) {
+    unsafe {
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        let dest = MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST);
+        let source = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        if dest == source {
+            return;
+        }
+        let work_area = |monitor: HMONITOR| {
+            let mut info = MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+            GetMonitorInfoW(monitor, &mut info).as_bool().then_some(info.rcWork)
+        };
+        let (Some(from), Some(to)) = (work_area(source), work_area(dest)) else { return };
+
+        // A maximized window has to be moved in its restored state
+        let maximized = IsZoomed(hwnd).as_bool();
+        if maximized {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+        let mut rect = RECT::default();
+        let _ = GetWindowRect(hwnd, &mut rect);
+        let width = (rect.right - rect.left).min(to.right - to.left);
+        let height = (rect.bottom - rect.top).min(to.bottom - to.top);
+        let x = (to.left + rect.left - from.left).clamp(to.left, to.right - width);
+        let y = (to.top + rect.top - from.top).clamp(to.top, to.bottom - height);
+        let _ = SetWindowPos(hwnd, None, x, y, width, height, SWP_NOZORDER | SWP_NOACTIVATE);
+        if maximized {
+            let _ = ShowWindow(hwnd, SW_MAXIMIZE);
+        }
+        crate::debug_log!("Moved target to the cursor's monitor at ({}, {})", x, y);
+    }
+}
+
+/// Whether `target` (or a window it owns, such as a dialog) is in front.
+fn is_foreground(target: HWND) -> bool {
+    unsafe {
+        let fg = GetForegroundWindow();
+        fg == target || GetAncestor(fg, GA_ROOTOWNER) == target
+    }
+}
+
+/// Bring a window to the front, escalating through the strategies until
+/// it's verified to be the foreground window. Returns whether it got there.
+pub fn activate_hwnd(target: HWND) -> bool {
+    let cfg = &crate::config::get().activation;
     unsafe {
         // Step 1: Allow any process to set foreground
         let _ = AllowSetForegroundWindow(ASFW_ANY);
@@ -66,59 +395,102 @@ fn activate_hwnd(target: HWND) {
         if IsIconic(target).as_bool() {
             let _ = ShowWindow(target, SW_RESTORE);
         }
+    }
+
+    // Another virtual desktop: switch there or fetch the window
+    crate::desktop::bring_into_view(target);
+
+    if cfg.follow_monitor {
+        move_to_cursor_monitor(target);
+    }
+
+    let strategies = [Strategy::Plain, Strategy::Sequence, Strategy::AltSequence, Strategy::MinimizeRestore]
+        .into_iter()
+        .filter(|s| cfg.alt_trick || *s != Strategy::AltSequence)
+        .take(1 + cfg.retries as usize);
+    for (attempt, strategy) in strategies.enumerate() {
+        if attempt > 0 {
+            // Give the last attempt a moment to land before judging it
+            std::thread::sleep(std::time::Duration::from_millis(30));
+        }
+        unsafe {
+            match strategy {
+                Strategy::Plain => {
+                    let _ = SetForegroundWindow(target);
+                }
+                Strategy::Sequence => force_foreground(target, false),
+                Strategy::AltSequence => force_foreground(target, true),
+                Strategy::MinimizeRestore => {
+                    let _ = ShowWindow(target, SW_MINIMIZE);
+                    let _ = ShowWindow(target, SW_RESTORE);
+                    force_foreground(target, cfg.alt_trick);
+                }
+            }
+        }
+        if is_foreground(target) {
+            crate::debug_log!("Activated with {:?} (attempt {})", strategy, attempt + 1);
+            return true;
+        }
+        crate::debug_log!("{:?} didn't activate the window", strategy);
+    }
+    crate::warn_log!("Activation failed; foreground is {:?}", unsafe { GetForegroundWindow() });
+    false
+}
 
-        // Step 3: ALT key trick
+/// Full 12-step activation sequence (SPEC 7.2), from step 3.
+unsafe fn force_foreground(target: HWND, alt_trick: bool) {
+    // Step 3: ALT key trick
+    if alt_trick {
         try_alt_key_trick();
+    }
 
-        // Steps 4-6: Get thread IDs
-        let fg_hwnd = GetForegroundWindow();
-        let fg_thread = GetWindowThreadProcessId(fg_hwnd, None);
-        let cur_thread = GetCurrentThreadId();
-        let target_thread = GetWindowThreadProcessId(target, None);
+    // Steps 4-6: Get thread IDs
+    let fg_hwnd = GetForegroundWindow();
+    let fg_thread = GetWindowThreadProcessId(fg_hwnd, None);
+    let cur_thread = GetCurrentThreadId();
+    let target_thread = GetWindowThreadProcessId(target, None);
 
-        // Steps 7-8: Attach thread input
-        let _ = AttachThreadInput(cur_thread, fg_thread, true);
-        let _ = AttachThreadInput(cur_thread, target_thread, true);
+    // Steps 7-8: Attach thread input
+    let _ = AttachThreadInput(cur_thread, fg_thread, true);
+    let _ = AttachThreadInput(cur_thread, target_thread, true);
 
-        // Step 9: Set window position to top
-        let _ = SetWindowPos(
-            target,
-            Some(HWND_TOP),
-            0, 0, 0, 0,
-            SWP_NOMOVE | SWP_NOSIZE | SWP_SHOWWINDOW,
-        );
+    // Step 9: Set window position to top
+    let _ = SetWindowPos(
+        target,
+        Some(HWND_TOP),
+        0, 0, 0, 0,
+        SWP_NOMOVE | SWP_NOSIZE | SWP_SHOWWINDOW,
+    );
 
-        // Step 10: Bring to top
-        let _ = BringWindowToTop(target);
+    // Step 10: Bring to top
+    let _ = BringWindowToTop(target);
 
-        // Step 11: SwitchToThisWindow (undocumented but effective)
-        SwitchToThisWindow(target, true);
+    // Step 11: SwitchToThisWindow (undocumented but effective)
+    SwitchToThisWindow(target, true);
 
-        // Step 12: Set foreground
-        let _ = SetForegroundWindow(target);
+    // Step 12: Set foreground
+    let _ = SetForegroundWindow(target);
 
-        // Detach thread input
-        let _ = AttachThreadInput(cur_thread, target_thread, false);
-        let _ = AttachThreadInput(cur_thread, fg_thread, false);
-    }
+    // Detach thread input
+    let _ = AttachThreadInput(cur_thread, target_thread, false);
+    let _ = AttachThreadInput(cur_thread, fg_thread, false);
 }
 
 /// Simulate ALT key press/release to trick Windows into allowing
 /// foreground window changes (SPEC 7.1).
 fn try_alt_key_trick() {
-    unsafe {
-        keybd_event(
-            VK_MENU.0 as u8,
-            0,
-            KEYEVENTF_EXTENDEDKEY,
-            0,
-        );
-        keybd_event(
-            VK_MENU.0 as u8,
-            0,
-            KEYEVENTF_EXTENDEDKEY | KEYEVENTF_KEYUP,
-            0,
-        );
+    let key = |flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT { wVk: VK_MENU, dwFlags: KEYEVENTF_EXTENDEDKEY | flags, ..Default::default() },
+        },
+    };
+    let inputs = [key(KEYBD_EVENT_FLAGS(0)), key(KEYEVENTF_KEYUP)];
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        crate::debug_log!("SendInput for the ALT trick was blocked");
+        return;
     }
+    // Give the input time to reach the system before asking for the foreground
     std::thread::sleep(std::time::Duration::from_millis(50));
 }
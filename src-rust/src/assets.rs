@@ -1,13 +1,19 @@
-//! Asset discovery, font loading, icon extraction, and sound playback.
+//! Asset discovery, font loading, and icon extraction.
 
 use windows::core::PCWSTR;
+use windows::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE};
 use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::Graphics::Imaging::*;
 use windows::Win32::Storage::FileSystem::*;
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
 use windows::Win32::UI::Shell::ExtractIconExW;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
 const FR_PRIVATE: u32 = 0x10;
 
+/// Image extensions accepted for the toast icon; all but .ico are decoded through WIC.
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["ico", "png", "jpg", "jpeg"];
+
 /// Find the first file matching a pattern in a directory.
 /// e.g., find_first_file("C:\\dir", "*.wav")
 pub fn find_first_file(dir: &str, pattern: &str) -> Option<String> {
@@ -29,6 +35,25 @@ pub fn find_first_file(dir: &str, pattern: &str) -> Option<String> {
     }
 }
 
+/// Font file extensions loaded from assets\fonts.
+const FONT_EXTENSIONS: [&str; 2] = ["ttf", "otf"];
+
+/// Files in `dir` with one of `extensions`, sorted by name.
+fn find_files(dir: &str, extensions: &[&str]) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let ext = name.rsplit_once('.')?.1.to_ascii_lowercase();
+            extensions.contains(&ext.as_str()).then(|| format!("{}\\{}", dir, name))
+        })
+        .collect();
+    files.sort_by_key(|f| f.to_ascii_lowercase());
+    files
+}
+
 /// Get the directory containing the current executable.
 pub fn exe_dir() -> String {
     std::env::current_exe()
@@ -39,40 +64,155 @@ pub fn exe_dir() -> String {
         .into_owned()
 }
 
-/// Discover asset paths relative to the exe directory.
+/// Asset paths found by `discover_assets`.
 pub struct Assets {
-    pub sound_file: Option<String>,
-    pub font_file: Option<String>,
+    /// Every supported sound file, sorted by name; `sound::choose` picks one.
+    pub sound_files: Vec<String>,
+    /// Every font file, sorted by name; all are loaded as private fonts.
+    pub font_files: Vec<String>,
     pub default_icon_path: Option<String>,
 }
 
+/// Defaults compiled into the exe: (file name, contents). Used when no
+/// asset root has a file of that kind.
+const EMBEDDED_SOUND: (&str, &[u8]) =
+    ("notification.wav", include_bytes!("../../notifications/assets/sound/notification.wav"));
+const EMBEDDED_FONT: (&str, &[u8]) =
+    ("JetBrainsMono-ExtraBold.ttf", include_bytes!("../../notifications/assets/font/JetBrainsMono-ExtraBold.ttf"));
+const EMBEDDED_ICON: (&str, &[u8]) = ("claude.ico", include_bytes!("../../notifications/assets/img/claude.ico"));
+
+/// Path of an embedded asset, written out to %LOCALAPPDATA%\claude-code-notify\embedded
+/// the first time (PlaySound, AddFontResourceEx, and LoadImage all want files).
+fn embedded((name, bytes): (&str, &[u8])) -> Option<String> {
+    let dir = crate::config::local_dir()?.join("embedded");
+    let path = dir.join(name);
+    let current = std::fs::metadata(&path).is_ok_and(|m| m.len() == bytes.len() as u64);
+    if !current {
+        let written = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, bytes));
+        if let Err(e) = written {
+            crate::warn_log!("Failed to extract embedded {}: {}", name, e);
+            return None;
+        }
+    }
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Asset roots in precedence order: `[assets] dir`, then
+/// %APPDATA%\claude-code-notify\assets, then the assets folder next to the exe.
+fn asset_roots() -> Vec<String> {
+    let mut roots = Vec::new();
+    let configured = &crate::config::get().assets.dir;
+    if !configured.is_empty() {
+        roots.push(configured.trim_end_matches(['\\', '/']).to_string());
+    }
+    if let Some(user) = crate::config::user_dir() {
+        roots.push(user.join("assets").to_string_lossy().into_owned());
+    }
+    roots.push(format!("{}\\assets", exe_dir()));
+    roots
+}
+
 pub fn discover_assets() -> Assets {
-    let dir = exe_dir();
-    let sound_dir = format!("{}\\assets\\sound", dir);
-    let font_dir = format!("{}\\assets\\fonts", dir);
-    let img_dir = format!("{}\\assets\\img", dir);
+    let roots = asset_roots();
+
+    // Each kind comes from the first root that has one; the embedded copies
+    // keep a bare exe working
+    let sound_files = roots
+        .iter()
+        .map(|root| find_files(&format!("{}\\sound", root), &crate::sound::EXTENSIONS))
+        .find(|files| !files.is_empty())
+        .unwrap_or_else(|| embedded(EMBEDDED_SOUND).into_iter().collect());
+    let font_files = roots
+        .iter()
+        .flat_map(|root| [format!("{}\\fonts", root), format!("{}\\font", root)])
+        .map(|dir| find_files(&dir, &FONT_EXTENSIONS))
+        .find(|files| !files.is_empty())
+        .unwrap_or_else(|| embedded(EMBEDDED_FONT).into_iter().collect());
+    let default_icon_path = roots
+        .iter()
+        .find_map(|root| {
+            let dir = format!("{}\\img", root);
+            IMAGE_EXTENSIONS.iter().find_map(|ext| find_first_file(&dir, &format!("*.{}", ext)))
+        })
+        .or_else(|| embedded(EMBEDDED_ICON));
 
     Assets {
-        sound_file: find_first_file(&sound_dir, "*.wav"),
-        font_file: find_first_file(&font_dir, "*.ttf")
-            .or_else(|| find_first_file(&font_dir, "*.otf")),
-        default_icon_path: find_first_file(&img_dir, "*.ico"),
+        sound_files,
+        font_files,
+        default_icon_path,
     }
 }
 
-/// Load a custom font file as a private font. Returns the derived font family name.
+/// Load a custom font file as a private font. Returns its family name, read
+/// from the font's name table or, failing that, derived from the file name.
 pub fn load_font(font_path: &str) -> Option<String> {
     let path_wide = crate::util::encode_wide(font_path);
     let result = unsafe {
         AddFontResourceExW(PCWSTR(path_wide.as_ptr()), FONT_RESOURCE_CHARACTERISTICS(FR_PRIVATE), None)
     };
     if result > 0 {
-        Some(derive_font_family(font_path))
+        let family = std::fs::read(font_path).ok().and_then(|data| font_family_name(&data));
+        if family.is_none() {
+            crate::debug_log!("No family name in {}, deriving it from the file name", font_path);
+        }
+        Some(family.unwrap_or_else(|| derive_font_family(font_path)))
     } else {
         None
     }
 }
 
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Family name of a TrueType/OpenType font (the first font of a .ttc),
+/// from its `name` table. This is name ID 1, the family GDI knows the font
+/// by: "JetBrains Mono ExtraBold", not the typographic "JetBrains Mono".
+fn font_family_name(data: &[u8]) -> Option<String> {
+    const FAMILY: u16 = 1;
+    const PLATFORM_MAC: u16 = 1;
+    const PLATFORM_WINDOWS: u16 = 3;
+    const LANGUAGE_EN_US: u16 = 0x0409;
+
+    let font = if data.get(0..4)? == b"ttcf" { be_u32(data, 12)? as usize } else { 0 };
+    let tables = be_u16(data, font + 4)? as usize;
+    let name = (0..tables)
+        .map(|i| font + 12 + i * 16)
+        .find(|&record| data.get(record..record + 4) == Some(b"name"))
+        .and_then(|record| be_u32(data, record + 8))? as usize;
+
+    let count = be_u16(data, name + 2)? as usize;
+    let strings = name + be_u16(data, name + 4)? as usize;
+    // Best candidate so far: (rank, text); Windows English beats any
+    // Windows language, which beats a Mac Roman name
+    let mut best: Option<(u8, String)> = None;
+    for i in 0..count {
+        let record = name + 6 + i * 12;
+        let (platform, language, id) = (be_u16(data, record)?, be_u16(data, record + 4)?, be_u16(data, record + 6)?);
+        if id != FAMILY {
+            continue;
+        }
+        let start = strings + be_u16(data, record + 10)? as usize;
+        let Some(bytes) = data.get(start..start + be_u16(data, record + 8)? as usize) else { continue };
+        let (rank, text) = match platform {
+            PLATFORM_WINDOWS => {
+                let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                (if language == LANGUAGE_EN_US { 3 } else { 2 }, String::from_utf16_lossy(&units))
+            }
+            PLATFORM_MAC => (1, bytes.iter().map(|&b| b as char).collect()),
+            _ => continue,
+        };
+        if !text.trim().is_empty() && best.as_ref().is_none_or(|(r, _)| rank > *r) {
+            best = Some((rank, text));
+        }
+    }
+    best.map(|(_, text)| text.trim().to_string())
+}
+
 /// Remove a previously loaded private font.
 pub fn unload_font(font_path: &str) {
     let path_wide = crate::util::encode_wide(font_path);
@@ -81,7 +221,8 @@ pub fn unload_font(font_path: &str) {
     }
 }
 
-/// Derive font family name from filename (SPEC 13.3).
+/// Derive font family name from filename (SPEC 13.3). Fallback for fonts
+/// without a readable name table.
 fn derive_font_family(path: &str) -> String {
     // Extract filename without directory
     let name = path
@@ -118,6 +259,73 @@ fn derive_font_family(path: &str) -> String {
     result
 }
 
+/// FNV-1a, a hash that stays the same across builds for cache file names.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// The icon of the calling app. Extracted icons are cached as PNGs in
+/// %LOCALAPPDATA%\claude-code-notify\icons, keyed by exe path and modification
+/// time, so an updated exe is extracted again.
+pub fn app_icon(exe_path: &str, size: i32) -> HICON {
+    let Some(dir) = crate::config::local_dir().map(|d| d.join("icons")) else { return extract_icon(exe_path) };
+    let Ok(modified) = std::fs::metadata(exe_path).and_then(|m| m.modified()) else {
+        return extract_icon(exe_path);
+    };
+    let mtime = modified.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let prefix = format!("{:016x}-", fnv1a(&exe_path.to_lowercase()));
+    let cached = dir.join(format!("{}{}.png", prefix, mtime));
+
+    if cached.is_file() {
+        let icon = load_icon_file(&cached.to_string_lossy(), size);
+        if !icon.is_invalid() {
+            return icon;
+        }
+    }
+
+    let icon = extract_icon(exe_path);
+    if !icon.is_invalid() {
+        // Drop entries for older builds of the same exe
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        let _ = std::fs::create_dir_all(&dir);
+        if let Err(e) = unsafe { save_icon_png(icon, &cached.to_string_lossy()) } {
+            crate::warn_log!("Failed to cache icon for {}: {:?}", exe_path, e);
+            let _ = std::fs::remove_file(&cached);
+        }
+    }
+    icon
+}
+
+/// Write an icon to a PNG file, keeping its alpha.
+unsafe fn save_icon_png(icon: HICON, path: &str) -> windows::core::Result<()> {
+    let factory: IWICImagingFactory = CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)?;
+    let bitmap = factory.CreateBitmapFromHICON(icon)?;
+    let (mut width, mut height) = (0u32, 0u32);
+    bitmap.GetSize(&mut width, &mut height)?;
+
+    let path_wide = crate::util::encode_wide(path);
+    let stream = factory.CreateStream()?;
+    stream.InitializeFromFilename(PCWSTR(path_wide.as_ptr()), GENERIC_WRITE.0)?;
+    let encoder = factory.CreateEncoder(&GUID_ContainerFormatPng, std::ptr::null())?;
+    encoder.Initialize(&stream, WICBitmapEncoderNoCache)?;
+    let mut frame = None;
+    encoder.CreateNewFrame(&mut frame, std::ptr::null_mut())?;
+    let frame = frame.ok_or_else(windows::core::Error::empty)?;
+    frame.Initialize(None)?;
+    frame.SetSize(width, height)?;
+    let mut format = GUID_WICPixelFormat32bppBGRA;
+    frame.SetPixelFormat(&mut format)?;
+    frame.WriteSource(&bitmap, std::ptr::null())?;
+    frame.Commit()?;
+    encoder.Commit()
+}
+
 /// Extract the large icon from an exe file (index 0).
 pub fn extract_icon(exe_path: &str) -> HICON {
     if exe_path.is_empty() {
@@ -147,30 +355,100 @@ pub fn extract_icon(exe_path: &str) -> HICON {
     large
 }
 
-/// Play a notification sound (SPEC 12.2).
-pub fn play_sound(wav_path: &Option<String>) {
-    use windows::Win32::Media::Audio::*;
-
-    if let Some(path) = wav_path {
+/// Load an image file as a `size` x `size` icon: .ico through LoadImage,
+/// PNG and JPEG through WIC. Returns an invalid HICON on failure; the
+/// caller destroys a valid one.
+pub fn load_icon_file(path: &str, size: i32) -> HICON {
+    if path.to_ascii_lowercase().ends_with(".ico") {
         let path_wide = crate::util::encode_wide(path);
-        unsafe {
-            let result = PlaySoundW(
-                PCWSTR(path_wide.as_ptr()),
-                None,
-                SND_FILENAME | SND_ASYNC,
-            );
-            if result.as_bool() {
-                return;
+        let loaded = unsafe {
+            LoadImageW(None, PCWSTR(path_wide.as_ptr()), IMAGE_ICON, size, size, LR_LOADFROMFILE)
+        };
+        return match loaded {
+            Ok(handle) => HICON(handle.0),
+            Err(e) => {
+                crate::warn_log!("Failed to load icon {}: {:?}", path, e);
+                HICON::default()
             }
+        };
+    }
+    match unsafe { decode_icon(path, size) } {
+        Ok(icon) => icon,
+        Err(e) => {
+            crate::warn_log!("Failed to decode image {}: {:?}", path, e);
+            HICON::default()
         }
     }
+}
 
-    // Fallback: system beep
-    #[link(name = "user32")]
-    extern "system" {
-        fn MessageBeep(utype: u32) -> i32;
-    }
-    unsafe {
-        MessageBeep(0x40); // MB_ICONASTERISK
+/// Decode an image with WIC, scale it to fit `size` x `size` keeping its
+/// aspect ratio, and build a 32-bit icon whose alpha comes from the image.
+unsafe fn decode_icon(path: &str, size: i32) -> windows::core::Result<HICON> {
+    let factory: IWICImagingFactory = CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)?;
+    let path_wide = crate::util::encode_wide(path);
+    let decoder = factory.CreateDecoderFromFilename(
+        PCWSTR(path_wide.as_ptr()),
+        None,
+        GENERIC_READ,
+        WICDecodeMetadataCacheOnDemand,
+    )?;
+    let frame = decoder.GetFrame(0)?;
+
+    let (mut width, mut height) = (0u32, 0u32);
+    frame.GetSize(&mut width, &mut height)?;
+    let side = size.max(1) as u32;
+    let longest = width.max(height).max(1);
+    let (scaled_w, scaled_h) = ((width * side / longest).max(1), (height * side / longest).max(1));
+
+    let scaler = factory.CreateBitmapScaler()?;
+    scaler.Initialize(&frame, scaled_w, scaled_h, WICBitmapInterpolationModeFant)?;
+    // Icons take straight (not premultiplied) alpha
+    let converter = factory.CreateFormatConverter()?;
+    converter.Initialize(
+        &scaler,
+        &GUID_WICPixelFormat32bppBGRA,
+        WICBitmapDitherTypeNone,
+        None,
+        0.0,
+        WICBitmapPaletteTypeCustom,
+    )?;
+    let stride = scaled_w * 4;
+    let mut pixels = vec![0u8; (stride * scaled_h) as usize];
+    converter.CopyPixels(std::ptr::null(), stride, &mut pixels)?;
+
+    // Center the image on a transparent square
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: side as i32,
+            biHeight: -(side as i32), // top-down, like the WIC rows
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut bits = std::ptr::null_mut();
+    let color = CreateDIBSection(None, &info, DIB_RGB_COLORS, &mut bits, None, 0)?;
+    let square = std::slice::from_raw_parts_mut(bits as *mut u8, (side * side * 4) as usize);
+    square.fill(0);
+    let (left, top) = ((side - scaled_w) / 2, (side - scaled_h) / 2);
+    for (row, line) in pixels.chunks_exact(stride as usize).enumerate() {
+        let start = (((top + row as u32) * side + left) * 4) as usize;
+        square[start..start + line.len()].copy_from_slice(line);
     }
+
+    // The mask is ignored for 32-bit color bitmaps but still required
+    let mask = CreateBitmap(side as i32, side as i32, 1, 1, None);
+    let icon_info = ICONINFO {
+        fIcon: true.into(),
+        hbmMask: mask,
+        hbmColor: color,
+        ..Default::default()
+    };
+    let icon = CreateIconIndirect(&icon_info);
+    let _ = DeleteObject(HGDIOBJ(color.0));
+    let _ = DeleteObject(HGDIOBJ(mask.0));
+    icon
 }
@@ -0,0 +1,160 @@
+//! Output devices through the Core Audio APIs (MMDevice, WASAPI).
+//!
+//! PlaySoundW always uses the default device. To play on another one the
+//! sound is rendered with a shared-mode WASAPI stream instead. Also answers
+//! whether a sound should be skipped: the device is muted, or a call is
+//! going on and `[sound] skip_during_calls` is set.
+
+use windows::core::*;
+use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+use windows::Win32::Foundation::E_INVALIDARG;
+use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+use windows::Win32::Media::Audio::*;
+use windows::Win32::System::Com::*;
+
+/// `[sound] device` value for the default communications device.
+const COMMUNICATIONS: &str = "communications";
+
+fn enumerator() -> Result<IMMDeviceEnumerator> {
+    unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) }
+}
+
+/// Resolve a `[sound] device` value: "" for the default output,
+/// "communications" for the default communications output, otherwise the
+/// first active output whose name contains the text (case-insensitive).
+/// Falls back to the default output when nothing matches.
+fn find_device(spec: &str) -> Result<IMMDevice> {
+    let devices = enumerator()?;
+    unsafe {
+        if spec.eq_ignore_ascii_case(COMMUNICATIONS) {
+            return devices.GetDefaultAudioEndpoint(eRender, eCommunications);
+        }
+        if !spec.is_empty() {
+            let wanted = spec.to_lowercase();
+            let outputs = devices.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+            for i in 0..outputs.GetCount()? {
+                let device = outputs.Item(i)?;
+                let name = device
+                    .OpenPropertyStore(STGM_READ)
+                    .and_then(|props| props.GetValue(&PKEY_Device_FriendlyName))
+                    .map(|value| value.to_string())
+                    .unwrap_or_default();
+                if name.to_lowercase().contains(&wanted) {
+                    crate::debug_log!("Sound device: {}", name);
+                    return Ok(device);
+                }
+            }
+            crate::debug_log!("No output device matching {:?}, using the default", spec);
+        }
+        devices.GetDefaultAudioEndpoint(eRender, eConsole)
+    }
+}
+
+fn is_muted(device: &IMMDevice) -> bool {
+    unsafe {
+        device
+            .Activate::<IAudioEndpointVolume>(CLSCTX_ALL, None)
+            .and_then(|volume| volume.GetMute())
+            .is_ok_and(|muted| muted.as_bool())
+    }
+}
+
+/// Whether a call seems to be going on: the default communications
+/// microphone has an active audio session.
+fn in_call() -> bool {
+    let active = || -> Result<bool> {
+        unsafe {
+            let mic = enumerator()?.GetDefaultAudioEndpoint(eCapture, eCommunications)?;
+            let sessions = mic.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None)?.GetSessionEnumerator()?;
+            for i in 0..sessions.GetCount()? {
+                if sessions.GetSession(i)?.GetState()? == AudioSessionStateActive {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+    };
+    active().unwrap_or(false)
+}
+
+/// Whether to stay silent on the output chosen by `spec`.
+pub fn should_skip(spec: &str) -> bool {
+    if find_device(spec).is_ok_and(|device| is_muted(&device)) {
+        crate::debug_log!("Output device muted, no sound");
+        return true;
+    }
+    if crate::config::get().sound.skip_during_calls && in_call() {
+        crate::debug_log!("Call in progress, no sound");
+        return true;
+    }
+    false
+}
+
+/// Play a WAV image on the output chosen by `spec`. Blocks until the sound
+/// has finished, so call it on a thread of its own.
+pub fn play_on_device(spec: &str, wav: &[u8]) {
+    let Some((fmt, data)) = crate::sound::wav_chunks(wav) else {
+        crate::debug_log!("Not a WAV image, nothing to play");
+        return;
+    };
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        if !should_skip(spec) {
+            if let Err(e) = find_device(spec).and_then(|device| render(&device, &wav[fmt], &wav[data])) {
+                crate::debug_log!("WASAPI playback failed: {:?}", e);
+            }
+        }
+        CoUninitialize();
+    }
+}
+
+/// Stream PCM `data` in the format described by `fmt` (a WAVEFORMATEX) to a device.
+unsafe fn render(device: &IMMDevice, fmt: &[u8], data: &[u8]) -> Result<()> {
+    // 200 ms buffer, in 100 ns units
+    const BUFFER_DURATION: i64 = 2_000_000;
+
+    if fmt.len() < std::mem::size_of::<WAVEFORMATEX>() - 2 {
+        return Err(Error::from(E_INVALIDARG));
+    }
+    // fmt may be 16 bytes (no cbSize); WAVEFORMATEX wants at least 18
+    let mut format = fmt.to_vec();
+    format.resize(format.len().max(std::mem::size_of::<WAVEFORMATEX>()), 0);
+    let wave = format.as_ptr() as *const WAVEFORMATEX;
+    let block_align = (*wave).nBlockAlign.max(1) as usize;
+
+    let client: IAudioClient = device.Activate(CLSCTX_ALL, None)?;
+    client.Initialize(
+        AUDCLNT_SHAREMODE_SHARED,
+        // Let the engine convert rate and channels to the device mix format
+        AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+        BUFFER_DURATION,
+        0,
+        wave,
+        None,
+    )?;
+    let buffer_frames = client.GetBufferSize()?;
+    let renderer: IAudioRenderClient = client.GetService()?;
+    client.Start()?;
+
+    let total_frames = data.len() / block_align;
+    let mut written = 0;
+    while written < total_frames {
+        let free = (buffer_frames - client.GetCurrentPadding()?) as usize;
+        let frames = free.min(total_frames - written);
+        if frames == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            continue;
+        }
+        let buffer = renderer.GetBuffer(frames as u32)?;
+        let bytes = &data[written * block_align..(written + frames) * block_align];
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+        renderer.ReleaseBuffer(frames as u32, 0)?;
+        written += frames;
+    }
+
+    // Let the buffered tail play out
+    while client.GetCurrentPadding()? > 0 {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    client.Stop()
+}
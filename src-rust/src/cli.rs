@@ -1,63 +1,360 @@
 //! CLI argument parsing for ToastWindow.
 //!
-//! Modes: --save, --notify, --input, --notify-show, --cleanup
-//! Flags: --debug/-d, --input-mode, --session <val>, --message <val>
+//! A command, as a subcommand (`save`, `show`, ...) or in the older flag
+//! form the hooks use (`--save`, `--notify-show`, ...), then options.
+//! Options take `--name value` or `--name=value`. Anything unknown is an
+//! error rather than ignored; see HELP for the list.
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum Mode {
     Save,
     Notify,
     Input,
     NotifyShow,
     Cleanup,
+    ResetPosition,
+    History,
+    Tray,
+    DismissAll,
+    ListSessions,
+    Activate,
+    Mute,
+    Unmute,
+    Preview,
+    Doctor,
+    Simulate,
+    Config,
+    Replay,
+    RegisterEventLog,
+    Logs,
+    Stats,
+    Help,
+    Version,
+    #[default]
     None,
 }
 
-#[derive(Debug)]
+/// Commands: subcommand name, flag form, and mode.
+const COMMANDS: [(&str, &str, Mode); 23] = [
+    ("save", "--save", Mode::Save),
+    ("notify", "--notify", Mode::Notify),
+    ("input", "--input", Mode::Input),
+    ("show", "--notify-show", Mode::NotifyShow),
+    ("cleanup", "--cleanup", Mode::Cleanup),
+    ("reset-position", "--reset-position", Mode::ResetPosition),
+    ("history", "--history", Mode::History),
+    ("tray", "--tray", Mode::Tray),
+    ("dismiss-all", "--dismiss-all", Mode::DismissAll),
+    ("list-sessions", "--list-sessions", Mode::ListSessions),
+    ("activate", "--activate", Mode::Activate),
+    ("mute", "--mute", Mode::Mute),
+    ("unmute", "--unmute", Mode::Unmute),
+    ("preview", "--preview", Mode::Preview),
+    ("doctor", "--doctor", Mode::Doctor),
+    ("simulate", "--simulate", Mode::Simulate),
+    ("config", "--config", Mode::Config),
+    ("replay", "--replay", Mode::Replay),
+    ("register-event-log", "--register-event-log", Mode::RegisterEventLog),
+    ("logs", "--logs", Mode::Logs),
+    ("stats", "--stats", Mode::Stats),
+    ("help", "--help", Mode::Help),
+    ("version", "--version", Mode::Version),
+];
+
+pub const HELP: &str = "\
+Usage: ToastWindow.exe <command> [options]
+
+Hook commands (read the hook's JSON on stdin):
+  save            Save window state (UserPromptSubmit hook)
+  notify          Show notification (Stop hook)
+  input           Show input-required notification (Notification hook)
+  cleanup         Delete the session's state (SessionEnd hook)
+
+Other commands:
+  show            Show a notification for --session (what notify and input start)
+  activate        Jump to the window and tab of --session
+  dismiss-all     Close all notifications
+  list-sessions   List sessions with saved state
+  history         Open the notification history
+  logs            Open a window following the --debug log, by session and level
+  stats           Tasks, durations and clicks per project and session (--since 7d)
+  mute [30m|2h]   Silence notifications, until unmute by default
+  unmute          Show notifications again
+  reset-position  Forget where toasts were dragged to
+  preview         Show sample toasts with the current config (--count N)
+  doctor          Check the setup and print a report for bug reports
+  simulate <stop|input|prompt>
+                  Run a hook with a made-up payload (--session, --prompt, --message)
+  config get <key>          Print a setting, e.g. config get toast.width
+  config set <key> <value>  Change a setting in config.toml
+  config open               Open config.toml in the default editor
+  replay <file>   Run the hooks again with payloads saved by --record
+  register-event-log
+                  Register the Event Log source, for readable events (run elevated)
+  tray            Run the tray agent (global hotkey)
+  help, version
+
+Each command also works as a flag: --save, --notify-show, --mute 2h, ...
+
+Options:
+  --session <id>         Session to show or activate
+  --title <text>         Toast title (show)
+  --message <text>       Toast message (show)
+  --icon <image>         Toast icon, an .ico or image file (show)
+  --sound <wav>          Sound to play (show)
+  --no-sound             Play no sound (show)
+  --sample               Not recorded in the history, shown even when muted (show)
+  --count <n>            Number of sample toasts (preview)
+  --prompt <text>        Prompt of the made-up payload (simulate)
+  --record               Append the hook's JSON to payloads.jsonl, for replay
+  --cwd <dir>            Project directory
+  --input-mode           Show as an input-required notification
+  --duration <ms>        Time on screen before fading
+  --persistent           Stay until clicked or closed
+  --json                 JSON output (list-sessions, history, doctor, stats)
+  --since <duration>     Only the last 30m, 12h, 7d, ... (stats)
+  --stdin                Read --session, --message and --cwd from hook JSON on stdin
+  --payload <json>       Hook JSON given inline instead of on stdin
+  --payload-file <path>  Hook JSON read from a file instead of stdin
+  -d, --debug            Append to debug.log (next to the executable, see [log]),
+                         and echo to the terminal when started from one
+  -h, --help             Show this help
+  -V, --version          Show the version";
+
+/// "ToastWindow 1.0.0 (commit, build date)", from build.rs.
+pub fn version() -> String {
+    format!(
+        "ToastWindow {} ({}, built {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("TOASTWINDOW_GIT_HASH"),
+        env!("TOASTWINDOW_BUILD_DATE")
+    )
+}
+
+#[derive(Debug, Default)]
 pub struct Args {
     pub mode: Mode,
     pub debug: bool,
     pub input_mode: bool,
     pub session: String,
     pub message: String,
+    pub cwd: String,
+    pub duration: Option<u32>,
+    pub persistent: bool,
+    /// Toast title instead of the one derived from the state.
+    pub title: Option<String>,
+    /// Image file for the toast icon.
+    pub icon: Option<String>,
+    /// WAV file to play instead of the discovered sounds.
+    pub sound: Option<String>,
+    pub no_sound: bool,
+    /// A preview toast: not recorded, shown even when muted.
+    pub sample: bool,
+    /// Toasts to show in preview mode.
+    pub count: u32,
+    /// Hook to simulate: "stop", "input" or "prompt".
+    pub simulate: String,
+    /// Prompt for a simulated UserPromptSubmit.
+    pub prompt: String,
+    /// Keep the payload for --replay.
+    pub record: bool,
+    /// File of recorded payloads to run again.
+    pub replay: String,
+    /// `config` action and its operands: ["get", key], ["set", key, value] or ["open"].
+    pub config: Vec<String>,
+    /// Machine-readable output for query commands.
+    pub json: bool,
+    /// Period --stats covers, back from now; None for everything.
+    pub since: Option<std::time::Duration>,
+    /// --mute duration, e.g. "30m"; None mutes until --unmute.
+    pub mute_for: Option<String>,
+    /// Read session, message and cwd from the hook JSON on stdin.
+    pub stdin: bool,
+    /// Hook JSON given inline instead of on stdin.
+    pub payload: Option<String>,
+    /// File holding the hook JSON, instead of stdin.
+    pub payload_file: Option<String>,
+    /// The payload is a Stop hook for a turn a Stop hook continued.
+    pub hook_continued: bool,
+    /// Session transcript (JSONL) named by the payload.
+    pub transcript_path: String,
+    /// Usage and cost of the turn given by the payload.
+    pub usage: Option<crate::transcript::Usage>,
+    pub cost_usd: Option<f64>,
+}
+
+impl Args {
+    /// The hook JSON: from --payload, --payload-file, or else stdin.
+    /// With --record it's kept for --replay.
+    pub fn read_payload(&self) -> String {
+        let payload = if let Some(json) = &self.payload {
+            json.clone()
+        } else if let Some(path) = &self.payload_file {
+            std::fs::read_to_string(path).unwrap_or_else(|e| {
+                crate::debug_log!("Can't read payload file {}: {}", path, e);
+                String::new()
+            })
+        } else {
+            crate::json::read_stdin_json()
+        };
+        if self.record {
+            crate::record::append(command_name(self.mode), &payload);
+        }
+        payload
+    }
+
+    /// With --stdin, --payload or --payload-file, fill in session, message
+    /// and cwd from that hook JSON; flags given on the command line win.
+    pub fn load_payload(&mut self) {
+        if self.stdin || self.payload.is_some() || self.payload_file.is_some() {
+            let input = self.read_payload();
+            self.apply_payload(&input);
+        }
+    }
+
+    /// Fill in session, message, cwd and transcript from hook JSON, where
+    /// not set yet.
+    pub fn apply_payload(&mut self, input: &str) {
+        let payload = crate::payload::Payload::parse(input, "");
+        for (field, value) in [
+            (&mut self.session, &payload.common.session_id),
+            (&mut self.message, &payload.message().to_string()),
+            (&mut self.cwd, &payload.common.cwd),
+            (&mut self.transcript_path, &payload.common.transcript_path),
+        ] {
+            if field.is_empty() {
+                value.clone_into(field);
+            }
+        }
+        self.hook_continued = payload.stop_hook_active();
+        self.usage = self.usage.or(payload.common.usage);
+        self.cost_usd = self.cost_usd.or(payload.common.cost_usd);
+    }
+}
+
+/// Subcommand name of a mode ("notify"), empty for none.
+pub fn command_name(mode: Mode) -> &'static str {
+    COMMANDS.iter().find(|(_, _, m)| *m == mode).map_or("", |(name, _, _)| name)
+}
+
+/// Mode of a subcommand name.
+pub fn command_mode(name: &str) -> Option<Mode> {
+    COMMANDS.iter().find(|(sub, _, _)| *sub == name).map(|(_, _, mode)| *mode)
+}
+
+/// Parse the command line. Errors say what was wrong, for printing.
+pub fn parse_args() -> Result<Args, String> {
+    parse(std::env::args().skip(1))
+}
+
+/// The value of option `name`: after its `=`, else the next argument.
+fn value(name: &str, inline: &mut Option<String>, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    inline.take().or_else(|| args.next()).ok_or_else(|| format!("{} needs a value", name))
 }
 
-pub fn parse_args() -> Args {
-    let args: Vec<String> = std::env::args().collect();
-    let mut result = Args {
-        mode: Mode::None,
-        debug: false,
-        input_mode: false,
-        session: String::new(),
-        message: String::new(),
-    };
-
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--save" => result.mode = Mode::Save,
-            "--notify" => result.mode = Mode::Notify,
-            "--input" => result.mode = Mode::Input,
-            "--notify-show" => result.mode = Mode::NotifyShow,
-            "--cleanup" => result.mode = Mode::Cleanup,
-            "--debug" | "-d" => result.debug = true,
-            "--input-mode" => result.input_mode = true,
-            "--session" => {
-                i += 1;
-                if i < args.len() {
-                    result.session = args[i].clone();
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut result = Args::default();
+    let mut args = args.into_iter().peekable();
+
+    while let Some(arg) = args.next() {
+        // --name=value
+        let (name, mut inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let name = match name.as_str() {
+            "-h" => "--help".to_string(),
+            "-V" => "--version".to_string(),
+            _ => name,
+        };
+        let command = COMMANDS.iter().find(|(sub, flag, _)| {
+            name == *flag || (*sub == name && result.mode == Mode::None && inline.is_none())
+        });
+        if let Some(&(_, _, mode)) = command {
+            match (result.mode, mode) {
+                // Help and version win over any command they come with
+                (_, Mode::Help | Mode::Version) | (Mode::None, _) => result.mode = mode,
+                (Mode::Help | Mode::Version, _) => {}
+                (current, _) if current == mode => {}
+                _ => return Err(format!("{} can't be combined with another command", name)),
+            }
+            if mode == Mode::Mute {
+                // Optional duration, unless the next argument is an option
+                result.mute_for = match inline.take() {
+                    Some(duration) => Some(duration),
+                    None => args.next_if(|a| !a.starts_with('-')),
+                };
+            }
+            if mode == Mode::Config {
+                let action = value(&name, &mut inline, &mut args)?;
+                let operands = match action.as_str() {
+                    "get" => ["<key>"].as_slice(),
+                    "set" => ["<key>", "<value>"].as_slice(),
+                    "open" => [].as_slice(),
+                    _ => return Err(format!("{} takes get, set or open, not {:?}", name, action)),
+                };
+                result.config = vec![action];
+                for operand in operands {
+                    let next = args.next().ok_or_else(|| format!("config {} needs {}", result.config[0], operand))?;
+                    result.config.push(next);
                 }
             }
-            "--message" => {
-                i += 1;
-                if i < args.len() {
-                    result.message = args[i].clone();
+            if mode == Mode::Replay {
+                result.replay = value(&name, &mut inline, &mut args)?;
+            }
+            if mode == Mode::Simulate {
+                result.simulate = value(&name, &mut inline, &mut args)?;
+                if !["stop", "input", "prompt"].contains(&result.simulate.as_str()) {
+                    return Err(format!("{} takes stop, input or prompt, not {:?}", name, result.simulate));
                 }
             }
-            _ => {}
+        } else {
+            match name.as_str() {
+                "--json" => result.json = true,
+                "--stdin" => result.stdin = true,
+                "--record" => result.record = true,
+                "--debug" | "-d" => result.debug = true,
+                "--input-mode" => result.input_mode = true,
+                "--persistent" => result.persistent = true,
+                "--no-sound" => result.no_sound = true,
+                "--sample" => result.sample = true,
+                "--title" => result.title = Some(value(&name, &mut inline, &mut args)?),
+                "--icon" => result.icon = Some(value(&name, &mut inline, &mut args)?),
+                "--sound" => result.sound = Some(value(&name, &mut inline, &mut args)?),
+                "--session" => result.session = value(&name, &mut inline, &mut args)?,
+                "--message" => result.message = value(&name, &mut inline, &mut args)?,
+                "--cwd" => result.cwd = value(&name, &mut inline, &mut args)?,
+                "--prompt" => result.prompt = value(&name, &mut inline, &mut args)?,
+                "--payload" => result.payload = Some(value(&name, &mut inline, &mut args)?),
+                "--payload-file" => result.payload_file = Some(value(&name, &mut inline, &mut args)?),
+                "--duration" => {
+                    let text = value(&name, &mut inline, &mut args)?;
+                    let ms = text.parse().map_err(|_| format!("--duration takes milliseconds, not {:?}", text))?;
+                    result.duration = Some(ms);
+                }
+                "--since" => {
+                    let text = value(&name, &mut inline, &mut args)?;
+                    let since = crate::mute::parse_duration(&text)
+                        .ok_or_else(|| format!("--since takes a duration like 7d or 12h, not {:?}", text))?;
+                    result.since = Some(since);
+                }
+                "--count" => {
+                    let text = value(&name, &mut inline, &mut args)?;
+                    result.count = text
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("--count takes a number of toasts, not {:?}", text))?;
+                }
+                _ if name.starts_with('-') => return Err(format!("Unknown option {}", name)),
+                _ if result.mode == Mode::None => return Err(format!("Unknown command {:?}", name)),
+                _ => return Err(format!("Unexpected argument {:?}", name)),
+            }
+        }
+        if let Some(value) = inline {
+            return Err(format!("{} doesn't take a value ({:?})", name, value));
         }
-        i += 1;
     }
 
-    result
+    Ok(result)
 }
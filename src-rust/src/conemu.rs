@@ -0,0 +1,73 @@
+//! ConEmu console switching via GuiMacro.
+//!
+//! Every ConEmu console runs its own server process (ConEmuC), whose PID the
+//! console's environment carries in `ConEmuServerPID`. That PID is saved as
+//! the console's identity; on activation a GuiMacro addressed to it
+//! (`ConEmuC -GuiMacro:<pid>`) runs in that console's context and activates
+//! its tab.
+
+use std::path::PathBuf;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+/// Activate the console the macro runs in (console number 0: the context one).
+const SELECT_MACRO: &str = "Tab(7,0)";
+
+/// Server PID of the ConEmu console we run in, or empty outside ConEmu.
+pub fn current_console() -> String {
+    if std::env::var_os("ConEmuPID").is_none() {
+        return String::new();
+    }
+    std::env::var("ConEmuServerPID")
+        .ok()
+        .filter(|pid| is_pid(pid))
+        .unwrap_or_default()
+}
+
+/// The PID goes into ConEmuC's command line, so nothing else may.
+fn is_pid(text: &str) -> bool {
+    text.parse::<u32>().is_ok()
+}
+
+/// ConEmuC next to the ConEmu.exe that owns `hwnd` (in its `ConEmu`
+/// subfolder), or in `ConEmuBaseDir` if we run inside ConEmu ourselves.
+fn conemuc_path(hwnd: HWND) -> Option<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(base) = std::env::var_os("ConEmuBaseDir") {
+        dirs.push(PathBuf::from(base));
+    }
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    let exe = crate::process::get_process_exe_path(pid);
+    if let Some(dir) = std::path::Path::new(&exe).parent() {
+        dirs.push(dir.join("ConEmu"));
+        dirs.push(dir.to_path_buf());
+    }
+    dirs.iter()
+        .flat_map(|dir| [dir.join("ConEmuC64.exe"), dir.join("ConEmuC.exe")])
+        .find(|path| path.is_file())
+}
+
+/// Switch the ConEmu window `hwnd` to the console with server PID `console`.
+/// Returns true if ConEmuC ran the macro.
+pub fn select_console(hwnd: HWND, console: &str) -> bool {
+    if !is_pid(console) {
+        crate::debug_log!("Invalid ConEmu console {:?}", console);
+        return false;
+    }
+    let Some(conemuc) = conemuc_path(hwnd) else {
+        crate::debug_log!("ConEmuC not found");
+        return false;
+    };
+    let cmd = format!(
+        "{} -GuiMacro:{} {}",
+        crate::spawn::quote_arg(&conemuc.to_string_lossy()),
+        console,
+        SELECT_MACRO
+    );
+    // Exit code 0 when the macro ran; the console may be gone
+    let code = crate::spawn::run_hidden(&cmd, 2000);
+    crate::debug_log!("GuiMacro {} on console {}: {:?}", SELECT_MACRO, console, code);
+    code == Some(0)
+}
@@ -0,0 +1,771 @@
+//! User configuration.
+//!
+//! Loaded once per process from config.toml. Lookup order:
+//! %APPDATA%\claude-code-notify\config.toml, then <exe_dir>\config.toml.
+//! A missing file or missing fields fall back to the defaults below.
+//! The tray agent reloads it when it changes and tells the open toasts to.
+//! `config get/set/open` read and edit it from the command line.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub toast: ToastConfig,
+    pub events: EventsConfig,
+    pub mouse: MouseConfig,
+    pub tray: TrayConfig,
+    pub activation: ActivationConfig,
+    pub speech: SpeechConfig,
+    pub sound: SoundConfig,
+    pub assets: AssetsConfig,
+    pub fonts: FontsConfig,
+    pub caller: CallerConfig,
+    pub state: StateConfig,
+    pub privacy: PrivacyConfig,
+    pub log: LogConfig,
+    /// `[projects."C:/path/to/repo"]`: settings for one project directory
+    /// and everything below it.
+    pub projects: HashMap<String, ProjectConfig>,
+}
+
+impl Config {
+    /// How long a toast stays up before fading, for the given event kind.
+    pub fn display_ms(&self, input_mode: bool) -> u32 {
+        self.events.get(input_mode).duration_ms.unwrap_or(self.toast.duration_ms)
+    }
+
+    /// Whether to speak an announcement for the given event kind.
+    pub fn speaks(&self, input_mode: bool) -> bool {
+        self.events.get(input_mode).speak.unwrap_or(self.speech.enabled)
+    }
+
+    /// Settings for the project containing `cwd`, with the key they were
+    /// found under. The most specific path wins.
+    pub fn project(&self, cwd: &str) -> Option<(&str, &ProjectConfig)> {
+        let normalize = |path: &str| path.replace('/', "\\").trim_end_matches('\\').to_lowercase();
+        let cwd = normalize(cwd);
+        self.projects
+            .iter()
+            .filter(|(dir, _)| {
+                let dir = normalize(dir);
+                !dir.is_empty() && (cwd == dir || cwd.starts_with(&format!("{}\\", dir)))
+            })
+            .max_by_key(|(dir, _)| dir.len())
+            .map(|(dir, project)| (dir.as_str(), project))
+    }
+}
+
+/// `[toast]` section: window geometry and text layout.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ToastConfig {
+    pub width: i32,
+    pub height: i32,
+    /// Grow the window to fit the message text, up to `max_height`.
+    pub auto_height: bool,
+    pub max_height: i32,
+    /// Maximum number of wrapped message lines before ellipsizing.
+    pub max_lines: i32,
+    /// Draw a bar along the bottom edge showing the time left before fading.
+    pub countdown_bar: bool,
+    /// Time shown before the fade starts, unless overridden per event.
+    pub duration_ms: u32,
+    /// Screen corner the toasts stack from.
+    pub position: Position,
+    /// Distance from the screen edges, on both axes.
+    pub edge_gap: i32,
+    /// Distance from the screen edge on the side of an auto-hidden taskbar.
+    pub autohide_inset: i32,
+    /// Extra distance from the left/right edge of the work area.
+    pub offset_x: i32,
+    /// Extra distance from the top/bottom edge of the work area.
+    pub offset_y: i32,
+    /// Space between stacked toasts.
+    pub gap: i32,
+    /// Monitor to show toasts on.
+    pub monitor: MonitorSelect,
+    /// Direction in which multiple toasts stack.
+    pub stack: StackAxis,
+    /// Open toasts where one was last dragged to, instead of `position`.
+    pub remember_position: bool,
+    /// Take the keyboard focus when shown: Enter activates, Esc dismisses,
+    /// Tab moves between the body, details chevron, and close button.
+    pub keyboard: bool,
+    /// How long the toast keeps the focus before handing it back, unless a key is pressed.
+    pub keyboard_focus_ms: u32,
+    /// Image (.ico, .png, .jpg) shown instead of the calling app's icon.
+    pub icon: String,
+    /// Pulse the border of input-required toasts so they stand out in a stack.
+    pub pulse: bool,
+    /// Most toast processes running at once; more notifications are dropped.
+    /// 0 for no limit.
+    pub max_processes: u32,
+    /// Show the tokens (and cost, when known) of the turn under the message
+    /// of completion toasts.
+    pub usage_footer: bool,
+    /// Longest excerpt of a reply or prompt, in console columns (CJK and
+    /// emoji count as two).
+    pub excerpt_columns: usize,
+}
+
+/// `stack` values: toasts on top of each other, or side by side along the edge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackAxis {
+    #[default]
+    Vertical,
+    Horizontal,
+}
+
+/// `monitor` values: "cursor", "primary", or a display number (1, 2, ...).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MonitorSelect {
+    Index(u32),
+    Named(NamedMonitor),
+}
+
+impl Default for MonitorSelect {
+    fn default() -> Self {
+        MonitorSelect::Named(NamedMonitor::Cursor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamedMonitor {
+    /// The monitor under the mouse cursor.
+    Cursor,
+    Primary,
+}
+
+/// `position` values. `auto` follows the taskbar (bottom-right for a bottom taskbar).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Position {
+    #[default]
+    Auto,
+    BottomRight,
+    TopRight,
+    BottomLeft,
+    TopLeft,
+}
+
+impl Default for ToastConfig {
+    fn default() -> Self {
+        Self {
+            width: 300,
+            height: 80,
+            auto_height: false,
+            max_height: 240,
+            max_lines: 3,
+            countdown_bar: true,
+            duration_ms: 3000,
+            position: Position::Auto,
+            edge_gap: 0,
+            autohide_inset: 0,
+            offset_x: 0,
+            offset_y: 0,
+            gap: 0,
+            monitor: MonitorSelect::default(),
+            stack: StackAxis::Vertical,
+            remember_position: true,
+            keyboard: false,
+            keyboard_focus_ms: 4000,
+            icon: String::new(),
+            pulse: true,
+            max_processes: 16,
+            usage_footer: true,
+            excerpt_columns: 300,
+        }
+    }
+}
+
+/// `[mouse]` section: what each click on the toast body does.
+/// The close button and the details chevron always work the same way.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MouseConfig {
+    pub left: MouseAction,
+    pub right: MouseAction,
+    pub middle: MouseAction,
+    /// Left double-click. When set, single left clicks wait out the
+    /// double-click time before acting.
+    pub double: MouseAction,
+    /// How long `snooze` hides the toast before showing it again.
+    pub snooze_minutes: u32,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        Self {
+            left: MouseAction::Activate,
+            right: MouseAction::Dismiss,
+            middle: MouseAction::Copy,
+            double: MouseAction::None,
+            snooze_minutes: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseAction {
+    /// Jump back to the Claude Code window and tab.
+    Activate,
+    /// Close the toast.
+    Dismiss,
+    /// Copy the full prompt and message to the clipboard, then close.
+    Copy,
+    /// Hide the toast for `snooze_minutes`, then show it again.
+    Snooze,
+    /// Open the notification history.
+    History,
+    None,
+}
+
+/// `[tray]` section: the resident agent that owns the tray icon and hotkeys.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TrayConfig {
+    /// Start the agent automatically from the hooks.
+    pub enabled: bool,
+    /// Global hotkey that jumps to the last notified session, e.g. "Win+Shift+C".
+    /// Empty to disable.
+    pub hotkey: String,
+    /// Show how many sessions are waiting for input on the tray icon.
+    pub badge: bool,
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "Win+Shift+C".to_string(),
+            badge: true,
+        }
+    }
+}
+
+/// `[activation]` section: how a click brings the terminal to the front.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ActivationConfig {
+    /// Bring the window to the front, or only flash its taskbar button.
+    pub mode: ActivationMode,
+    /// Flash the taskbar button this many times when a toast appears; 0 for none.
+    pub notify_flashes: u32,
+    /// Send a synthetic ALT press when Windows refuses the foreground change.
+    pub alt_trick: bool,
+    /// Stronger strategies to try when the window doesn't come to the front.
+    pub retries: u32,
+    /// Move the window onto the monitor where the toast was clicked.
+    pub follow_monitor: bool,
+    /// How to bring back a hidden quake-mode Windows Terminal window.
+    pub quake: QuakeMode,
+    /// WT's global summon hotkey for the quake window, pressed by `quake = "hotkey"`.
+    pub quake_hotkey: String,
+    /// What to do when the window is on another virtual desktop.
+    pub virtual_desktop: DesktopMode,
+}
+
+impl Default for ActivationConfig {
+    fn default() -> Self {
+        Self {
+            mode: ActivationMode::Focus,
+            notify_flashes: 0,
+            alt_trick: true,
+            retries: 3,
+            follow_monitor: false,
+            quake: QuakeMode::Hotkey,
+            quake_hotkey: "Win+`".to_string(),
+            virtual_desktop: DesktopMode::Switch,
+        }
+    }
+}
+
+/// `[activation] mode` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivationMode {
+    #[default]
+    Focus,
+    /// Flash the taskbar button until the user switches to the window;
+    /// for setups where taking the focus is unwelcome.
+    Flash,
+}
+
+/// `quake` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuakeMode {
+    /// Press WT's summon hotkey, so WT slides the window in itself.
+    #[default]
+    Hotkey,
+    /// Show the hidden window directly; for when the hotkey is unbound.
+    Show,
+}
+
+/// `virtual_desktop` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DesktopMode {
+    /// Switch to the window's desktop.
+    #[default]
+    Switch,
+    /// Move the window to the current desktop; switches when Windows refuses.
+    Move,
+}
+
+/// `[assets]` section: where sounds, fonts, and icons come from.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AssetsConfig {
+    /// Folder with sound\, fonts\, and img\ subfolders, searched before
+    /// %APPDATA%\claude-code-notify\assets and the assets folder next to the exe.
+    pub dir: String,
+}
+
+/// `[fonts]` section: font families to try in order, by family name. Each
+/// run of characters uses the first family that has them; empty lists use
+/// the fonts in assets\fonts, in file name order. The system fallback (CJK,
+/// symbol, and emoji fonts) is tried after the list either way.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FontsConfig {
+    pub title: Vec<String>,
+    /// Message and details text.
+    pub body: Vec<String>,
+}
+
+/// `[caller]` section: changes to the built-in process lists used to find
+/// the app a session runs in (for its icon). Names are exe names without
+/// `.exe`, case-insensitive.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CallerConfig {
+    /// Shells and runtimes to look past, e.g. `["nu", "xonsh"]`.
+    pub skip_add: Vec<String>,
+    pub skip_remove: Vec<String>,
+    /// Apps that end the search (also matching "name-suffix").
+    pub apps_add: Vec<String>,
+    pub apps_remove: Vec<String>,
+}
+
+/// `[state]` section: the per-session state files.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StateConfig {
+    /// Folder of the database (notify.db); empty for
+    /// %LOCALAPPDATA%\claude-code-notify.
+    pub dir: String,
+    /// Delete session state not updated for this many hours; 0 keeps it.
+    pub ttl_hours: u64,
+    /// How many of a session's latest prompts to keep, the current one
+    /// included.
+    pub prompts: usize,
+    /// Delete history and stats older than this many days; 0 keeps them.
+    pub keep_days: u64,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            dir: String::new(),
+            ttl_hours: 72,
+            prompts: 3,
+            keep_days: 90,
+        }
+    }
+}
+
+/// `[log]` section: the --debug log files.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Folder for the log files; empty for the exe's folder.
+    pub dir: String,
+    pub rotate: LogRotate,
+    /// Size in KB at which debug.log is rotated (rotate = "size").
+    pub max_kb: u64,
+    /// Rotated files, or days, kept besides the current one.
+    pub keep: usize,
+    /// Least important level written.
+    pub level: LogLevel,
+    pub format: LogFormat,
+    /// Also send the lines to OutputDebugString (DebugView), even without --debug.
+    pub debugger: bool,
+    /// Report warnings and errors to the Windows Event Log.
+    pub event_log: bool,
+    /// Log each session's processes to their own debug-{session}-{mode}.log.
+    pub per_session: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            dir: String::new(),
+            rotate: LogRotate::Size,
+            max_kb: 1024,
+            keep: 5,
+            level: LogLevel::Debug,
+            format: LogFormat::Text,
+            debugger: false,
+            event_log: true,
+            per_session: true,
+        }
+    }
+}
+
+/// `rotate` values: debug.log moves to debug.1.log when it's full, or
+/// each day has its own debug-YYYY-MM-DD.log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotate {
+    #[default]
+    Size,
+    Daily,
+}
+
+/// Log levels, most important first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    #[default]
+    Debug,
+}
+
+/// `format` values: a line of text, or a JSON object per line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// `[privacy]` section.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    pub prompt: PromptPrivacy,
+}
+
+/// `[privacy] prompt` values: what happens to the prompt text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptPrivacy {
+    /// Saved as-is and shown on completion toasts.
+    #[default]
+    Show,
+    /// Saved encrypted for the current Windows user (DPAPI), and shown.
+    Encrypt,
+    /// Never saved, logged, or shown; completion toasts say "Task completed".
+    Hide,
+}
+
+/// `[sound]` section: the notification sound.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SoundConfig {
+    /// 0-100; 0 plays nothing.
+    pub volume: u8,
+    /// Which file to play when assets\sound holds several.
+    pub pick: SoundPick,
+    /// Fixed seed for `random`, making the choice repeatable.
+    pub seed: Option<u64>,
+    /// Output device: "" for the default, "communications" for the default
+    /// communications device, or part of a device name.
+    pub device: String,
+    /// Stay silent while the communications microphone is in use (calls, meetings).
+    pub skip_during_calls: bool,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        Self {
+            volume: 100,
+            pick: SoundPick::First,
+            seed: None,
+            device: String::new(),
+            skip_during_calls: false,
+        }
+    }
+}
+
+/// `pick` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SoundPick {
+    /// The first file by name.
+    #[default]
+    First,
+    /// A different file at random each time.
+    Random,
+    /// Each file in turn, by name.
+    Rotate,
+}
+
+/// `[speech]` section: spoken announcements through the Windows speech engine.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpeechConfig {
+    /// Speak with every toast, unless an event overrides it.
+    pub enabled: bool,
+    /// Speaking rate from -10 (slowest) to 10 (fastest).
+    pub rate: i32,
+    /// 0-100.
+    pub volume: u16,
+    /// Local time range without speech, e.g. "22:00-07:00". Empty for none.
+    pub quiet_hours: String,
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: 0,
+            volume: 100,
+            quiet_hours: String::new(),
+        }
+    }
+}
+
+/// `[events.completion]` and `[events.input]`: per-event overrides.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Stop hook: the task finished.
+    pub completion: EventConfig,
+    /// Notification hook: Claude is waiting for input.
+    pub input: EventConfig,
+}
+
+impl EventsConfig {
+    pub fn get(&self, input_mode: bool) -> &EventConfig {
+        if input_mode { &self.input } else { &self.completion }
+    }
+}
+
+/// Settings for one event kind. Unset fields fall back to the `[toast]` values.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EventConfig {
+    pub duration_ms: Option<u32>,
+    /// Never fade; stay until clicked or closed.
+    pub persistent: bool,
+    /// Speak an announcement; unset follows `[speech] enabled`.
+    pub speak: Option<bool>,
+    /// Play the sound again at this interval until the toast is clicked or
+    /// closed; 0 plays it once. Meant for `[events.input]`.
+    pub repeat_sound_secs: u32,
+    /// Keys typed into the window after a click brings it to the front,
+    /// e.g. "1<Enter>" to pick the first choice. Empty for none.
+    pub keys: String,
+    /// What to do when the Stop hook fires for a turn that a Stop hook made
+    /// Claude continue (`stop_hook_active`). Meant for `[events.completion]`.
+    pub hook_continue: HookContinue,
+}
+
+/// `hook_continue` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookContinue {
+    /// No toast, so a hook-driven retry loop doesn't repeat it.
+    #[default]
+    Skip,
+    /// A toast with "continued" in the title.
+    Mark,
+    /// A toast like any other.
+    Show,
+}
+
+/// `[projects."<dir>"]`: per-project settings.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Image shown for this project's toasts; relative paths are resolved
+    /// against the project directory.
+    pub icon: String,
+}
+
+/// The config in effect. One replaced by a reload is freed once the last
+/// `get` holding it is dropped.
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// Directory for per-user files: %APPDATA%\claude-code-notify.
+pub fn user_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("APPDATA")
+        .map(|appdata| std::path::PathBuf::from(appdata).join("claude-code-notify"))
+}
+
+/// Directory for machine-specific data: %LOCALAPPDATA%\claude-code-notify.
+pub fn local_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("LOCALAPPDATA")
+        .map(|local| std::path::PathBuf::from(local).join("claude-code-notify"))
+}
+
+/// Path of the config file in effect: the user config if it exists,
+/// otherwise the one next to the exe.
+pub fn config_path() -> std::path::PathBuf {
+    if let Some(user) = user_dir().map(|d| d.join("config.toml")) {
+        if user.exists() {
+            return user;
+        }
+    }
+    std::path::PathBuf::from(crate::assets::exe_dir()).join("config.toml")
+}
+
+/// Read the config file: Ok(None) if there is none.
+fn load() -> Result<Option<Config>, String> {
+    let path = config_path();
+    let Ok(text) = std::fs::read_to_string(&path) else { return Ok(None) };
+    match toml::from_str::<Config>(&text) {
+        Ok(c) => {
+            crate::debug_log!("Loaded config from {:?}", path);
+            Ok(Some(c))
+        }
+        Err(e) => Err(format!("Invalid config {:?}: {}", path, e)),
+    }
+}
+
+fn store(config: Config) {
+    if let Ok(mut current) = CONFIG.write() {
+        *current = Some(Arc::new(config));
+    }
+}
+
+/// Load the config file. Call once at startup, after log::init and
+/// before log::open.
+pub fn init() {
+    let config = load().unwrap_or_else(|e| {
+        crate::warn_log!("{}", e);
+        None
+    });
+    store(config.unwrap_or_default());
+}
+
+/// Load the config file again after it changed. An invalid file (say, saved
+/// halfway through an edit) keeps the settings in effect.
+pub fn reload() {
+    match load() {
+        Ok(config) => store(config.unwrap_or_default()),
+        Err(e) => crate::warn_log!("{}, keeping the previous settings", e),
+    }
+}
+
+/// Get the loaded config (defaults if init was not called).
+pub fn get() -> Arc<Config> {
+    if let Some(config) = CONFIG.read().ok().and_then(|c| c.clone()) {
+        return config;
+    }
+    match CONFIG.write() {
+        Ok(mut current) => current.get_or_insert_with(Default::default).clone(),
+        Err(_) => Arc::default(),
+    }
+}
+
+/// Split a dotted key (`toast.width`, `projects."C:/repo".icon`).
+fn parse_key(key: &str) -> Result<Vec<toml_edit::Key>, String> {
+    toml_edit::Key::parse(key).map_err(|_| format!("Invalid key {:?}", key))
+}
+
+fn find<'a>(value: &'a toml::Value, keys: &[toml_edit::Key]) -> Option<&'a toml::Value> {
+    keys.iter().try_fold(value, |value, key| value.get(key.get()))
+}
+
+/// The setting at a dotted key, with the defaults filled in: a string as
+/// is, anything else as TOML.
+pub fn lookup(key: &str) -> Result<String, String> {
+    let keys = parse_key(key)?;
+    let settings = toml::Value::try_from(&*get()).map_err(|e| e.to_string())?;
+    match find(&settings, &keys) {
+        Some(toml::Value::String(text)) => Ok(text.clone()),
+        Some(toml::Value::Table(table)) => toml::to_string(table).map_err(|e| e.to_string()),
+        Some(value) => Ok(value.to_string()),
+        None => Err(format!("Unknown or unset setting {}", key)),
+    }
+}
+
+/// File `config set` writes: the one in effect if it exists, else the user config.
+fn writable_path() -> std::path::PathBuf {
+    let path = config_path();
+    if path.exists() {
+        return path;
+    }
+    user_dir().map(|d| d.join("config.toml")).unwrap_or(path)
+}
+
+/// Set a dotted key in the config file, keeping the rest of it (comments
+/// included). `value` is TOML (`300`, `true`, `"text"`), else taken as a
+/// string. Nothing is written unless the result is a valid config that
+/// has the key. Returns the file written.
+pub fn set(key: &str, value: &str) -> Result<std::path::PathBuf, String> {
+    let keys = parse_key(key)?;
+    let path = writable_path();
+    let text = std::fs::read_to_string(&path).unwrap_or_default();
+    let mut doc = text
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| format!("{} can't be read: {}", path.display(), e))?;
+
+    let mut value = value.parse::<toml_edit::Value>().unwrap_or_else(|_| toml_edit::Value::from(value));
+    let (last, parents) = keys.split_last().ok_or_else(|| "Empty key".to_string())?;
+    let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+    for key in parents {
+        let mut new = toml_edit::Table::new();
+        new.set_implicit(true);
+        table = table
+            .entry(key.get())
+            .or_insert(toml_edit::Item::Table(new))
+            .as_table_like_mut()
+            .ok_or_else(|| format!("{} is not a section", key.get()))?;
+    }
+    // Keep the comment after a value that's replaced
+    if let Some(old) = table.get(last.get()).and_then(toml_edit::Item::as_value) {
+        *value.decor_mut() = old.decor().clone();
+    }
+    table.insert(last.get(), toml_edit::value(value));
+
+    let text = doc.to_string();
+    let config: Config = toml::from_str(&text).map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+    let settings = toml::Value::try_from(&config).map_err(|e| e.to_string())?;
+    if find(&settings, &keys).is_none() {
+        return Err(format!("Unknown setting {}", key));
+    }
+
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    std::fs::write(&path, text).map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Open the config file in the default editor, creating it if needed.
+pub fn open() {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::{AllowSetForegroundWindow, ASFW_ANY, SW_SHOWNORMAL};
+
+    let path = writable_path();
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(&path, "# claude-code-notify settings, see the README for the keys\n") {
+            crate::warn_log!("Failed to create config {:?}: {}", path, e);
+            return;
+        }
+    }
+    let file = crate::util::encode_wide(&path.to_string_lossy());
+    unsafe {
+        let _ = AllowSetForegroundWindow(ASFW_ANY);
+        ShellExecuteW(None, w!("open"), PCWSTR(file.as_ptr()), None, None, SW_SHOWNORMAL);
+    }
+}
@@ -0,0 +1,119 @@
+//! Crash reports.
+//!
+//! ToastWindow runs in the GUI subsystem from hooks, so a crash leaves no
+//! trace anywhere. An unhandled exception filter and a panic hook write two
+//! files to the log folder (`[log] dir`, next to the exe by default):
+//! crash-{time}-{pid}.dmp, a minidump to open in a debugger, and
+//! crash-{time}-{pid}.log, what happened followed by the last lines logged,
+//! which are kept in memory even without --debug.
+
+use std::sync::OnceLock;
+
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::Storage::FileSystem::*;
+use windows::Win32::System::Diagnostics::Debug::*;
+use windows::Win32::System::Threading::{GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId};
+
+/// Where the reports go, settled at install (the config may be unusable by
+/// the time of a crash).
+static DIR: OnceLock<std::path::PathBuf> = OnceLock::new();
+
+/// Install the handlers. Call once the config is loaded.
+pub fn install() {
+    let _ = DIR.set(crate::log::configured_dir());
+    unsafe {
+        SetUnhandledExceptionFilter(Some(on_exception));
+    }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        report(&format!("Panic: {}", info), None);
+        default_hook(info);
+    }));
+}
+
+unsafe extern "system" fn on_exception(info: *const EXCEPTION_POINTERS) -> i32 {
+    let what = match unsafe { info.as_ref().and_then(|i| i.ExceptionRecord.as_ref()) } {
+        Some(record) => format!(
+            "Unhandled exception 0x{:08X} at {:?}",
+            record.ExceptionCode.0 as u32, record.ExceptionAddress
+        ),
+        None => "Unhandled exception".to_string(),
+    };
+    report(&what, Some(info));
+    // Let Windows Error Reporting see it too
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+/// Write the minidump and the log tail. Runs at most once.
+fn report(what: &str, exception: Option<*const EXCEPTION_POINTERS>) {
+    static REPORTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+    if REPORTED.swap(true, std::sync::atomic::Ordering::SeqCst) {
+        return;
+    }
+    let Some(dir) = DIR.get() else { return };
+    let _ = std::fs::create_dir_all(dir);
+    let base = dir.join(format!("crash-{}-{}", timestamp(), std::process::id()));
+
+    let dump_path = base.with_extension("dmp");
+    let dumped = write_dump(&dump_path, exception);
+
+    let mut text = format!("{}\n{}\n", crate::cli::version(), what);
+    match &dumped {
+        Ok(()) => text.push_str(&format!("Minidump: {}\n", dump_path.display())),
+        Err(e) => text.push_str(&format!("No minidump: {}\n", e)),
+    }
+    text.push_str("\nLast log lines:\n");
+    for line in crate::log::recent() {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    let _ = std::fs::write(base.with_extension("log"), text);
+}
+
+fn write_dump(path: &std::path::Path, exception: Option<*const EXCEPTION_POINTERS>) -> Result<(), String> {
+    let wide = crate::util::encode_wide(&path.to_string_lossy());
+    let file = unsafe {
+        CreateFileW(
+            windows::core::PCWSTR(wide.as_ptr()),
+            FILE_GENERIC_WRITE.0,
+            FILE_SHARE_NONE,
+            None,
+            CREATE_ALWAYS,
+            FILE_ATTRIBUTE_NORMAL,
+            None,
+        )
+    }
+    .map_err(|e| format!("can't create {}: {:?}", path.display(), e))?;
+    let info = exception.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: unsafe { GetCurrentThreadId() },
+        ExceptionPointers: pointers as *mut _,
+        ClientPointers: false.into(),
+    });
+    let written = unsafe {
+        MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            file,
+            MiniDumpWithThreadInfo | MiniDumpWithIndirectlyReferencedMemory,
+            info.as_ref().map(|i| i as *const _),
+            None,
+            None,
+        )
+    };
+    unsafe {
+        let _ = CloseHandle(file);
+    }
+    written.map_err(|e| {
+        let _ = std::fs::remove_file(path);
+        format!("MiniDumpWriteDump failed: {:?}", e)
+    })
+}
+
+/// Local time as YYYYMMDD-HHMMSS, for file names.
+fn timestamp() -> String {
+    let now = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        now.wYear, now.wMonth, now.wDay, now.wHour, now.wMinute, now.wSecond
+    )
+}
@@ -0,0 +1,95 @@
+//! Virtual desktops (IVirtualDesktopManager).
+//!
+//! Activating a window that lives on another virtual desktop does nothing
+//! visible, so before the activation sequence the window is brought into
+//! view: either by switching to its desktop, or by moving it to the current
+//! one (`[activation] virtual_desktop`).
+//!
+//! The public API can't switch desktops, and only moves windows of the
+//! calling process. Both are done with a helper window of our own: placed on
+//! the target's desktop and activated, it makes the shell switch there.
+
+use windows::core::*;
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_ALL};
+use windows::Win32::UI::Shell::{IVirtualDesktopManager, VirtualDesktopManager};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::config::DesktopMode;
+
+/// Make `target` visible on the current desktop, if it's on another one.
+pub fn bring_into_view(target: HWND) {
+    let manager: IVirtualDesktopManager = match unsafe { CoCreateInstance(&VirtualDesktopManager, None, CLSCTX_ALL) } {
+        Ok(m) => m,
+        Err(e) => {
+            crate::debug_log!("No virtual desktop manager: {:?}", e);
+            return;
+        }
+    };
+    unsafe {
+        // Fails for windows the shell doesn't track; treat those as here
+        if manager.IsWindowOnCurrentVirtualDesktop(target).map_or(true, |here| here.as_bool()) {
+            return;
+        }
+        let Ok(desktop) = manager.GetWindowDesktopId(target) else { return };
+        crate::debug_log!("Target is on virtual desktop {:?}", desktop);
+
+        if crate::config::get().activation.virtual_desktop == DesktopMode::Move {
+            if let Some(current) = current_desktop(&manager) {
+                match manager.MoveWindowToDesktop(target, &current) {
+                    Ok(()) => {
+                        crate::debug_log!("Moved target to the current desktop");
+                        return;
+                    }
+                    // E_ACCESSDENIED for other processes' windows on most builds
+                    Err(e) => crate::debug_log!("Can't move target ({:?}), switching instead", e),
+                }
+            }
+        }
+        switch_to(&manager, &desktop);
+    }
+}
+
+/// An invisible, activatable window of our own.
+unsafe fn helper_window() -> Option<HWND> {
+    let hwnd = CreateWindowExW(
+        WS_EX_LAYERED,
+        w!("STATIC"),
+        None,
+        WS_POPUP,
+        0, 0, 1, 1,
+        None,
+        None,
+        None,
+        None,
+    )
+    .ok()?;
+    let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), 0, LWA_ALPHA);
+    Some(hwnd)
+}
+
+/// Id of the desktop the user is on: where a new window of ours appears.
+unsafe fn current_desktop(manager: &IVirtualDesktopManager) -> Option<GUID> {
+    let helper = helper_window()?;
+    let _ = ShowWindow(helper, SW_SHOWNOACTIVATE);
+    let id = manager.GetWindowDesktopId(helper).ok();
+    let _ = DestroyWindow(helper);
+    id.filter(|id| *id != GUID::zeroed())
+}
+
+/// Switch to `desktop` by activating a helper window placed on it.
+unsafe fn switch_to(manager: &IVirtualDesktopManager, desktop: &GUID) {
+    let Some(helper) = helper_window() else { return };
+    // A window has to be shown before the shell assigns it a desktop
+    let _ = ShowWindow(helper, SW_SHOWNOACTIVATE);
+    match manager.MoveWindowToDesktop(helper, desktop) {
+        Ok(()) => {
+            let _ = SetForegroundWindow(helper);
+            // The shell switches asynchronously; let it finish before activating
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            crate::debug_log!("Switched to the target's desktop");
+        }
+        Err(e) => crate::warn_log!("Failed to switch desktops: {:?}", e),
+    }
+    let _ = DestroyWindow(helper);
+}
@@ -0,0 +1,208 @@
+//! Self-check (--doctor).
+//!
+//! Looks at everything a notification depends on that can be wrong on a
+//! given machine: the hook entries in Claude's settings, the config file,
+//! the state folder, the assets, UI Automation, DPI scaling, and what
+//! Windows allows for activating windows. Prints one line per check, to be
+//! pasted into bug reports, or with --json as one object for tools.
+
+use serde::Serialize;
+use windows::Win32::System::Threading::GetCurrentProcessId;
+use windows::Win32::UI::HiDpi::*;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Check { name, status, detail: detail.into() }
+    }
+}
+
+/// Events the hooks are registered for, see hooks/hooks.json.
+const HOOK_EVENTS: [&str; 4] = ["UserPromptSubmit", "Notification", "Stop", "SessionEnd"];
+
+/// Claude's settings folder: CLAUDE_CONFIG_DIR, else %USERPROFILE%\.claude.
+fn claude_dir() -> Option<std::path::PathBuf> {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("USERPROFILE").map(|home| std::path::PathBuf::from(home).join(".claude")))
+}
+
+/// The plugin is enabled, or settings.json has hook commands running
+/// ToastWindow.exe for every event.
+fn check_hooks() -> Check {
+    let Some(dir) = claude_dir() else {
+        return Check::new("Hooks", Status::Fail, "Neither CLAUDE_CONFIG_DIR nor USERPROFILE is set");
+    };
+    let mut registered = Vec::new();
+    for name in ["settings.json", "settings.local.json"] {
+        let path = dir.join(name);
+        let Ok(text) = std::fs::read_to_string(&path) else { continue };
+        let doc = crate::json::Document::parse(&text);
+        let plugin = doc
+            .get("enabledPlugins")
+            .and_then(|p| p.as_object())
+            .is_some_and(|p| p.iter().any(|(id, on)| id.starts_with("claude-code-notify") && on.as_bool() == Some(true)));
+        if plugin {
+            return Check::new("Hooks", Status::Pass, format!("claude-code-notify plugin enabled in {}", path.display()));
+        }
+        for event in HOOK_EVENTS {
+            let runs_us = doc
+                .get(&format!("hooks.{}", event))
+                .is_some_and(|entries| entries.to_string().contains("ToastWindow"));
+            if runs_us && !registered.contains(&event) {
+                registered.push(event);
+            }
+        }
+    }
+    let missing: Vec<&str> = HOOK_EVENTS.iter().copied().filter(|e| !registered.contains(e)).collect();
+    match (registered.is_empty(), missing.is_empty()) {
+        (_, true) => Check::new("Hooks", Status::Pass, format!("All events in {}", dir.display())),
+        (true, _) => Check::new(
+            "Hooks",
+            Status::Fail,
+            format!("Plugin not enabled and no ToastWindow hooks in {}", dir.display()),
+        ),
+        (false, false) => Check::new("Hooks", Status::Warn, format!("No hook for {}", missing.join(", "))),
+    }
+}
+
+fn check_config() -> Check {
+    let path = crate::config::config_path();
+    match std::fs::read_to_string(&path) {
+        Err(_) => Check::new("Config", Status::Pass, format!("No {}, using the defaults", path.display())),
+        Ok(text) => match toml::from_str::<crate::config::Config>(&text) {
+            Ok(_) => Check::new("Config", Status::Pass, path.display().to_string()),
+            Err(e) => Check::new("Config", Status::Fail, format!("{} is ignored: {}", path.display(), e)),
+        },
+    }
+}
+
+/// Open (and create or upgrade) the database.
+fn check_store() -> Check {
+    let path = crate::store::path();
+    match crate::store::open() {
+        Ok(_) => Check::new("Store", Status::Pass, format!("{} opens", path.display())),
+        Err(e) => Check::new("Store", Status::Fail, e),
+    }
+}
+
+fn check_assets() -> Vec<Check> {
+    let assets = crate::assets::discover_assets();
+    let found = |kind: &'static str, files: &[String]| match files.first() {
+        Some(first) => Check::new(kind, Status::Pass, format!("{} file(s), first {}", files.len(), first)),
+        None => Check::new(kind, Status::Fail, "None found"),
+    };
+    vec![
+        found("Sounds", &assets.sound_files),
+        found("Fonts", &assets.font_files),
+        found("Icon", assets.default_icon_path.as_slice()),
+    ]
+}
+
+fn check_uia() -> Check {
+    match crate::uiautomation::check() {
+        Ok(()) => Check::new("UI Automation", Status::Pass, "Available, terminal tabs can be found"),
+        Err(e) => Check::new("UI Automation", Status::Fail, format!("Can't create CUIAutomation: {:?}", e)),
+    }
+}
+
+fn check_dpi() -> Check {
+    let (awareness, dpi) =
+        unsafe { (GetAwarenessFromDpiAwarenessContext(GetThreadDpiAwarenessContext()), GetDpiForSystem()) };
+    let scale = dpi * 100 / 96;
+    match awareness {
+        DPI_AWARENESS_UNAWARE if scale != 100 => Check::new(
+            "DPI",
+            Status::Warn,
+            format!("Not DPI aware at {}% scaling, Windows stretches the toasts", scale),
+        ),
+        DPI_AWARENESS_UNAWARE => Check::new("DPI", Status::Pass, "Not DPI aware, at 100% scaling"),
+        DPI_AWARENESS_SYSTEM_AWARE => Check::new("DPI", Status::Pass, format!("System aware, {}% scaling", scale)),
+        _ => Check::new("DPI", Status::Pass, format!("Per-monitor aware, {}% scaling", scale)),
+    }
+}
+
+/// What Windows lets us do with the focus: the foreground lock timeout, and
+/// whether we run elevated (UIPI keeps unelevated processes from
+/// activating elevated windows).
+fn check_foreground() -> Vec<Check> {
+    let mut timeout = 0u32;
+    let read = unsafe {
+        SystemParametersInfoW(
+            SPI_GETFOREGROUNDLOCKTIMEOUT,
+            0,
+            Some(&mut timeout as *mut u32 as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    let lock = match read {
+        Ok(()) if timeout == 0 => Check::new("Foreground lock", Status::Pass, "Off, any process may take the focus"),
+        Ok(()) => Check::new(
+            "Foreground lock",
+            Status::Pass,
+            format!("{} ms, windows are brought forward from a click on the toast", timeout),
+        ),
+        Err(e) => Check::new("Foreground lock", Status::Warn, format!("Can't read: {:?}", e)),
+    };
+
+    let integrity = match crate::process::integrity_level(unsafe { GetCurrentProcessId() }) {
+        Some(level) if level >= 0x3000 => Check::new("Elevation", Status::Pass, "Elevated, any window can be activated"),
+        Some(_) => Check::new("Elevation", Status::Pass, "Not elevated; elevated terminals can only be flashed"),
+        None => Check::new("Elevation", Status::Warn, "Integrity level unknown"),
+    };
+    vec![lock, integrity]
+}
+
+/// Run every check and print the report, as text or JSON. Exits with 1 if
+/// any failed.
+pub fn run(json: bool) -> i32 {
+    let mut checks = vec![check_hooks(), check_config(), check_store()];
+    checks.extend(check_assets());
+    checks.push(check_uia());
+    checks.push(check_dpi());
+    checks.extend(check_foreground());
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let code = if failed > 0 { 1 } else { 0 };
+
+    if json {
+        let report = serde_json::json!({
+            "version": crate::cli::version(),
+            "exe_dir": crate::assets::exe_dir(),
+            "checks": checks,
+            "failed": failed,
+        });
+        println!("{}", report);
+        return code;
+    }
+
+    println!("{}", crate::cli::version());
+    println!("{}", crate::assets::exe_dir());
+    println!();
+    for check in &checks {
+        let status = match check.status {
+            Status::Pass => "PASS",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        };
+        println!("[{}] {}: {}", status, check.name, check.detail);
+    }
+    println!();
+    println!("{} checks, {} failed", checks.len(), failed);
+    code
+}
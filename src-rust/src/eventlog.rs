@@ -0,0 +1,73 @@
+//! Windows Event Log output for failures.
+//!
+//! Warnings and errors are reported to the Application log under the source
+//! "ClaudeCodeNotify", so breakage can be found in Event Viewer without
+//! --debug (`[log] event_log = false` turns it off). `register-event-log`,
+//! run elevated once, registers the source with .NET's EventLogMessages.dll;
+//! without it Event Viewer shows the text after a "description not found"
+//! preamble.
+
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::EventLog::*;
+use windows::Win32::System::Registry::*;
+
+use crate::config::LogLevel;
+
+const SOURCE: PCWSTR = w!("ClaudeCodeNotify");
+const SOURCE_KEY: PCWSTR = w!("SYSTEM\\CurrentControlSet\\Services\\EventLog\\Application\\ClaudeCodeNotify");
+
+/// Message file whose every event id formats as just the inserted text.
+const MESSAGE_FILE: &str = "%SystemRoot%\\Microsoft.NET\\Framework64\\v4.0.30319\\EventLogMessages.dll";
+
+/// Event ids, one per level.
+const EVENT_WARNING: u32 = 1;
+const EVENT_ERROR: u32 = 2;
+
+/// Report a warning or error line; other levels are ignored.
+pub fn report(level: LogLevel, text: &str) {
+    let (kind, id) = match level {
+        LogLevel::Error => (EVENTLOG_ERROR_TYPE, EVENT_ERROR),
+        LogLevel::Warn => (EVENTLOG_WARNING_TYPE, EVENT_WARNING),
+        _ => return,
+    };
+    let wide = crate::util::encode_wide(text);
+    unsafe {
+        // Failures are rare, so the source is opened for each one
+        let Ok(source) = RegisterEventSourceW(None, SOURCE) else { return };
+        let _ = ReportEventW(source, kind, 0, id, None, 0, Some(&[PCWSTR(wide.as_ptr())]), None);
+        let _ = DeregisterEventSource(source);
+    }
+}
+
+/// Register the event source. Needs an elevated process.
+pub fn register() -> Result<(), String> {
+    let mut key = HKEY::default();
+    unsafe {
+        let created = RegCreateKeyExW(
+            HKEY_LOCAL_MACHINE,
+            SOURCE_KEY,
+            None,
+            None,
+            REG_OPTION_NON_VOLATILE,
+            KEY_SET_VALUE,
+            None,
+            &mut key,
+            None,
+        );
+        if created != ERROR_SUCCESS {
+            return Err(format!("Can't create the event source key (run elevated?): {:?}", created));
+        }
+        let file: Vec<u8> = crate::util::encode_wide(MESSAGE_FILE).iter().flat_map(|c| c.to_le_bytes()).collect();
+        let types = (EVENTLOG_ERROR_TYPE.0 | EVENTLOG_WARNING_TYPE.0) as u32;
+        let results = [
+            RegSetValueExW(key, w!("EventMessageFile"), None, REG_EXPAND_SZ, Some(&file)),
+            RegSetValueExW(key, w!("TypesSupported"), None, REG_DWORD, Some(&types.to_le_bytes())),
+        ];
+        let _ = RegCloseKey(key);
+        match results.into_iter().find(|r| *r != ERROR_SUCCESS) {
+            Some(e) => Err(format!("Can't write the event source: {:?}", e)),
+            None => Ok(()),
+        }
+    }
+}
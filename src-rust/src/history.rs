@@ -0,0 +1,134 @@
+//! Notification history.
+//!
+//! Every toast shown is added to the notifications table of the store
+//! (kept for `[state] keep_days`). The "history" mouse action and
+//! --history show the most recent MAX_ENTRIES as text. Older versions
+//! appended JSON lines to history.jsonl, which the store imports.
+
+use serde::{Deserialize, Serialize};
+
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix time in seconds.
+    pub time: u64,
+    pub session: String,
+    /// "completion" or "input".
+    pub event: String,
+    pub title: String,
+    pub message: String,
+    #[serde(default)]
+    pub cwd: String,
+}
+
+impl Entry {
+    pub fn new(session: &str, input_mode: bool, title: &str, message: &str, cwd: &str) -> Self {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            time,
+            session: session.to_string(),
+            event: if input_mode { "input" } else { "completion" }.to_string(),
+            title: title.to_string(),
+            message: message.to_string(),
+            cwd: cwd.to_string(),
+        }
+    }
+}
+
+fn insert(db: &crate::store::Db, entry: &Entry) -> Result<(), String> {
+    db.execute(
+        "INSERT INTO notifications (time, session, event, title, message, cwd) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        &[
+            entry.time.into(),
+            entry.session.as_str().into(),
+            entry.event.as_str().into(),
+            entry.title.as_str().into(),
+            entry.message.as_str().into(),
+            entry.cwd.as_str().into(),
+        ],
+    )
+}
+
+/// Add an entry.
+pub fn append(entry: &Entry) {
+    crate::store::with(|db| insert(db, entry));
+}
+
+/// The latest MAX_ENTRIES entries, oldest first.
+pub fn load() -> Vec<Entry> {
+    let entries = crate::store::with(|db| {
+        db.query(
+            "SELECT time, session, event, title, message, cwd FROM notifications ORDER BY id DESC LIMIT ?1",
+            &[(MAX_ENTRIES as u64).into()],
+            |row| Entry {
+                time: row.int(0) as u64,
+                session: row.text(1),
+                event: row.text(2),
+                title: row.text(3),
+                message: row.text(4),
+                cwd: row.text(5),
+            },
+        )
+    });
+    let mut entries = entries.unwrap_or_default();
+    entries.reverse();
+    entries
+}
+
+/// Copy history.jsonl of older versions into the store.
+pub fn import(db: &crate::store::Db) -> Vec<std::path::PathBuf> {
+    let Some(path) = crate::config::local_dir().map(|d| d.join("history.jsonl")) else { return Vec::new() };
+    let Ok(text) = std::fs::read_to_string(&path) else { return Vec::new() };
+    for entry in text.lines().filter_map(|line| serde_json::from_str::<Entry>(line).ok()) {
+        if let Err(e) = insert(db, &entry) {
+            crate::warn_log!("Failed to import history entry: {}", e);
+        }
+    }
+    vec![path]
+}
+
+/// Human-readable listing, newest first.
+pub fn render(entries: &[Entry]) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut out = String::from("Claude Code notification history (newest first)\r\n");
+    if entries.is_empty() {
+        out.push_str("\r\nNo notifications yet.\r\n");
+    }
+    for e in entries.iter().rev() {
+        let ago = crate::util::format_duration(std::time::Duration::from_secs(now.saturating_sub(e.time)));
+        out.push_str(&format!("\r\n{} ago  {}", ago, e.title));
+        if !e.cwd.is_empty() {
+            out.push_str(&format!("  ({})", e.cwd));
+        }
+        out.push_str(&format!("\r\n    {}\r\n", e.message));
+    }
+    out
+}
+
+/// Write the listing to a text file in %TEMP% and open it with the default editor.
+pub fn open() {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::{AllowSetForegroundWindow, ASFW_ANY, SW_SHOWNORMAL};
+
+    // Not claude-notify-*.txt, which are session state files
+    let path = std::env::temp_dir().join("claude-code-notify-history.txt");
+    if let Err(e) = std::fs::write(&path, render(&load())) {
+        crate::warn_log!("Failed to write history view: {}", e);
+        return;
+    }
+    let file = crate::util::encode_wide(&path.to_string_lossy());
+    unsafe {
+        // Let the editor take the foreground from this background process
+        let _ = AllowSetForegroundWindow(ASFW_ANY);
+        ShellExecuteW(None, w!("open"), PCWSTR(file.as_ptr()), None, None, SW_SHOWNORMAL);
+    }
+}
@@ -0,0 +1,135 @@
+//! Job object holding the spawned toast processes.
+//!
+//! Every `--notify-show` child is put into one named job, so the processes
+//! can be counted, listed and ended from any other instance, and the job's
+//! active-process limit (`[toast] max_processes`) keeps them from piling up
+//! when notifications come in faster than they close.
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::System::JobObjects::*;
+
+const JOB_NAME: &str = "Local\\ClaudeCodeNotify.Toasts";
+
+const JOB_OBJECT_QUERY: u32 = 0x4;
+const JOB_OBJECT_TERMINATE: u32 = 0x8;
+
+/// Room for this many ids in a process list query; more than any limit.
+const MAX_LISTED: usize = 64;
+
+/// JOBOBJECT_BASIC_PROCESS_ID_LIST with room for MAX_LISTED ids.
+#[repr(C)]
+struct ProcessIdList {
+    assigned: u32,
+    listed: u32,
+    ids: [usize; MAX_LISTED],
+}
+
+pub struct Job(HANDLE);
+
+impl Job {
+    /// The toast job, created if no toast is running, with the configured
+    /// process limit applied.
+    pub fn toasts() -> Option<Job> {
+        let handle = match unsafe { CreateJobObjectW(None, &HSTRING::from(JOB_NAME)) } {
+            Ok(handle) => handle,
+            Err(e) => {
+                crate::debug_log!("CreateJobObjectW failed: {:?}", e);
+                return None;
+            }
+        };
+        let job = Job(handle);
+        job.set_limit(crate::config::get().toast.max_processes);
+        Some(job)
+    }
+
+    /// The toast job, if any toast process exists.
+    pub fn open() -> Option<Job> {
+        unsafe { OpenJobObjectW(JOB_OBJECT_QUERY | JOB_OBJECT_TERMINATE, false, &HSTRING::from(JOB_NAME)) }
+            .ok()
+            .map(Job)
+    }
+
+    /// Allow at most `max` processes in the job at once; 0 for no limit.
+    fn set_limit(&self, max: u32) {
+        let mut info = JOBOBJECT_BASIC_LIMIT_INFORMATION::default();
+        if max > 0 {
+            info.LimitFlags = JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+            info.ActiveProcessLimit = max;
+        }
+        let result = unsafe {
+            SetInformationJobObject(
+                self.0,
+                JobObjectBasicLimitInformation,
+                &info as *const _ as *const _,
+                std::mem::size_of::<JOBOBJECT_BASIC_LIMIT_INFORMATION>() as u32,
+            )
+        };
+        if let Err(e) = result {
+            crate::debug_log!("SetInformationJobObject failed: {:?}", e);
+        }
+    }
+
+    /// Put a (suspended) process into the job. Fails when the job is full.
+    pub fn assign(&self, process: HANDLE) -> bool {
+        match unsafe { AssignProcessToJobObject(self.0, process) } {
+            Ok(()) => true,
+            Err(e) => {
+                crate::debug_log!("AssignProcessToJobObject failed: {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Processes in the job now and since it was created.
+    pub fn counts(&self) -> (u32, u32) {
+        let mut info = JOBOBJECT_BASIC_ACCOUNTING_INFORMATION::default();
+        let result = unsafe {
+            QueryInformationJobObject(
+                Some(self.0),
+                JobObjectBasicAccountingInformation,
+                &mut info as *mut _ as *mut _,
+                std::mem::size_of::<JOBOBJECT_BASIC_ACCOUNTING_INFORMATION>() as u32,
+                None,
+            )
+        };
+        if result.is_err() {
+            return (0, 0);
+        }
+        (info.ActiveProcesses, info.TotalProcesses)
+    }
+
+    /// PIDs of the processes in the job.
+    pub fn process_ids(&self) -> Vec<u32> {
+        let mut list = ProcessIdList { assigned: 0, listed: 0, ids: [0; MAX_LISTED] };
+        let result = unsafe {
+            QueryInformationJobObject(
+                Some(self.0),
+                JobObjectBasicProcessIdList,
+                &mut list as *mut _ as *mut _,
+                std::mem::size_of::<ProcessIdList>() as u32,
+                None,
+            )
+        };
+        if result.is_err() {
+            return Vec::new();
+        }
+        let listed = (list.listed as usize).min(MAX_LISTED);
+        list.ids[..listed].iter().map(|&id| id as u32).collect()
+    }
+
+    /// End every process in the job.
+    pub fn terminate(&self) {
+        if let Err(e) = unsafe { TerminateJobObject(self.0, 1) } {
+            crate::debug_log!("TerminateJobObject failed: {:?}", e);
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
@@ -1,27 +1,166 @@
 //! Stdin JSON reading and field extraction.
 //!
-//! Reads stdin in binary mode, parses as JSON via serde_json,
-//! and extracts string fields.
+//! Reads stdin in binary mode, parses as JSON via serde_json, and looks up
+//! fields by dot path into nested objects and arrays (`tool_input.command`,
+//! `messages[0].text`). The hook payloads themselves are typed, see
+//! `payload.rs`.
+//!
+//! A writer that never closes stdin can't hang the hook: reading stops at
+//! a deadline. Input cut short is repaired as far as possible, and with
+//! --debug the raw bytes are kept in payload.json next to debug.log.
 
 use std::io::Read;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// How long to wait for stdin to be closed.
+const READ_TIMEOUT_MS: u64 = 2000;
+
+/// At most this many cut points are tried when repairing truncated JSON.
+const MAX_REPAIRS: usize = 32;
 
-/// Read all of stdin into a String.
+/// Read all of stdin into a String, up to the read deadline.
 /// Mirrors the C++ ReadStdinJson() which reads in binary mode with fread in 4096 chunks.
 pub fn read_stdin_json() -> String {
+    // Chunks come from a reader thread, so whatever arrived is kept when the
+    // deadline passes; the thread is abandoned, blocked in the read
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin().lock();
+        let mut chunk = [0u8; 4096];
+        while let Ok(n) = stdin.read(&mut chunk) {
+            if n == 0 || tx.send(chunk[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let deadline = Instant::now() + Duration::from_millis(READ_TIMEOUT_MS);
     let mut buf = Vec::new();
-    let _ = std::io::stdin().lock().read_to_end(&mut buf);
-    String::from_utf8_lossy(&buf).into_owned()
+    loop {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(chunk) => buf.extend_from_slice(&chunk),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                crate::debug_log!("Stdin still open after {} ms, using {} bytes", READ_TIMEOUT_MS, buf.len());
+                break;
+            }
+        }
+    }
+    capture(&buf);
+
+    let text = String::from_utf8_lossy(&buf);
+    text.strip_prefix('\u{feff}').unwrap_or(&text).to_string()
+}
+
+/// With --debug, keep the raw payload for bug reports, unless the prompt
+/// in it may not be stored as-is.
+fn capture(raw: &[u8]) {
+    let Some(dir) = crate::log::dir() else { return };
+    if crate::config::get().privacy.prompt != crate::config::PromptPrivacy::Show {
+        return;
+    }
+    let path = dir.join("payload.json");
+    match std::fs::write(&path, raw) {
+        Ok(()) => crate::debug_log!("Payload ({} bytes) captured to {:?}", raw.len(), path),
+        Err(e) => crate::debug_log!("Can't capture payload to {:?}: {}", path, e),
+    }
 }
 
-/// Extract a string field from a JSON string.
-/// Returns empty string if the field is not found or not a string.
-pub fn extract_string(json: &str, key: &str) -> String {
-    let v: serde_json::Value = match serde_json::from_str(json) {
-        Ok(v) => v,
-        Err(_) => return String::new(),
-    };
-    v.get(key)
-        .and_then(|v| v.as_str())
-        .unwrap_or("")
-        .to_string()
+/// Parse JSON that may have been cut off: close an open string and the
+/// open arrays and objects, dropping trailing members until it parses.
+fn parse_truncated(json: &str) -> Option<Value> {
+    // Where each member could be cut off (before a comma), with the
+    // brackets open there
+    let mut cuts = Vec::new();
+    let mut open = Vec::new();
+    let (mut in_string, mut escaped) = (false, false);
+    for (i, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                open.pop();
+            }
+            ',' => cuts.push((i, open.clone())),
+            _ => {}
+        }
+    }
+
+    let close = |text: &str, open: &[char]| -> String { text.chars().chain(open.iter().rev().copied()).collect() };
+    let mut whole = json.trim_end().trim_end_matches('\\').to_string();
+    if in_string {
+        whole.push('"');
+    }
+    std::iter::once(close(&whole, &open))
+        .chain(cuts.iter().rev().take(MAX_REPAIRS).map(|(i, open)| close(&json[..*i], open)))
+        .find_map(|text| serde_json::from_str(&text).ok())
+}
+
+/// A parsed JSON document, for several lookups without parsing it again.
+/// Invalid JSON gives an empty document, after trying to repair truncation.
+pub struct Document(Value);
+
+impl Document {
+    pub fn parse(json: &str) -> Self {
+        let json = json.trim();
+        if json.is_empty() {
+            crate::debug_log!("No JSON input");
+            return Document(Value::Null);
+        }
+        match serde_json::from_str(json) {
+            Ok(value) => Document(value),
+            Err(e) => {
+                crate::debug_log!("Invalid JSON ({} bytes): {}", json.len(), e);
+                let repaired = parse_truncated(json);
+                crate::debug_log!("Truncated JSON {}", if repaired.is_some() { "repaired" } else { "not repairable" });
+                Document(repaired.unwrap_or(Value::Null))
+            }
+        }
+    }
+
+    /// The value at a dot path: object keys separated by dots, array
+    /// elements as `[n]` or a plain number (`a.b[0].c`, `a.b.0.c`).
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut value = &self.0;
+        for segment in path.split('.') {
+            let (key, indices) = match segment.find('[') {
+                Some(i) => (&segment[..i], &segment[i..]),
+                None => (segment, ""),
+            };
+            if !key.is_empty() {
+                value = match value {
+                    Value::Object(map) => map.get(key)?,
+                    Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+                    _ => return None,
+                };
+            }
+            for index in indices.split_terminator(']') {
+                let index = index.strip_prefix('[')?.parse::<usize>().ok()?;
+                value = value.as_array()?.get(index)?;
+            }
+        }
+        Some(value)
+    }
+
+    /// The string at a dot path; empty if missing or not a string.
+    pub fn string(&self, path: &str) -> String {
+        self.get(path).and_then(Value::as_str).unwrap_or("").to_string()
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
 }
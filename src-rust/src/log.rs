@@ -1,58 +1,445 @@
 //! Debug logging system.
 //!
-//! When --debug is active, logs to stdout and to <exe_dir>\debug.log.
+//! When --debug is active, lines are appended to debug.log in `[log] dir`
+//! (next to the exe by default). The file is rotated so it can't grow
+//! without bound: past `max_kb` it becomes debug.1.log (and so on up to
+//! `keep`), or with `rotate = "daily"` every day has its own
+//! debug-YYYY-MM-DD.log and only the newest `keep` are kept. Lines logged
+//! before the config is loaded are held until `open`.
+//!
+//! With `per_session = true` (the default) each process of a session logs
+//! to debug-{session}-{mode}.log instead, so concurrent hooks and toasts
+//! don't interleave or rotate each other's lines away. The file is picked
+//! by `set_session`; until then lines are held, and processes that never
+//! learn a session write them to the shared file at `close`. Session files
+//! are rotated by size like debug.log and removed after
+//! SESSION_LOG_DAYS days.
+//!
+//! Every line carries the time, level, process id, mode, and session, so
+//! the save, notify and show processes of one hook can be told apart in a
+//! shared file:
+//!
+//! `2026-01-31 14:05:09.123 DEBUG [4711 show 3f2a...] Title: Claude Code`
+//!
+//! or with `format = "json"` the same fields as a JSON object per line.
+//!
+//! With `[log] debugger = true` or CLAUDE_NOTIFY_DEBUGGER=1 in the
+//! environment, lines also go to OutputDebugString, to be watched live in
+//! DebugView or a debugger, with or without --debug. Started with --debug
+//! from a terminal, lines are echoed to its console as well. Warnings and
+//! errors also go to the Windows Event Log (see eventlog.rs) unless
+//! `[log] event_log = false`.
+//!
+//! The last `RECENT_LINES` lines of every level are also kept in memory,
+//! with or without --debug, for the crash report (see crash.rs).
 
-use std::sync::OnceLock;
+use std::collections::VecDeque;
+use std::io::Write;
 use std::sync::Mutex;
+use std::sync::OnceLock;
+
+use crate::config::{LogFormat, LogLevel, LogRotate};
+
+/// A line held until the log file is open.
+struct Pending {
+    level: LogLevel,
+    time: String,
+    msg: String,
+}
+
+/// Environment variable that turns on the OutputDebugString output.
+const DEBUGGER_ENV: &str = "CLAUDE_NOTIFY_DEBUGGER";
+
+/// Lines kept in memory for a crash report.
+const RECENT_LINES: usize = 200;
+
+/// Age in days past which per-session log files are removed.
+const SESSION_LOG_DAYS: u64 = 7;
 
 struct Logger {
     debug: bool,
     log_path: Option<std::path::PathBuf>,
+    /// Folder of the per-session file while it isn't picked yet.
+    session_dir: Option<std::path::PathBuf>,
+    /// Lines for the per-session file, until it's picked.
+    held: Vec<String>,
+    /// Mirror lines to OutputDebugString.
+    debugger: bool,
+    /// Echo lines to the console of the terminal we were started from.
+    console: bool,
+    opened: bool,
+    /// Lines logged before `open`.
+    pending: Vec<Pending>,
+    /// The last RECENT_LINES lines, whether written anywhere or not.
+    recent: VecDeque<String>,
+    /// Command of this process ("save", "show", ...).
+    mode: &'static str,
+    session: String,
 }
 
 static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
 
-/// Initialize the logger. Call once at startup.
-pub fn init(debug: bool) {
-    let log_path = if debug {
-        let exe = std::env::current_exe().unwrap_or_default();
-        let dir = exe.parent().unwrap_or(std::path::Path::new("."));
-        let path = dir.join("debug.log");
-        // Create/truncate with header
-        let _ = std::fs::write(&path, "=== ToastWindow Debug Log ===\n");
-        Some(path)
+/// Initialize the logger. Call once at startup, before config::init.
+pub fn init(debug: bool, mode: &'static str) {
+    // Only from a terminal: hooks have their output redirected to Claude,
+    // whose console must not be written to
+    let console = debug && crate::util::attach_parent_console();
+    let _ = LOGGER.set(Mutex::new(Logger {
+        debug,
+        log_path: None,
+        session_dir: None,
+        held: Vec::new(),
+        debugger: false,
+        console,
+        opened: false,
+        pending: Vec::new(),
+        recent: VecDeque::new(),
+        mode,
+        session: String::new(),
+    }));
+}
+
+/// Tag the following lines with a session, once it's known. With
+/// per-session files, the first call picks the file (the shared one for
+/// an empty session) and writes the lines held so far to it.
+pub fn set_session(session: &str) {
+    let Some(logger) = LOGGER.get() else { return };
+    let Ok(mut logger) = logger.lock() else { return };
+    session.clone_into(&mut logger.session);
+    let Some(dir) = logger.session_dir.take() else { return };
+    let path = if session.is_empty() {
+        shared_path(&dir)
+    } else {
+        let path = dir.join(format!("debug-{}-{}.log", file_safe(session), logger.mode));
+        if crate::config::get().log.rotate == LogRotate::Size {
+            rotate_if_full(&path);
+        }
+        path
+    };
+    let held = std::mem::take(&mut logger.held);
+    if !held.is_empty() {
+        append(&path, &held.join("\n"));
+    }
+    logger.log_path = Some(path);
+}
+
+/// Write the lines still held, if no session was ever set, to the shared
+/// file. Call before exiting.
+pub fn close() {
+    let Some(logger) = LOGGER.get() else { return };
+    let Ok(mut logger) = logger.lock() else { return };
+    let Some(dir) = logger.session_dir.take() else { return };
+    let held = std::mem::take(&mut logger.held);
+    if !held.is_empty() {
+        append(&shared_path(&dir), &held.join("\n"));
+    }
+}
+
+/// Open the log file, rotating old ones, and write what was logged so far.
+/// Call once the config is loaded.
+pub fn open() {
+    let Some(logger) = LOGGER.get() else { return };
+    let Ok(mut logger) = logger.lock() else { return };
+    let cfg = &crate::config::get().log;
+    logger.opened = true;
+    logger.debugger = cfg.debugger || std::env::var_os(DEBUGGER_ENV).is_some_and(|v| v != "0" && !v.is_empty());
+    let pending = std::mem::take(&mut logger.pending);
+    for p in &pending {
+        let line = format_line(&logger, p.level, &p.time, &p.msg);
+        remember(&mut logger, line);
+    }
+    if cfg.event_log {
+        for p in pending.iter().filter(|p| p.level <= LogLevel::Warn) {
+            crate::eventlog::report(p.level, &format_line(&logger, p.level, &p.time, &p.msg));
+        }
+    }
+    if !logger.debug {
+        if logger.debugger {
+            for p in pending.iter().filter(|p| p.level <= cfg.level) {
+                output_debug_string(&format_line(&logger, p.level, &p.time, &p.msg));
+            }
+        }
+        return;
+    }
+
+    let dir = configured_dir();
+    let _ = std::fs::create_dir_all(&dir);
+
+    let lines: Vec<String> = pending
+        .iter()
+        .filter(|p| p.level <= cfg.level)
+        .map(|p| format_line(&logger, p.level, &p.time, &p.msg))
+        .collect();
+    if logger.debugger {
+        lines.iter().for_each(|line| output_debug_string(line));
+    }
+    if logger.console {
+        lines.iter().for_each(|line| console_line(line));
+    }
+    if cfg.per_session {
+        prune_sessions(&dir);
+        logger.held = lines;
+        logger.session_dir = Some(dir);
+        return;
+    }
+    let path = shared_path(&dir);
+    if !lines.is_empty() {
+        append(&path, &lines.join("\n"));
+    }
+    logger.log_path = Some(path);
+}
+
+/// debug.log (rotated if full), or today's file with daily rotation
+/// (pruning old days).
+fn shared_path(dir: &std::path::Path) -> std::path::PathBuf {
+    let cfg = &crate::config::get().log;
+    match cfg.rotate {
+        LogRotate::Size => {
+            let path = dir.join("debug.log");
+            rotate_if_full(&path);
+            path
+        }
+        LogRotate::Daily => {
+            prune_days(dir, cfg.keep);
+            dir.join(format!("debug-{}.log", today()))
+        }
+    }
+}
+
+/// Folder of debug.log, if --debug was specified.
+pub fn dir() -> Option<std::path::PathBuf> {
+    let logger = LOGGER.get()?.lock().ok()?;
+    if let Some(dir) = &logger.session_dir {
+        return Some(dir.clone());
+    }
+    logger.log_path.as_deref()?.parent().map(|dir| dir.to_path_buf())
+}
+
+/// `[log] dir`, or the exe's folder; where the log files go with --debug.
+pub fn configured_dir() -> std::path::PathBuf {
+    let cfg = &crate::config::get().log;
+    if cfg.dir.is_empty() {
+        std::path::PathBuf::from(crate::assets::exe_dir())
     } else {
-        None
+        std::path::PathBuf::from(&cfg.dir)
+    }
+}
+
+/// The last lines logged, oldest first. Doesn't wait for the logger, as a
+/// crash may happen while it's locked.
+pub fn recent() -> Vec<String> {
+    let Some(logger) = LOGGER.get() else { return Vec::new() };
+    let logger = match logger.try_lock() {
+        Ok(logger) => logger,
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return vec!["(log lines unavailable, the logger was busy)".into()],
     };
+    logger.recent.iter().cloned().collect()
+}
 
-    let _ = LOGGER.set(Mutex::new(Logger { debug, log_path }));
+fn remember(logger: &mut Logger, line: String) {
+    if logger.recent.len() == RECENT_LINES {
+        logger.recent.pop_front();
+    }
+    logger.recent.push_back(line);
 }
 
-/// Log a message. Only outputs if --debug was specified.
-pub fn log(msg: &str) {
+/// Log a message. Only outputs with --debug or the debugger output on.
+pub fn log(level: LogLevel, msg: &str) {
     let Some(logger) = LOGGER.get() else { return };
-    let Ok(logger) = logger.lock() else { return };
+    let Ok(mut logger) = logger.lock() else { return };
 
-    if !logger.debug && logger.log_path.is_none() {
+    // Debug output goes to the log file, the debugger, and a console only
+    // if we attached to the terminal's. AllocConsole() would create a
+    // visible CMD window for GUI subsystem apps, which is unacceptable for a
+    // notification tool.
+
+    if !logger.opened {
+        // Which outputs are on is only known once the config is loaded
+        logger.pending.push(Pending { level, time: now_precise(), msg: msg.to_string() });
+        return;
+    }
+    let cfg = &crate::config::get().log;
+    let event_log = cfg.event_log && level <= LogLevel::Warn;
+    let to_file = logger.log_path.is_some() || logger.session_dir.is_some();
+    let output = (logger.debugger || logger.console || to_file) && level <= cfg.level;
+    let line = format_line(&logger, level, &now_precise(), msg);
+    remember(&mut logger, line.clone());
+    if event_log {
+        crate::eventlog::report(level, &line);
+    }
+    if output {
+        write(&mut logger, &line);
+    }
+}
+
+/// Send a formatted line to the outputs that are on.
+fn write(logger: &mut Logger, line: &str) {
+    if logger.debugger {
+        output_debug_string(line);
+    }
+    if logger.console {
+        console_line(line);
+    }
+    if let Some(path) = &logger.log_path {
+        append(path, line);
+        if crate::config::get().log.rotate == LogRotate::Size {
+            rotate_if_full(path);
+        }
+    } else if logger.session_dir.is_some() {
+        logger.held.push(line.to_string());
+    }
+}
+
+/// eprintln! would panic if the console went away.
+fn console_line(line: &str) {
+    let _ = writeln!(std::io::stderr(), "{}", line);
+}
+
+fn output_debug_string(line: &str) {
+    let wide = crate::util::encode_wide(&format!("ToastWindow: {}\n", line));
+    unsafe { windows::Win32::System::Diagnostics::Debug::OutputDebugStringW(windows::core::PCWSTR(wide.as_ptr())) };
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Error => "ERROR",
+        LogLevel::Warn => "WARN",
+        LogLevel::Info => "INFO",
+        LogLevel::Debug => "DEBUG",
+    }
+}
+
+fn format_line(logger: &Logger, level: LogLevel, time: &str, msg: &str) -> String {
+    let pid = std::process::id();
+    match crate::config::get().log.format {
+        LogFormat::Text => {
+            let session = if logger.session.is_empty() { "-" } else { &logger.session };
+            format!("{} {:5} [{} {} {}] {}", time, level_name(level), pid, logger.mode, session, msg)
+        }
+        LogFormat::Json => serde_json::json!({
+            "time": time,
+            "level": level,
+            "pid": pid,
+            "mode": logger.mode,
+            "session": logger.session,
+            "msg": msg,
+        })
+        .to_string(),
+    }
+}
+
+fn append(path: &std::path::Path, text: &str) {
+    if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(f, "{}", text);
+    }
+}
+
+/// Past `[log] max_kb`, shift debug.log to debug.1.log, debug.1.log to
+/// debug.2.log, and so on, dropping the oldest (likewise for session files).
+fn rotate_if_full(path: &std::path::Path) {
+    let cfg = &crate::config::get().log;
+    let full = std::fs::metadata(path).is_ok_and(|m| m.len() > cfg.max_kb.max(1) * 1024);
+    if !full {
+        return;
+    }
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let numbered = |n: usize| path.with_file_name(format!("{}.{}.log", stem, n));
+    if cfg.keep == 0 {
+        let _ = std::fs::remove_file(path);
         return;
     }
+    let _ = std::fs::remove_file(numbered(cfg.keep));
+    for n in (1..cfg.keep).rev() {
+        let _ = std::fs::rename(numbered(n), numbered(n + 1));
+    }
+    // Another process may have rotated it already; then this fails harmlessly
+    let _ = std::fs::rename(path, numbered(1));
+}
 
-    // Debug output goes ONLY to the log file, never to stdout/console.
-    // AllocConsole() would create a visible CMD window for GUI subsystem apps,
-    // which is unacceptable for a notification tool.
+/// Keep the newest `keep` daily logs besides today's.
+fn prune_days(dir: &std::path::Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let current = format!("debug-{}.log", today());
+    let mut days: Vec<String> = entries
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter(|name| is_daily(name) && *name != current)
+        .collect();
+    // YYYY-MM-DD sorts by date
+    days.sort_unstable_by(|a, b| b.cmp(a));
+    for name in days.iter().skip(keep) {
+        let _ = std::fs::remove_file(dir.join(name));
+    }
+}
+
+/// Whether a file name is a daily log, debug-YYYY-MM-DD.log.
+fn is_daily(name: &str) -> bool {
+    let Some(date) = name.strip_prefix("debug-").and_then(|n| n.strip_suffix(".log")) else { return false };
+    date.len() == 10 && date.chars().enumerate().all(|(i, c)| if i == 4 || i == 7 { c == '-' } else { c.is_ascii_digit() })
+}
 
-    if let Some(ref path) = logger.log_path {
-        use std::io::Write;
-        if let Ok(mut f) = std::fs::OpenOptions::new().append(true).open(path) {
-            let _ = writeln!(f, "{}", msg);
+/// Remove session files (and their rotated parts) not written to for
+/// SESSION_LOG_DAYS.
+fn prune_sessions(dir: &std::path::Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let max_age = std::time::Duration::from_secs(SESSION_LOG_DAYS * 24 * 60 * 60);
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with("debug-") || !name.ends_with(".log") || is_daily(&name) {
+            continue;
+        }
+        let old = entry.metadata().and_then(|m| m.modified()).is_ok_and(|t| t.elapsed().is_ok_and(|age| age > max_age));
+        if old {
+            let _ = std::fs::remove_file(entry.path());
         }
     }
 }
 
-/// Convenience macro for formatted logging with [DEBUG] prefix.
+/// A session id as it can appear in a file name.
+fn file_safe(session: &str) -> String {
+    session.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+/// Local date as YYYY-MM-DD.
+fn today() -> String {
+    let now = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+    format!("{:04}-{:02}-{:02}", now.wYear, now.wMonth, now.wDay)
+}
+
+/// Local time as YYYY-MM-DD HH:MM:SS.mmm.
+fn now_precise() -> String {
+    let now = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        now.wYear, now.wMonth, now.wDay, now.wHour, now.wMinute, now.wSecond, now.wMilliseconds
+    )
+}
+
+/// Log at a level, formatted.
 #[macro_export]
-macro_rules! debug_log {
-    ($($arg:tt)*) => {
-        $crate::log::log(&format!("[DEBUG] {}", format!($($arg)*)))
+macro_rules! log_at {
+    ($level:ident, $($arg:tt)*) => {
+        $crate::log::log($crate::config::LogLevel::$level, &format!($($arg)*))
     };
 }
+
+#[macro_export]
+macro_rules! error_log {
+    ($($arg:tt)*) => { $crate::log_at!(Error, $($arg)*) };
+}
+
+#[macro_export]
+macro_rules! warn_log {
+    ($($arg:tt)*) => { $crate::log_at!(Warn, $($arg)*) };
+}
+
+#[macro_export]
+macro_rules! info_log {
+    ($($arg:tt)*) => { $crate::log_at!(Info, $($arg)*) };
+}
+
+/// Convenience macro for formatted logging at the debug level.
+#[macro_export]
+macro_rules! debug_log {
+    ($($arg:tt)*) => { $crate::log_at!(Debug, $($arg)*) };
+}
@@ -0,0 +1,473 @@
+//! Log viewer window (--logs).
+//!
+//! Shows the debug log files in `[log] dir` merged by time, with a session
+//! and a level filter, and follows them as they grow, so nobody has to go
+//! looking for debug-*.log files next to the exe. Only the current files
+//! are read (not the rotated .1.log ones), and only their last
+//! TAIL_BYTES.
+
+use std::cell::RefCell;
+
+use windows::core::*;
+use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::*;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::config::LogLevel;
+
+const LOGS_CLASS_NAME: &str = "ClaudeCodeNotifyLogs";
+
+const ID_SESSION: usize = 1;
+const ID_LEVEL: usize = 2;
+const ID_TEXT: usize = 3;
+
+const TIMER_TAIL: usize = 1;
+const TAIL_INTERVAL_MS: u32 = 1000;
+
+/// Newest log files read.
+const MAX_FILES: usize = 20;
+/// Bytes read from the end of each file.
+const TAIL_BYTES: usize = 512 * 1024;
+/// Lines shown, the newest.
+const MAX_LINES: usize = 5000;
+
+const MARGIN: i32 = 8;
+const COMBO_WIDTH: i32 = 320;
+const COMBO_HEIGHT: i32 = 24;
+/// Height of the open drop-down lists.
+const DROPDOWN_HEIGHT: i32 = 300;
+
+/// Level filter entries, each showing its level and the more important ones.
+const LEVELS: [(&str, LogLevel); 4] = [
+    ("Errors", LogLevel::Error),
+    ("Warnings and errors", LogLevel::Warn),
+    ("Info and above", LogLevel::Info),
+    ("Everything", LogLevel::Debug),
+];
+const ALL_SESSIONS: &str = "All sessions";
+
+/// A log line, in either format.
+struct Line {
+    time: String,
+    /// None for lines that don't parse, shown only with "Everything".
+    level: Option<LogLevel>,
+    session: String,
+    text: String,
+}
+
+/// A file as last read: path, size and modification time.
+type FileStamp = (std::path::PathBuf, u64, Option<std::time::SystemTime>);
+
+struct LogView {
+    session_box: HWND,
+    level_box: HWND,
+    text_box: HWND,
+    ui_font: HFONT,
+    text_font: HFONT,
+    dir: std::path::PathBuf,
+    stamps: Vec<FileStamp>,
+    lines: Vec<Line>,
+    /// Sessions in the combo box, newest first.
+    sessions: Vec<String>,
+    /// Text shown, to skip updates that change nothing.
+    shown: String,
+}
+
+thread_local! {
+    static VIEW: RefCell<Option<LogView>> = const { RefCell::new(None) };
+}
+
+fn level_from_name(name: &str) -> Option<LogLevel> {
+    match name.to_ascii_uppercase().as_str() {
+        "ERROR" => Some(LogLevel::Error),
+        "WARN" => Some(LogLevel::Warn),
+        "INFO" => Some(LogLevel::Info),
+        "DEBUG" => Some(LogLevel::Debug),
+        _ => None,
+    }
+}
+
+/// Parse `time LEVEL [pid mode session] msg`, or a JSON line, which is
+/// shown in the text format.
+fn parse_line(raw: &str) -> Line {
+    if raw.starts_with('{') {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(raw) {
+            let field = |name: &str| match &value[name] {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            let level = level_from_name(&field("level"));
+            let session = field("session");
+            let text = format!(
+                "{} {:5} [{} {} {}] {}",
+                field("time"),
+                field("level").to_ascii_uppercase(),
+                field("pid"),
+                field("mode"),
+                if session.is_empty() { "-" } else { &session },
+                field("msg")
+            );
+            return Line { time: field("time"), level, session, text };
+        }
+    }
+    // YYYY-MM-DD HH:MM:SS.mmm
+    const TIME_LEN: usize = 23;
+    let mut line = Line { time: String::new(), level: None, session: String::new(), text: raw.to_string() };
+    let (Some(time), Some(rest)) = (raw.get(..TIME_LEN), raw.get(TIME_LEN..)) else { return line };
+    line.time = time.to_string();
+    line.level = rest.split_whitespace().next().and_then(level_from_name);
+    if let Some((inner, _)) = rest.split_once('[').and_then(|(_, tail)| tail.split_once(']')) {
+        let session = inner.split(' ').nth(2).unwrap_or_default();
+        if session != "-" {
+            line.session = session.to_string();
+        }
+    }
+    line
+}
+
+/// The newest current log files: debug.log, daily and session files, not
+/// the rotated ones.
+fn log_files(dir: &std::path::Path) -> Vec<FileStamp> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<FileStamp> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let Some(stem) = name.strip_prefix("debug").and_then(|n| n.strip_suffix(".log")) else { return false };
+            let rotated = stem.rsplit_once('.').is_some_and(|(_, n)| n.parse::<u32>().is_ok());
+            !rotated
+        })
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            Some((e.path(), meta.len(), meta.modified().ok()))
+        })
+        .collect();
+    files.sort_by_key(|f| std::cmp::Reverse(f.2));
+    files.truncate(MAX_FILES);
+    files
+}
+
+/// The last TAIL_BYTES of a file, from the first whole line.
+fn tail(path: &std::path::Path) -> String {
+    let Ok(bytes) = std::fs::read(path) else { return String::new() };
+    if bytes.len() <= TAIL_BYTES {
+        return String::from_utf8_lossy(&bytes).into_owned();
+    }
+    let end = &bytes[bytes.len() - TAIL_BYTES..];
+    let start = end.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+    String::from_utf8_lossy(&end[start..]).into_owned()
+}
+
+fn read_lines(files: &[FileStamp]) -> Vec<Line> {
+    let mut lines: Vec<Line> = files
+        .iter()
+        .flat_map(|(path, _, _)| tail(path).lines().filter(|l| !l.trim().is_empty()).map(parse_line).collect::<Vec<_>>())
+        .collect();
+    // Stable, so lines of one file keep their order within a millisecond
+    lines.sort_by(|a, b| a.time.cmp(&b.time));
+    lines
+}
+
+fn send(hwnd: HWND, msg: u32, wparam: usize, lparam: isize) -> isize {
+    unsafe { SendMessageW(hwnd, msg, Some(WPARAM(wparam)), Some(LPARAM(lparam))).0 }
+}
+
+fn add_item(combo: HWND, text: &str) {
+    let wide = crate::util::encode_wide(text);
+    send(combo, CB_ADDSTRING, 0, wide.as_ptr() as isize);
+}
+
+/// Fill the session list from the lines, keeping the selection.
+fn update_sessions(view: &mut LogView) {
+    let mut sessions: Vec<String> = Vec::new();
+    for line in view.lines.iter().rev() {
+        if !line.session.is_empty() && !sessions.contains(&line.session) {
+            sessions.push(line.session.clone());
+        }
+    }
+    if sessions == view.sessions {
+        return;
+    }
+    let selected = selected_session(view);
+    send(view.session_box, CB_RESETCONTENT, 0, 0);
+    add_item(view.session_box, ALL_SESSIONS);
+    sessions.iter().for_each(|s| add_item(view.session_box, s));
+    let index = selected.and_then(|s| sessions.iter().position(|x| *x == s)).map_or(0, |i| i + 1);
+    send(view.session_box, CB_SETCURSEL, index, 0);
+    view.sessions = sessions;
+}
+
+/// The session filtered on, None for all.
+fn selected_session(view: &LogView) -> Option<String> {
+    let index = send(view.session_box, CB_GETCURSEL, 0, 0);
+    if index <= 0 {
+        return None;
+    }
+    view.sessions.get(index as usize - 1).cloned()
+}
+
+fn selected_level(view: &LogView) -> LogLevel {
+    let index = send(view.level_box, CB_GETCURSEL, 0, 0);
+    LEVELS.get(index.max(0) as usize).map_or(LogLevel::Debug, |(_, level)| *level)
+}
+
+/// Show the lines that pass the filters, staying at the bottom if the view
+/// was there and keeping the scroll position otherwise.
+fn render(view: &mut LogView) {
+    let session = selected_session(view);
+    let level = selected_level(view);
+    let shown: Vec<&str> = view
+        .lines
+        .iter()
+        .filter(|l| session.as_ref().is_none_or(|s| *s == l.session))
+        .filter(|l| l.level.map_or(level == LogLevel::Debug, |l| l <= level))
+        .map(|l| l.text.as_str())
+        .collect();
+    let text = if view.lines.is_empty() {
+        format!(
+            "No log files in {} yet.\r\nThey are written when ToastWindow.exe runs with --debug (add it to the hook commands).",
+            view.dir.display()
+        )
+    } else {
+        shown[shown.len().saturating_sub(MAX_LINES)..].join("\r\n")
+    };
+    if text == view.shown {
+        return;
+    }
+
+    let mut scroll = SCROLLINFO {
+        cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
+        fMask: SIF_ALL,
+        ..Default::default()
+    };
+    let at_bottom = view.shown.is_empty()
+        || unsafe { GetScrollInfo(view.text_box, SB_VERT, &mut scroll) }.is_err()
+        || scroll.nPos + scroll.nPage as i32 > scroll.nMax;
+    let first_line = send(view.text_box, EM_GETFIRSTVISIBLELINE, 0, 0);
+
+    let wide = crate::util::encode_wide(&text);
+    unsafe {
+        let _ = SetWindowTextW(view.text_box, PCWSTR(wide.as_ptr()));
+    }
+    if at_bottom {
+        let end = wide.len().saturating_sub(1);
+        send(view.text_box, EM_SETSEL, end, end as isize);
+        send(view.text_box, EM_SCROLLCARET, 0, 0);
+    } else {
+        send(view.text_box, EM_LINESCROLL, 0, first_line);
+    }
+    view.shown = text;
+}
+
+/// Read the files again if any changed, then show the result.
+fn refresh(view: &mut LogView) {
+    let stamps = log_files(&view.dir);
+    if stamps != view.stamps || view.shown.is_empty() {
+        view.lines = read_lines(&stamps);
+        view.stamps = stamps;
+        update_sessions(view);
+    }
+    render(view);
+}
+
+fn layout(hwnd: HWND) {
+    let mut rect = RECT::default();
+    unsafe {
+        let _ = GetClientRect(hwnd, &mut rect);
+    }
+    VIEW.with(|cell| {
+        let view = cell.borrow();
+        let Some(view) = view.as_ref() else { return };
+        let text_top = MARGIN * 2 + COMBO_HEIGHT;
+        unsafe {
+            let _ = MoveWindow(view.session_box, MARGIN, MARGIN, COMBO_WIDTH, DROPDOWN_HEIGHT, true);
+            let _ = MoveWindow(view.level_box, MARGIN * 2 + COMBO_WIDTH, MARGIN, COMBO_WIDTH / 2, DROPDOWN_HEIGHT, true);
+            let _ = MoveWindow(
+                view.text_box,
+                MARGIN,
+                text_top,
+                (rect.right - MARGIN * 2).max(0),
+                (rect.bottom - text_top - MARGIN).max(0),
+                true,
+            );
+        }
+    });
+}
+
+unsafe extern "system" fn wnd_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_SIZE => {
+            layout(hwnd);
+            LRESULT(0)
+        }
+
+        WM_TIMER if wparam.0 == TIMER_TAIL => {
+            VIEW.with(|cell| {
+                if let Some(view) = cell.borrow_mut().as_mut() {
+                    refresh(view);
+                }
+            });
+            LRESULT(0)
+        }
+
+        WM_COMMAND if (wparam.0 >> 16) as u32 == CBN_SELCHANGE => {
+            VIEW.with(|cell| {
+                if let Some(view) = cell.borrow_mut().as_mut() {
+                    render(view);
+                }
+            });
+            LRESULT(0)
+        }
+
+        WM_DESTROY => {
+            let _ = KillTimer(Some(hwnd), TIMER_TAIL);
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// The message font of the desktop, for the combo boxes.
+fn ui_font() -> HFONT {
+    let mut metrics = NONCLIENTMETRICSW {
+        cbSize: std::mem::size_of::<NONCLIENTMETRICSW>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        let read = SystemParametersInfoW(
+            SPI_GETNONCLIENTMETRICS,
+            metrics.cbSize,
+            Some(&mut metrics as *mut _ as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
+        match read {
+            Ok(()) => CreateFontIndirectW(&metrics.lfMessageFont),
+            Err(_) => HFONT(GetStockObject(DEFAULT_GUI_FONT).0),
+        }
+    }
+}
+
+fn create_child(parent: HWND, class: PCWSTR, style: WINDOW_STYLE, ex_style: WINDOW_EX_STYLE, id: usize, font: HFONT) -> HWND {
+    unsafe {
+        let instance = GetModuleHandleW(None).unwrap_or_default();
+        let hwnd = CreateWindowExW(
+            ex_style,
+            class,
+            None,
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP | style,
+            0, 0, 0, 0,
+            Some(parent),
+            Some(HMENU(id as *mut _)),
+            Some(instance.into()),
+            None,
+        )
+        .unwrap_or_default();
+        send(hwnd, WM_SETFONT, font.0 as usize, 1);
+        hwnd
+    }
+}
+
+/// Show the viewer until it's closed. Returns the process exit code.
+pub fn run() -> i32 {
+    let dir = crate::log::configured_dir();
+    unsafe {
+        let instance = GetModuleHandleW(None).unwrap_or_default();
+        let class_wide = crate::util::encode_wide(LOGS_CLASS_NAME);
+        let wc = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(wnd_proc),
+            hInstance: instance.into(),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize as *mut _),
+            lpszClassName: PCWSTR(class_wide.as_ptr()),
+            ..Default::default()
+        };
+        let _ = RegisterClassExW(&wc);
+
+        let title = crate::util::encode_wide(&format!("Claude Code Notify log - {}", dir.display()));
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            PCWSTR(class_wide.as_ptr()),
+            PCWSTR(title.as_ptr()),
+            WS_OVERLAPPEDWINDOW,
+            CW_USEDEFAULT, CW_USEDEFAULT, 1000, 640,
+            None, None, Some(instance.into()), None,
+        )
+        .unwrap_or_default();
+        if hwnd.is_invalid() {
+            crate::warn_log!("Log viewer window creation failed");
+            return 1;
+        }
+
+        let ui_font = ui_font();
+        let text_font = crate::util::encode_wide("Consolas");
+        let text_font = CreateFontW(
+            -14, 0, 0, 0, FW_NORMAL.0 as i32, 0, 0, 0,
+            FONT_CHARSET(0),
+            FONT_OUTPUT_PRECISION(0),
+            FONT_CLIP_PRECISION(0),
+            FONT_QUALITY(0),
+            0,
+            PCWSTR(text_font.as_ptr()),
+        );
+        let combo_style = WINDOW_STYLE(CBS_DROPDOWNLIST as u32) | WS_VSCROLL;
+        let session_box = create_child(hwnd, w!("ComboBox"), combo_style, WINDOW_EX_STYLE(0), ID_SESSION, ui_font);
+        let level_box = create_child(hwnd, w!("ComboBox"), combo_style, WINDOW_EX_STYLE(0), ID_LEVEL, ui_font);
+        let text_style = WINDOW_STYLE((ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL | ES_AUTOHSCROLL) as u32)
+            | WS_VSCROLL
+            | WS_HSCROLL;
+        let text_box = create_child(hwnd, w!("Edit"), text_style, WS_EX_CLIENTEDGE, ID_TEXT, text_font);
+        // The default is 30000 characters
+        send(text_box, EM_SETLIMITTEXT, 0, 0);
+
+        LEVELS.iter().for_each(|(name, _)| add_item(level_box, name));
+        send(level_box, CB_SETCURSEL, LEVELS.len() - 1, 0);
+        add_item(session_box, ALL_SESSIONS);
+        send(session_box, CB_SETCURSEL, 0, 0);
+
+        VIEW.with(|cell| {
+            *cell.borrow_mut() = Some(LogView {
+                session_box,
+                level_box,
+                text_box,
+                ui_font,
+                text_font,
+                dir,
+                stamps: Vec::new(),
+                lines: Vec::new(),
+                sessions: Vec::new(),
+                shown: String::new(),
+            });
+        });
+        layout(hwnd);
+        VIEW.with(|cell| {
+            if let Some(view) = cell.borrow_mut().as_mut() {
+                refresh(view);
+            }
+        });
+        let _ = ShowWindow(hwnd, SW_SHOWNORMAL);
+        let _ = SetForegroundWindow(hwnd);
+        SetTimer(Some(hwnd), TIMER_TAIL, TAIL_INTERVAL_MS, None);
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            // Tab between the controls
+            if IsDialogMessageW(hwnd, &msg).as_bool() {
+                continue;
+            }
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+
+        if let Some(view) = VIEW.with(|cell| cell.borrow_mut().take()) {
+            let _ = DeleteObject(view.ui_font.into());
+            let _ = DeleteObject(view.text_font.into());
+        }
+    }
+    0
+}
@@ -1,16 +1,43 @@
 #![windows_subsystem = "windows"]
 
+mod accessibility;
 mod activate;
+mod audio;
 mod assets;
 mod cli;
+mod config;
+mod conemu;
+mod desktop;
+mod crash;
+mod doctor;
+mod eventlog;
+mod history;
+mod job;
 mod json;
 mod log;
+mod logview;
+mod mute;
+mod payload;
+mod placement;
 mod process;
+mod record;
+mod sound;
 mod spawn;
+mod speech;
 mod state;
+mod stats;
+mod store;
+mod text;
+mod tmux;
 mod toast;
+mod totals;
+mod transcript;
+mod tray;
 mod uiautomation;
 mod util;
+mod waiting;
+mod watch;
+mod wezterm;
 
 use windows::Win32::Foundation::HWND;
 use windows::Win32::System::Com::*;
@@ -20,13 +47,7 @@ fn print_usage() {
     unsafe {
         let _ = windows::Win32::System::Console::AllocConsole();
     }
-    println!(
-        "Usage:\n  \
-         ToastWindow.exe --save      Save window state (UserPromptSubmit hook)\n  \
-         ToastWindow.exe --notify    Show notification (Stop hook)\n  \
-         ToastWindow.exe --input     Show input-required notification (Notification hook)\n\n\
-         Both modes read session_id from stdin JSON for state file isolation."
-    );
+    println!("{}", cli::HELP);
 }
 
 fn exe_path() -> String {
@@ -36,18 +57,22 @@ fn exe_path() -> String {
         .into_owned()
 }
 
-fn run_save_mode(immediate_hwnd: HWND) -> i32 {
-    let input = json::read_stdin_json();
-    let session_id = json::extract_string(&input, "session_id");
-    let prompt = json::extract_string(&input, "prompt");
+fn run_save_mode(immediate_hwnd: HWND, args: &cli::Args) -> i32 {
+    let input = payload::Payload::parse(&args.read_payload(), "UserPromptSubmit");
+    debug_log!("Payload: {}", input.describe());
+    let session_id = input.common.session_id.clone();
+    let prompt = input.prompt().to_string();
 
     if session_id.is_empty() {
         debug_log!("No session_id, skipping save");
         return 0;
     }
 
-    debug_log!("Session ID: {}", session_id);
-    debug_log!("Prompt: {}", prompt);
+    log::set_session(&session_id);
+    info_log!("Save mode");
+    if config::get().privacy.prompt == config::PromptPrivacy::Show {
+        debug_log!("Prompt: {}", prompt);
+    }
 
     // Use immediate_hwnd, fall back to GetForegroundWindow if invalid (SPEC 3.2)
     let hwnd = if !immediate_hwnd.is_invalid()
@@ -63,102 +88,522 @@ fn run_save_mode(immediate_hwnd: HWND) -> i32 {
     };
 
     // Detect Windows Terminal and get RuntimeId
-    let mut runtime_id = String::new();
+    let (mut runtime_id, mut tab_name) = (String::new(), String::new());
     let class = util::get_class_name(hwnd);
     debug_log!("Window class: {}", class);
 
     if class == "CASCADIA_HOSTING_WINDOW_CLASS" {
         debug_log!("Detected Windows Terminal, capturing tab RuntimeId");
-        runtime_id = uiautomation::get_selected_tab_runtime_id(hwnd);
-        debug_log!("RuntimeId: {}", runtime_id);
+        (runtime_id, tab_name) = uiautomation::get_selected_tab(hwnd);
+        debug_log!("RuntimeId: {}, tab: {}", runtime_id, tab_name);
+    }
+
+    // Find caller exe path for icon extraction; the session's app doesn't
+    // change, so the process tree is only walked on its first prompt
+    let caller = state::saved_caller(&session_id).unwrap_or_else(process::find_caller);
+    debug_log!("Caller: {:?}", caller);
+    let caller_path = caller.icon_path().to_string();
+
+    // VS Code-based editors: which integrated terminal the session runs in
+    let mut terminal_tab = String::new();
+    if class == "Chrome_WidgetWin_1" && process::is_vscode(&caller_path) {
+        terminal_tab = uiautomation::get_vscode_terminal_tab(hwnd);
+        debug_log!("Editor terminal tab: {:?}", terminal_tab);
+    }
+
+    // JetBrains IDEs: which project window, since one process has them all
+    let mut project = String::new();
+    if process::is_jetbrains(&caller_path) {
+        let title = util::get_window_text(hwnd);
+        project = state::project_from_title(&title).to_string();
+        if project.is_empty() {
+            let dir = std::path::Path::new(&input.common.cwd).file_name().unwrap_or_default();
+            project = dir.to_string_lossy().into_owned();
+        }
+        debug_log!("IDE project: {:?}", project);
     }
 
-    // Find caller exe path for icon extraction
-    let caller_path = process::find_caller_exe_path();
-    debug_log!("Caller exe path: {}", caller_path);
+    // ConEmu: which console, by its server process
+    let conemu_console = conemu::current_console();
+    if !conemu_console.is_empty() {
+        debug_log!("ConEmu console: {}", conemu_console);
+    }
+    let wezterm_pane = wezterm::current_pane();
+    if !wezterm_pane.is_empty() {
+        debug_log!("WezTerm pane: {}", wezterm_pane);
+    }
+    let tmux = tmux::Pane::current();
+    if let Some(pane) = &tmux {
+        debug_log!("tmux pane {} in {:?}", pane.pane, pane.distro);
+    }
 
     // Save state
-    state::save_state(&session_id, hwnd, &runtime_id, &caller_path, &prompt);
-    debug_log!("State saved to {:?}", state::state_file_path(&session_id));
+    state::save_state(
+        &session_id,
+        &state::State {
+            target_hwnd: hwnd,
+            wt_runtime_id: runtime_id,
+            wt_tab_name: tab_name,
+            terminal_tab,
+            project,
+            conemu_console,
+            wezterm_pane,
+            tmux,
+            icon_path: caller_path,
+            terminal_pid: caller.terminal_pid,
+            terminal_path: caller.terminal,
+            user_prompt: prompt,
+            ..Default::default()
+        },
+    );
+    debug_log!("State saved to {:?}", store::path());
+    // A new prompt answers whatever the session was waiting for
+    waiting::clear(&session_id);
+
+    tray::ensure_running();
 
     0
 }
 
-fn run_notify_mode(debug: bool) -> i32 {
-    let input = json::read_stdin_json();
-    let session_id = json::extract_string(&input, "session_id");
+fn run_notify_mode(args: &cli::Args) -> i32 {
+    let input = args.read_payload();
+    let payload = payload::Payload::parse(&input, "Stop");
+    debug_log!("Payload: {}", payload.describe());
+    let session_id = &payload.common.session_id;
 
     if session_id.is_empty() {
         debug_log!("No session_id for notify mode");
         return 1;
     }
 
-    debug_log!("Notify mode, session: {}", session_id);
+    log::set_session(session_id);
+    info_log!("Notify mode");
+    waiting::clear(session_id);
 
-    let mut cmd = format!("\"{}\" --notify-show --session \"{}\"", exe_path(), session_id);
-    if debug {
-        cmd.push_str(" --debug");
+    // A Stop hook made Claude go on; toasting every round of such a loop
+    // would repeat the same notification
+    if payload.stop_hook_active() && config::get().events.completion.hook_continue == config::HookContinue::Skip {
+        debug_log!("Stop hook continuation, not notifying");
+        return 0;
     }
 
-    debug_log!("Spawning: {}", cmd);
-    spawn::spawn_detached(&cmd);
+    spawn_notify_show(&input, false, args.debug);
     0
 }
 
-fn run_input_mode(debug: bool) -> i32 {
-    let input = json::read_stdin_json();
-    let session_id = json::extract_string(&input, "session_id");
-    let message = json::extract_string(&input, "message");
+fn run_input_mode(args: &cli::Args) -> i32 {
+    let input = args.read_payload();
+    let payload = payload::Payload::parse(&input, "Notification");
+    let session_id = &payload.common.session_id;
+    let message = payload.message();
 
     if session_id.is_empty() {
         debug_log!("No session_id for input mode");
         return 1;
     }
 
-    debug_log!("Input mode, session: {}, message: {}", session_id, message);
+    log::set_session(session_id);
+    info_log!("Input mode, message: {}", message);
+    waiting::set(session_id);
 
-    let mut cmd = format!(
-        "\"{}\" --notify-show --input-mode --session \"{}\"",
-        exe_path(),
-        session_id
-    );
-    if !message.is_empty() {
-        // Escape quotes in message (SPEC 16.2)
-        let escaped = message.replace('"', "\\\"");
-        cmd.push_str(&format!(" --message \"{}\"", escaped));
+    spawn_notify_show(&input, true, args.debug);
+    0
+}
+
+/// Wait before the second attempt to start a toast process.
+const SPAWN_RETRY_MS: u64 = 250;
+
+/// Start the detached `--notify-show` process, handing it the hook's JSON
+/// on stdin; only fixed flags go on the command line, so nothing from the
+/// payload needs quoting. If it can't be started, even on a second try, the
+/// toast is shown from this process instead, which then blocks the hook
+/// until it closes.
+fn spawn_notify_show(input: &str, input_mode: bool, debug: bool) {
+    let mut cmd = format!("{} --notify-show --stdin", spawn::quote_arg(&exe_path()));
+    if input_mode {
+        cmd.push_str(" --input-mode");
     }
     if debug {
         cmd.push_str(" --debug");
     }
 
-    debug_log!("Spawning: {}", cmd);
-    spawn::spawn_detached(&cmd);
+    debug_log!("Spawning: {} ({} payload bytes)", cmd, input.len());
+    let job = job::Job::toasts();
+    let mut spawned = spawn::spawn_detached_with_stdin(&cmd, input.as_bytes(), job.as_ref());
+    if spawned == spawn::Spawned::Failed {
+        std::thread::sleep(std::time::Duration::from_millis(SPAWN_RETRY_MS));
+        spawned = spawn::spawn_detached_with_stdin(&cmd, input.as_bytes(), job.as_ref());
+    }
+    if let Some(job) = &job {
+        let (active, total) = job.counts();
+        debug_log!("Toast processes: {} running, {} since the first", active, total);
+    }
+
+    match spawned {
+        spawn::Spawned::Started => {}
+        spawn::Spawned::JobFull => warn_log!("Too many toasts running, notification dropped"),
+        spawn::Spawned::Failed => {
+            warn_log!("Failed to spawn notify-show, showing the toast in-process");
+            let mut args = cli::Args {
+                mode: cli::Mode::NotifyShow,
+                debug,
+                input_mode,
+                ..Default::default()
+            };
+            args.apply_payload(input);
+            run_notify_show_mode(&args);
+        }
+    }
+}
+
+/// Prompts and notifications shown by --preview, in turn.
+const PREVIEW_PROMPTS: [&str; 3] = [
+    "Refactor the config loader so unknown keys are reported with their line number",
+    "Why does the release build fail on CI but not locally?",
+    "Add a test for the toast stacking order",
+];
+const PREVIEW_MESSAGES: [&str; 2] = ["Claude needs your permission to use Bash", "Claude is waiting for your input"];
+
+/// Pause between preview toasts, so they stack in order.
+const PREVIEW_STAGGER_MS: u64 = 200;
+
+/// Show sample toasts with the current config, each in its own process like
+/// real ones, so stacking can be seen too.
+fn run_preview_mode(args: &cli::Args) -> i32 {
+    let cwd = std::env::current_dir().map(|d| d.to_string_lossy().into_owned()).unwrap_or_default();
+    let samples: &[&str] = if args.input_mode { &PREVIEW_MESSAGES } else { &PREVIEW_PROMPTS };
+    let job = job::Job::toasts();
+    for i in 0..args.count.max(1) as usize {
+        let mut cmd = format!(
+            "{} --notify-show --sample --message {} --cwd {}",
+            spawn::quote_arg(&exe_path()),
+            spawn::quote_arg(samples[i % samples.len()]),
+            spawn::quote_arg(&cwd)
+        );
+        if args.input_mode {
+            cmd.push_str(" --input-mode");
+        }
+        if args.debug {
+            cmd.push_str(" --debug");
+        }
+        if i > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(PREVIEW_STAGGER_MS));
+        }
+        if spawn::spawn_detached_with_stdin(&cmd, b"", job.as_ref()) != spawn::Spawned::Started {
+            debug_log!("Preview toast {} not shown", i + 1);
+            return 1;
+        }
+    }
     0
 }
 
-fn run_cleanup_mode() -> i32 {
-    let input = json::read_stdin_json();
-    let session_id = json::extract_string(&input, "session_id");
+/// Session id of simulated hooks when --session isn't given.
+const SIMULATED_SESSION: &str = "simulated";
+
+/// Run a hook as Claude would, with a made-up payload: `prompt` saves the
+/// window state, `stop` and `input` show their notification. `stop` with
+/// --prompt saves that prompt first.
+fn run_simulate_mode(immediate_hwnd: HWND, args: &cli::Args) -> i32 {
+    let session = if args.session.is_empty() { SIMULATED_SESSION } else { &args.session };
+    let cwd = if args.cwd.is_empty() {
+        std::env::current_dir().map(|d| d.to_string_lossy().into_owned()).unwrap_or_default()
+    } else {
+        args.cwd.clone()
+    };
+    let hook = |event: &str, fields: serde_json::Value| {
+        let mut payload = serde_json::json!({
+            "session_id": session,
+            "transcript_path": "",
+            "cwd": cwd,
+            "hook_event_name": event,
+        });
+        if let (Some(payload), Some(fields)) = (payload.as_object_mut(), fields.as_object()) {
+            payload.extend(fields.clone());
+        }
+        debug_log!("Simulating {}", event);
+        cli::Args {
+            debug: args.debug,
+            payload: Some(payload.to_string()),
+            ..Default::default()
+        }
+    };
+    let prompt = if args.prompt.is_empty() { "Simulated prompt" } else { &args.prompt };
+    let save = || run_save_mode(immediate_hwnd, &hook("UserPromptSubmit", serde_json::json!({ "prompt": prompt })));
+
+    match args.simulate.as_str() {
+        "prompt" => save(),
+        "input" => {
+            let message = if args.message.is_empty() { "Claude needs your permission to use Bash" } else { &args.message };
+            run_input_mode(&hook("Notification", serde_json::json!({ "message": message, "title": "Claude Code" })))
+        }
+        _ => {
+            if !args.prompt.is_empty() {
+                save();
+            }
+            run_notify_mode(&hook("Stop", serde_json::json!({ "stop_hook_active": false })))
+        }
+    }
+}
+
+fn run_config_mode(config: &[String]) -> i32 {
+    util::attach_parent_console();
+    let result = match config {
+        [_, key] => config::lookup(key).map(|value| println!("{}", value)),
+        [_, key, value] => config::set(key, value).map(|path| println!("Set {} in {}", key, path.display())),
+        _ => {
+            config::open();
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Longest pause kept between replayed payloads.
+const REPLAY_MAX_GAP_MS: u64 = 2000;
+
+/// Run recorded payloads through their hooks again, in order, with the
+/// pauses between them (shortened to REPLAY_MAX_GAP_MS).
+fn run_replay_mode(immediate_hwnd: HWND, args: &cli::Args) -> i32 {
+    let recorded = match record::load(&args.replay) {
+        Ok(recorded) => recorded,
+        Err(e) => {
+            util::attach_parent_console();
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let mut failed = 0;
+    let mut previous = None;
+    for (i, entry) in recorded.iter().enumerate() {
+        if let Some(previous) = previous {
+            let gap = entry.time_ms.saturating_sub(previous).min(REPLAY_MAX_GAP_MS);
+            std::thread::sleep(std::time::Duration::from_millis(gap));
+        }
+        previous = Some(entry.time_ms);
+
+        let hook = cli::Args {
+            mode: cli::command_mode(&entry.command).unwrap_or_default(),
+            debug: args.debug,
+            payload: Some(entry.payload.clone()),
+            ..Default::default()
+        };
+        debug_log!("Replaying {} of {}: {}", i + 1, recorded.len(), entry.command);
+        let code = match hook.mode {
+            cli::Mode::Save => run_save_mode(immediate_hwnd, &hook),
+            cli::Mode::Notify => run_notify_mode(&hook),
+            cli::Mode::Input => run_input_mode(&hook),
+            cli::Mode::Cleanup => run_cleanup_mode(&hook),
+            _ => {
+                debug_log!("Can't replay {:?}", entry.command);
+                1
+            }
+        };
+        failed += (code != 0) as usize;
+    }
+    if failed > 0 { 1 } else { 0 }
+}
+
+fn run_cleanup_mode(args: &cli::Args) -> i32 {
+    let input = payload::Payload::parse(&args.read_payload(), "SessionEnd");
+    debug_log!("Payload: {}", input.describe());
+    let session_id = &input.common.session_id;
 
     if !session_id.is_empty() {
         debug_log!("Cleanup: deleting state for session {}", session_id);
-        state::delete_state(&session_id);
+        state::delete_state(session_id);
+    }
+    0
+}
+
+/// How long `--dismiss-all` gives the toasts to fade out before ending their processes.
+const DISMISS_WAIT_MS: u64 = 2000;
+
+fn run_dismiss_all_mode() -> i32 {
+    let count = toast::dismiss_all();
+    debug_log!("Dismissed {} toast(s)", count);
+
+    // Toast processes still around after the fade (hung, or without a
+    // window yet) are ended
+    if let Some(job) = job::Job::open() {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(DISMISS_WAIT_MS);
+        while job.counts().0 > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        let left = job.process_ids();
+        if !left.is_empty() {
+            debug_log!("Ending toast processes {:?}", left);
+            job.terminate();
+        }
+    }
+    0
+}
+
+fn run_mute_mode(duration: Option<&str>) -> i32 {
+    util::attach_parent_console();
+    let duration = match duration {
+        Some(text) => match mute::parse_duration(text) {
+            Some(d) => Some(d),
+            None => {
+                println!("Invalid duration: {} (use e.g. 30m, 2h, 1h30m)", text);
+                return 1;
+            }
+        },
+        None => None,
+    };
+    mute::mute(duration);
+    match duration {
+        Some(d) => println!("Notifications muted for {}.", util::format_duration(d)),
+        None => println!("Notifications muted until --unmute."),
+    }
+    0
+}
+
+fn run_unmute_mode() -> i32 {
+    util::attach_parent_console();
+    mute::unmute();
+    println!("Notifications unmuted.");
+    0
+}
+
+fn run_activate_mode(session_id: &str) -> i32 {
+    if session_id.is_empty() {
+        debug_log!("No session ID for activate mode");
+        return 1;
+    }
+    if activate::activate_session(session_id) { 0 } else { 1 }
+}
+
+fn run_list_sessions_mode(json: bool) -> i32 {
+    util::attach_parent_console();
+
+    // Project comes from the session's latest notification
+    let history = history::load();
+    let mut sessions: Vec<(String, Option<std::time::SystemTime>)> = state::list_sessions()
+        .into_iter()
+        .map(|id| {
+            let saved = state::saved_at(&id);
+            (id, saved)
+        })
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.1));
+
+    let rows: Vec<serde_json::Value> = sessions
+        .iter()
+        .map(|(id, saved)| {
+            let st = state::load_state(id);
+            let project = history
+                .iter()
+                .rev()
+                .find(|e| &e.session == id)
+                .map(|e| e.cwd.clone())
+                .unwrap_or_default();
+            let age = saved.and_then(|t| t.elapsed().ok()).map(|d| d.as_secs());
+            let alive = st.target_hwnd != HWND::default();
+            serde_json::json!({
+                "session": id,
+                "project": project,
+                "prompt": st.user_prompt,
+                "age_secs": age,
+                "window_alive": alive,
+            })
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::Value::Array(rows));
+        return 0;
+    }
+
+    if rows.is_empty() {
+        println!("No sessions.");
+    }
+    for row in &rows {
+        let age = row["age_secs"]
+            .as_u64()
+            .map(|s| format!("{} ago", util::format_duration(std::time::Duration::from_secs(s))))
+            .unwrap_or_else(|| "unknown age".to_string());
+        let window = if row["window_alive"].as_bool() == Some(true) { "window alive" } else { "window gone" };
+        println!("{}  {}  {}", row["session"].as_str().unwrap_or_default(), age, window);
+        let project = row["project"].as_str().unwrap_or_default();
+        if !project.is_empty() {
+            println!("    Project: {}", project);
+        }
+        let prompt = row["prompt"].as_str().unwrap_or_default();
+        if !prompt.is_empty() {
+            println!("    Prompt: {}", util::excerpt(prompt, config::get().toast.excerpt_columns));
+        }
     }
     0
 }
 
+fn run_history_mode(json: bool) -> i32 {
+    if !json {
+        history::open();
+        return 0;
+    }
+    util::attach_parent_console();
+    let mut entries = history::load();
+    entries.reverse();
+    match serde_json::to_string(&entries) {
+        Ok(text) => {
+            println!("{}", text);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn run_reset_position_mode() -> i32 {
+    debug_log!("Clearing remembered placement");
+    placement::clear();
+    0
+}
+
 fn run_notify_show_mode(args: &cli::Args) -> i32 {
-    if args.session.is_empty() {
+    // Without a session only an ad-hoc toast can be shown, from --title/--message
+    let ad_hoc = args.title.is_some() || !args.message.is_empty();
+    if args.session.is_empty() && !ad_hoc {
         debug_log!("No session ID for notify-show mode");
         return 1;
     }
 
-    debug_log!("NotifyShow mode, session: {}", args.session);
+    info_log!("NotifyShow mode");
 
     // 1. Load state from file
-    let st = state::load_state(&args.session);
-    debug_log!("Loaded state: HWND={:?}, RuntimeId={}, IconPath={}, Prompt={}",
-        st.target_hwnd, st.wt_runtime_id, st.icon_path, st.user_prompt);
+    let st = if args.session.is_empty() { state::State::default() } else { state::load_state(&args.session) };
+    debug_log!("Loaded state: HWND={:?}, RuntimeId={}, IconPath={}, Prompt: {} chars",
+        st.target_hwnd, st.wt_runtime_id, st.icon_path, st.user_prompt.chars().count());
+
+    // What the session's transcript says about the finished task
+    let transcript = if args.input_mode { None } else { transcript::summarize(&args.transcript_path) };
+    if let Some(t) = &transcript {
+        debug_log!("Transcript: {} turns, {} tokens, error: {}", t.turns, t.usage.total(), t.error);
+    }
+
+    // The turn's usage: from the payload when it has it, else the transcript
+    let turn_usage = args
+        .usage
+        .or(transcript.as_ref().map(|t| t.turn_usage))
+        .filter(|u| !args.input_mode && u.total() > 0);
+    let turn_cost = args.cost_usd.or(transcript.as_ref().and_then(|t| t.turn_cost_usd));
+    if let Some(usage) = &turn_usage {
+        totals::record(&args.cwd, usage, turn_cost);
+    }
+    let footer = match turn_usage {
+        Some(usage) if config::get().toast.usage_footer => format_usage(usage.total(), turn_cost),
+        // A sample shows what the footer looks like
+        None if args.sample && !args.input_mode && config::get().toast.usage_footer => format_usage(12_400, Some(0.31)),
+        _ => String::new(),
+    };
 
     // 2. Determine notification content (SPEC 14.1-14.2)
     let (title, message) = if args.input_mode {
@@ -169,73 +614,221 @@ fn run_notify_show_mode(args: &cli::Args) -> i32 {
         };
         ("Input Required".to_string(), msg)
     } else {
-        let msg = if !st.user_prompt.is_empty() {
+        let msg = if !args.message.is_empty() {
+            args.message.clone()
+        } else if !st.user_prompt.is_empty() {
             st.user_prompt.clone()
         } else {
             "Task completed".to_string()
         };
-        ("Claude Code".to_string(), msg)
+        // Several prompts finished in a row: say which one this is
+        let count = st.earlier_prompts.len() + 1;
+        let title = if count > 1 && !st.user_prompt.is_empty() {
+            format!("Claude Code · prompt {}/{}", count, count)
+        } else {
+            "Claude Code".to_string()
+        };
+        let title = if args.hook_continued && config::get().events.completion.hook_continue == config::HookContinue::Mark {
+            format!("{} · continued", title)
+        } else {
+            title
+        };
+        let title = if transcript.as_ref().is_some_and(|t| t.error) { format!("{} · error", title) } else { title };
+        (title, msg)
     };
+    let title = args.title.clone().unwrap_or(title);
+
+    // Middle-click copies the full prompt and message, not the sanitized text shown
+    let clipboard_text = build_clipboard_text(&st.user_prompt, &message);
 
     // 3. Sanitize message (SPEC 14.3)
     let message = sanitize_message(&message);
-    debug_log!("Title: {}, Message: {}", title, message);
+    // The prompt only reaches the log and the plaintext history when it may be stored as-is
+    let private = !args.input_mode
+        && !st.user_prompt.is_empty()
+        && config::get().privacy.prompt != config::PromptPrivacy::Show;
+    let logged = if private { "Task completed" } else { message.as_str() };
+    debug_log!("Title: {}, Message: {}", title, logged);
+
+    if !args.sample {
+        history::append(&history::Entry::new(&args.session, args.input_mode, &title, logged, &args.cwd));
+    }
+    // From the prompt to now, for the stats; ad-hoc and sample toasts aren't counted
+    let counted = !args.sample && !args.session.is_empty();
+    let task_time = if args.input_mode { None } else { state::saved_at(&args.session).and_then(|t| t.elapsed().ok()) };
+
+    // Recorded in the history, but nothing shown or played
+    if mute::is_muted() && !args.sample {
+        debug_log!("Muted, not showing");
+        if counted {
+            stats::record(&args.session, &args.cwd, args.input_mode, task_time, None);
+        }
+        return 0;
+    }
+
+    // An elevated window can't be activated from here; say so, a click flashes it
+    let target = if st.wt_hwnd != HWND::default() { st.wt_hwnd } else { st.target_hwnd };
+    let title = if activate::blocked_by_uipi(target) { format!("{} · elevated window", title) } else { title };
+
+    let details = build_details(&st, transcript.as_ref(), &args.cwd, &args.session, state::saved_at(&args.session));
+
+    // Timeout: --persistent > --duration > config
+    let cfg = config::get();
+    let timeout = if args.persistent {
+        toast::TimeoutPolicy::Persistent
+    } else if let Some(ms) = args.duration {
+        toast::TimeoutPolicy::Timed(ms)
+    } else if cfg.events.get(args.input_mode).persistent {
+        toast::TimeoutPolicy::Persistent
+    } else {
+        toast::TimeoutPolicy::Timed(cfg.display_ms(args.input_mode))
+    };
+    debug_log!("Timeout: {:?}", timeout);
 
     // 4. Discover assets
     let discovered = assets::discover_assets();
-    debug_log!("Sound: {:?}, Font: {:?}, Icon: {:?}",
-        discovered.sound_file, discovered.font_file, discovered.default_icon_path);
-
-    // 5. Extract icon from saved exe path
-    let icon = assets::extract_icon(&st.icon_path);
+    debug_log!("Sounds: {:?}, Font: {:?}, Icon: {:?}",
+        discovered.sound_files, discovered.font_files, discovered.default_icon_path);
+
+    // 5. Icon: --icon, else the project's image, else the configured one,
+    // else the saved exe's (cached) icon
+    let image = match (&args.icon, cfg.project(&args.cwd)) {
+        (Some(icon), _) => icon.clone(),
+        (None, Some((dir, project))) if !project.icon.is_empty() => {
+            std::path::Path::new(dir).join(&project.icon).to_string_lossy().into_owned()
+        }
+        _ => cfg.toast.icon.clone(),
+    };
+    let icon = if image.is_empty() {
+        assets::app_icon(&st.icon_path, toast::ICON_SIZE)
+    } else {
+        assets::load_icon_file(&image, toast::ICON_SIZE)
+    };
     debug_log!("App icon: {:?}", icon);
 
-    // 6. Load custom font
-    let font_family = if let Some(ref font_path) = discovered.font_file {
-        assets::load_font(font_path).unwrap_or_else(|| "Segoe UI".to_string())
+    // 6. Load custom fonts; [fonts] lists override their order
+    let families: Vec<String> = discovered.font_files.iter().filter_map(|path| assets::load_font(path)).collect();
+    text::load_fonts(&discovered.font_files);
+    let chain = |configured: &Vec<String>| if configured.is_empty() { families.clone() } else { configured.clone() };
+    let (title_fonts, body_fonts) = (chain(&cfg.fonts.title), chain(&cfg.fonts.body));
+    debug_log!("Fonts: title {:?}, body {:?}", title_fonts, body_fonts);
+
+    // 7. Play sound (--sound, else a discovered one), and speak if enabled
+    let sound_file = if args.no_sound { None } else { args.sound.clone().or_else(|| sound::choose(&discovered.sound_files)) };
+    let sound = if args.no_sound { None } else { sound::play(&sound_file) };
+    let speech = if cfg.speaks(args.input_mode) {
+        speech::start(speech::announcement(args.input_mode, &args.cwd))
     } else {
-        "Segoe UI".to_string()
+        None
     };
-    debug_log!("Font family: {}", font_family);
 
-    // 7. Play sound
-    assets::play_sound(&discovered.sound_file);
+    if cfg.activation.notify_flashes > 0 {
+        activate::flash_window(target, cfg.activation.notify_flashes);
+    }
 
     // 8. Show toast (blocks until closed)
-    toast::show_toast(toast::ToastParams {
+    let outcome = toast::show_toast(toast::ToastParams {
         title,
         message,
+        details,
+        footer,
+        clipboard_text,
+        sound_file,
         input_mode: args.input_mode,
-        font_family,
+        timeout,
+        title_fonts,
+        body_fonts,
         icon,
         default_icon_path: discovered.default_icon_path.unwrap_or_default(),
-        target_hwnd: st.target_hwnd,
-        wt_hwnd: st.wt_hwnd,
-        wt_runtime_id: st.wt_runtime_id,
+        target: st.target(),
     });
+    debug_log!("Toast outcome: {:?}", outcome);
+    if counted && outcome.is_some() {
+        stats::record(&args.session, &args.cwd, args.input_mode, task_time, outcome);
+    }
+    // Clicking an input toast goes to answer it
+    if args.input_mode && outcome == Some(toast::Outcome::Clicked) && !args.session.is_empty() {
+        waiting::clear(&args.session);
+    }
 
     // 9. Cleanup
     if !icon.is_invalid() {
         unsafe { let _ = windows::Win32::UI::WindowsAndMessaging::DestroyIcon(icon); }
     }
-    if let Some(ref font_path) = discovered.font_file {
+    for font_path in &discovered.font_files {
         assets::unload_font(font_path);
     }
+    for playing in [sound, speech].into_iter().flatten() {
+        let _ = playing.join();
+    }
 
     0
 }
 
-fn sanitize_message(msg: &str) -> String {
-    // Replace newlines with space
-    let mut s: String = msg.chars().map(|c| {
-        if c == '\n' || c == '\r' { ' ' } else { c }
-    }).collect();
+/// The prompt followed by the notification message, or just one of them when
+/// they are the same (completion toasts show the prompt itself).
+fn build_clipboard_text(prompt: &str, message: &str) -> String {
+    let prompt = prompt.trim();
+    let message = message.trim();
+    if prompt.is_empty() || prompt == message {
+        message.to_string()
+    } else {
+        format!("{}\n\n{}", prompt, message)
+    }
+}
 
-    // Truncate at 35 chars + "..."
-    if s.chars().count() > 35 {
-        s = s.chars().take(35).collect::<String>() + "...";
+/// Text for the toast's expandable details section.
+fn build_details(
+    st: &state::State,
+    transcript: Option<&transcript::Summary>,
+    cwd: &str,
+    session_id: &str,
+    saved_at: Option<std::time::SystemTime>,
+) -> String {
+    let mut lines = Vec::new();
+    if !st.user_prompt.is_empty() {
+        lines.push(format!("Prompt: {}", st.user_prompt.trim()));
+    }
+    if let Some(t) = transcript.filter(|t| !t.last_reply.is_empty()) {
+        let reply = sanitize_message(&t.last_reply);
+        lines.push(format!("Reply: {}", util::excerpt(&reply, config::get().toast.excerpt_columns)));
+    }
+    if !st.earlier_prompts.is_empty() {
+        lines.push("Earlier prompts:".to_string());
+        for (i, prompt) in st.earlier_prompts.iter().enumerate().rev() {
+            lines.push(format!("  {}. {}", i + 1, sanitize_message(prompt)));
+        }
+    }
+    if !cwd.is_empty() {
+        lines.push(format!("Project: {}", cwd));
+    }
+    lines.push(format!("Session: {}", session_id));
+    if let Some(t) = transcript.filter(|t| t.turns > 0) {
+        lines.push(format!("Turns: {}", t.turns));
+    }
+    if let Some(elapsed) = saved_at.and_then(|t| t.elapsed().ok()) {
+        lines.push(format!("Elapsed: {}", util::format_duration(elapsed)));
+    }
+    lines.join("\n")
+}
+
+/// "12.4k tokens · $0.31", or without the cost when it isn't known.
+fn format_usage(tokens: u64, cost_usd: Option<f64>) -> String {
+    let tokens = match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1e3),
+        _ => format!("{:.1}M", tokens as f64 / 1e6),
+    };
+    match cost_usd {
+        Some(cost) => format!("{} tokens · ${:.2}", tokens, cost),
+        None => format!("{} tokens", tokens),
     }
-    s
+}
+
+fn sanitize_message(msg: &str) -> String {
+    // Collapse newlines and runs of whitespace into single spaces.
+    // Wrapping and ellipsizing happen at draw time (toast::MESSAGE_FORMAT).
+    msg.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
 fn main() {
@@ -249,21 +842,90 @@ fn main() {
         }
     }
 
-    let args = cli::parse_args();
-    log::init(args.debug);
+    let mut args = match cli::parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            // Not 2: a hook exiting with 2 blocks Claude
+            util::attach_parent_console();
+            eprintln!("{}\nRun ToastWindow.exe --help for usage.", e);
+            std::process::exit(1);
+        }
+    };
+    log::init(args.debug, cli::command_name(args.mode));
+    config::init();
+    log::open();
+    crash::install();
+    args.load_payload();
+    // The hooks set it once they've read their payload; with per-session log
+    // files an empty session here would pick the shared file too early
+    let payload_later = matches!(args.mode, cli::Mode::Save | cli::Mode::Notify | cli::Mode::Input);
+    if !payload_later || !args.session.is_empty() {
+        log::set_session(&args.session);
+    }
 
     let exit_code = match args.mode {
-        cli::Mode::Save => run_save_mode(immediate_hwnd),
-        cli::Mode::Notify => run_notify_mode(args.debug),
-        cli::Mode::Input => run_input_mode(args.debug),
+        cli::Mode::Save => run_save_mode(immediate_hwnd, &args),
+        cli::Mode::Notify => run_notify_mode(&args),
+        cli::Mode::Input => run_input_mode(&args),
         cli::Mode::NotifyShow => run_notify_show_mode(&args),
-        cli::Mode::Cleanup => run_cleanup_mode(),
+        cli::Mode::Cleanup => run_cleanup_mode(&args),
+        cli::Mode::ResetPosition => run_reset_position_mode(),
+        cli::Mode::History => run_history_mode(args.json),
+        cli::Mode::Stats => {
+            util::attach_parent_console();
+            stats::run(args.since, args.json)
+        }
+        cli::Mode::Tray => tray::run(),
+        cli::Mode::Logs => logview::run(),
+        cli::Mode::DismissAll => run_dismiss_all_mode(),
+        cli::Mode::ListSessions => run_list_sessions_mode(args.json),
+        cli::Mode::Activate => run_activate_mode(&args.session),
+        cli::Mode::Mute => run_mute_mode(args.mute_for.as_deref()),
+        cli::Mode::Unmute => run_unmute_mode(),
+        cli::Mode::Preview => run_preview_mode(&args),
+        cli::Mode::Config => run_config_mode(&args.config),
+        cli::Mode::RegisterEventLog => {
+            util::attach_parent_console();
+            match eventlog::register() {
+                Ok(()) => {
+                    println!("Event source ClaudeCodeNotify registered.");
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            }
+        }
+        cli::Mode::Replay => run_replay_mode(immediate_hwnd, &args),
+        cli::Mode::Simulate => run_simulate_mode(immediate_hwnd, &args),
+        cli::Mode::Doctor => {
+            util::attach_parent_console();
+            doctor::run(args.json)
+        }
+        cli::Mode::Help => {
+            util::attach_parent_console();
+            println!("{}", cli::HELP);
+            0
+        }
+        cli::Mode::Version => {
+            util::attach_parent_console();
+            println!("{}", cli::version());
+            0
+        }
         cli::Mode::None => {
             print_usage();
             1
         }
     };
 
+    // After the work, so the hooks aren't slowed down; other commands
+    // shouldn't create the database or check on sessions
+    if matches!(args.mode, cli::Mode::Save | cli::Mode::Notify | cli::Mode::Input | cli::Mode::Cleanup) {
+        store::maintain();
+    }
+    log::close();
+
     unsafe {
         CoUninitialize();
     }
@@ -0,0 +1,90 @@
+//! Muting notifications (--mute, --unmute, tray menu).
+//!
+//! The flag lives in %LOCALAPPDATA%\claude-code-notify\mute.json so every
+//! hook invocation sees it. While muted, notifications are still recorded in
+//! the history but no toast, sound, or speech comes up.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MuteState {
+    /// Unix time in seconds when the mute ends; None mutes until --unmute.
+    until: Option<u64>,
+}
+
+fn mute_path() -> Option<std::path::PathBuf> {
+    crate::config::local_dir().map(|d| d.join("mute.json"))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Mute for `duration`, or until unmuted when None.
+pub fn mute(duration: Option<Duration>) {
+    let Some(path) = mute_path() else { return };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    // A duration past the end of time mutes until unmuted
+    let state = MuteState { until: duration.and_then(|d| now_secs().checked_add(d.as_secs())) };
+    if let Ok(text) = serde_json::to_string(&state) {
+        if let Err(e) = std::fs::write(&path, text) {
+            crate::warn_log!("Failed to write mute state: {}", e);
+        }
+    }
+}
+
+pub fn unmute() {
+    if let Some(path) = mute_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// None when not muted; Some(None) when muted until unmuted; Some(Some(left))
+/// with the time left otherwise. An expired mute is cleared.
+pub fn status() -> Option<Option<Duration>> {
+    let text = std::fs::read_to_string(mute_path()?).ok()?;
+    let state: MuteState = serde_json::from_str(&text).ok()?;
+    match state.until {
+        None => Some(None),
+        Some(until) if until > now_secs() => Some(Some(Duration::from_secs(until - now_secs()))),
+        Some(_) => {
+            unmute();
+            None
+        }
+    }
+}
+
+pub fn is_muted() -> bool {
+    status().is_some()
+}
+
+/// Parse a duration such as "30m", "2h", "1h30m", "90s", or "1d".
+/// A bare number is minutes. None if invalid or too long to count.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_ascii_lowercase();
+    if let Ok(minutes) = text.parse::<u64>() {
+        return Some(Duration::from_secs(minutes.checked_mul(60)?));
+    }
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    (number.is_empty() && total > 0).then(|| Duration::from_secs(total))
+}
@@ -0,0 +1,157 @@
+//! Typed Claude Code hook payloads.
+//!
+//! Each hook gets a JSON object on stdin with some common fields and some
+//! specific to its event (`hook_event_name`). The object is parsed once into
+//! a `Payload`; fields an event should have but lacks are logged, not fatal.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::json::Document;
+
+/// Fields every hook event has.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Common {
+    pub session_id: String,
+    pub transcript_path: String,
+    pub cwd: String,
+    pub hook_event_name: String,
+    /// Token usage and cost of the turn, when the payload carries them;
+    /// otherwise they come from the transcript.
+    pub usage: Option<crate::transcript::Usage>,
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UserPromptSubmit {
+    pub prompt: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Stop {
+    /// Claude is already continuing because of a Stop hook.
+    #[serde(default)]
+    pub stop_hook_active: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Notification {
+    pub message: String,
+    #[serde(default)]
+    pub title: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PreToolUse {
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SessionEnd {
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    UserPromptSubmit(UserPromptSubmit),
+    Stop(Stop),
+    Notification(Notification),
+    PreToolUse(PreToolUse),
+    SessionEnd(SessionEnd),
+    /// An event name this version doesn't know; only the common fields apply.
+    Other,
+}
+
+#[derive(Debug)]
+pub struct Payload {
+    pub common: Common,
+    pub event: Event,
+}
+
+impl Payload {
+    /// Parse a hook's JSON. `expected` is the event the mode is registered
+    /// for, assumed when the payload doesn't name one (e.g. typed by hand).
+    pub fn parse(text: &str, expected: &str) -> Payload {
+        let doc = Document::parse(text);
+        let name = doc.string("hook_event_name");
+        let value = doc.into_value();
+        if !value.is_object() {
+            crate::debug_log!("Hook payload is not a JSON object ({} bytes)", text.len());
+        }
+        let mut common: Common = serde_json::from_value(value.clone()).unwrap_or_default();
+        if common.session_id.is_empty() {
+            crate::debug_log!("Hook payload has no session_id");
+        }
+        if name.is_empty() {
+            common.hook_event_name = expected.to_string();
+        } else if !expected.is_empty() && name != expected {
+            crate::debug_log!("Expected a {} payload, got {}", expected, name);
+        }
+
+        let event = match common.hook_event_name.as_str() {
+            "UserPromptSubmit" => Event::UserPromptSubmit(fields(&common.hook_event_name, value)),
+            "Stop" => Event::Stop(fields(&common.hook_event_name, value)),
+            "Notification" => Event::Notification(fields(&common.hook_event_name, value)),
+            "PreToolUse" => Event::PreToolUse(fields(&common.hook_event_name, value)),
+            "SessionEnd" => Event::SessionEnd(fields(&common.hook_event_name, value)),
+            name => {
+                crate::debug_log!("Unknown hook event {:?}", name);
+                Event::Other
+            }
+        };
+        Payload { common, event }
+    }
+
+    /// The submitted prompt (UserPromptSubmit), else empty.
+    pub fn prompt(&self) -> &str {
+        match &self.event {
+            Event::UserPromptSubmit(e) => &e.prompt,
+            _ => "",
+        }
+    }
+
+    /// The notification text (Notification), else empty.
+    pub fn message(&self) -> &str {
+        match &self.event {
+            Event::Notification(e) => &e.message,
+            _ => "",
+        }
+    }
+
+    /// The Stop hook fired for a turn a Stop hook made Claude continue.
+    pub fn stop_hook_active(&self) -> bool {
+        matches!(&self.event, Event::Stop(e) if e.stop_hook_active)
+    }
+
+    /// One line about the event for the log; leaves out the prompt itself.
+    pub fn describe(&self) -> String {
+        let detail = match &self.event {
+            Event::UserPromptSubmit(e) => format!("prompt of {} chars", e.prompt.chars().count()),
+            Event::Stop(e) => format!("stop_hook_active={}", e.stop_hook_active),
+            Event::Notification(e) => format!("{:?}: {:?}", e.title, e.message),
+            Event::PreToolUse(e) => {
+                let keys: Vec<&str> =
+                    e.tool_input.as_object().map_or(Vec::new(), |m| m.keys().map(String::as_str).collect());
+                format!("{} {:?}", e.tool_name, keys)
+            }
+            Event::SessionEnd(e) => format!("reason {:?}", e.reason),
+            Event::Other => String::new(),
+        };
+        format!(
+            "{} for session {} in {:?}, transcript {:?}: {}",
+            self.common.hook_event_name, self.common.session_id, self.common.cwd, self.common.transcript_path, detail
+        )
+    }
+}
+
+/// The event-specific fields, or their defaults (logged) if some are missing.
+fn fields<T: serde::de::DeserializeOwned + Default>(event: &str, value: Value) -> T {
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        crate::debug_log!("Incomplete {} payload: {}", event, e);
+        T::default()
+    })
+}
@@ -0,0 +1,55 @@
+//! Remembered toast placement.
+//!
+//! Dropping a dragged toast stores where it landed: the monitor, the nearest
+//! corner of that monitor's placement area, and the distance from that corner's
+//! edges. Later toasts open at the same spot. Stored per machine in
+//! %LOCALAPPDATA%\claude-code-notify\placement.json.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Placement {
+    /// Monitor device name, e.g. \\.\DISPLAY2.
+    pub monitor: String,
+    pub top: bool,
+    pub left: bool,
+    /// Distance from the left/right edge of the anchored corner.
+    pub offset_x: i32,
+    /// Distance from the top/bottom edge of the anchored corner.
+    pub offset_y: i32,
+}
+
+fn placement_path() -> Option<std::path::PathBuf> {
+    crate::config::local_dir().map(|d| d.join("placement.json"))
+}
+
+/// Load the remembered placement, if any.
+pub fn load() -> Option<Placement> {
+    let text = std::fs::read_to_string(placement_path()?).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(p) => Some(p),
+        Err(e) => {
+            crate::debug_log!("Invalid placement file: {}", e);
+            None
+        }
+    }
+}
+
+/// Remember a placement for future toasts.
+pub fn save(placement: &Placement) {
+    let Some(path) = placement_path() else { return };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Ok(text) = serde_json::to_string_pretty(placement) {
+        let _ = std::fs::write(&path, text);
+        crate::debug_log!("Saved placement {:?}", placement);
+    }
+}
+
+/// Forget the remembered placement; toasts go back to the configured position.
+pub fn clear() {
+    if let Some(path) = placement_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
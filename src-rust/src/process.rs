@@ -1,13 +1,17 @@
 //! Process tree walking to find the caller application.
 //!
 //! Walks up the process tree (max 10 levels) to find the first non-shell process,
-//! which is used to extract an icon for the toast notification.
+//! which is used to extract an icon for the toast notification. When that's a
+//! terminal emulator, the walk goes on for an IDE the terminal runs under. The
+//! tree comes from one process snapshot, walked in memory.
 
 use windows::Win32::Foundation::*;
+use windows::Win32::Security::*;
 use windows::Win32::System::Diagnostics::ToolHelp::*;
 use windows::Win32::System::Threading::*;
 
 /// Shell/runtime processes to skip (exact match, case-insensitive).
+/// `[caller] skip_add`/`skip_remove` adjust it.
 const SKIP_LIST: &[&str] = &[
     // Windows shells
     "cmd", "powershell", "pwsh", "conhost", "explorer",
@@ -29,6 +33,7 @@ const SKIP_LIST: &[&str] = &[
 
 /// Known application processes (immediate match).
 /// Match is exact OR prefix-dash (e.g. "code" matches "code-insiders").
+/// `[caller] apps_add`/`apps_remove` adjust it.
 const KNOWN_APPS: &[&str] = &[
     // VS Code variants
     "code", "code-insiders", "codium", "cursor", "windsurf",
@@ -41,56 +46,140 @@ const KNOWN_APPS: &[&str] = &[
     "tabby", "wezterm", "wezterm-gui",
 ];
 
-/// Find the caller application's exe path by walking up the process tree.
-pub fn find_caller_exe_path() -> String {
+/// Terminal emulators: a caller in this list is the terminal host, and the
+/// walk goes on for a known app above it.
+const TERMINALS: &[&str] = &[
+    "windowsterminal", "wt", "conemu", "conemu64",
+    "tabby", "wezterm", "wezterm-gui", "alacritty",
+];
+
+/// The apps a session runs in: the terminal emulator hosting its shell and
+/// the app above that (usually an IDE). Either may be empty.
+#[derive(Debug, Clone, Default)]
+pub struct Caller {
+    pub terminal: String,
+    pub terminal_pid: u32,
+    pub app: String,
+}
+
+impl Caller {
+    /// The exe the toast icon comes from: the IDE, else the terminal.
+    pub fn icon_path(&self) -> &str {
+        if self.app.is_empty() { &self.terminal } else { &self.app }
+    }
+}
+
+/// Find the caller applications by walking up the process tree.
+pub fn find_caller() -> Caller {
+    let mut caller = Caller::default();
     let mut pid = unsafe { GetCurrentProcessId() };
+    let processes = process_map();
+    let mut created = creation_time(pid);
 
     for _ in 0..10 {
-        let parent_pid = get_parent_pid(pid);
+        let parent_pid = processes.get(&pid).map_or(0, |p| p.0);
         if parent_pid == 0 || parent_pid == pid {
             break;
         }
 
+        // A "parent" started after its child is an unrelated process that got
+        // the PID of the real, exited parent
+        let parent_created = creation_time(parent_pid);
+        if let (Some(child), Some(parent)) = (created, parent_created) {
+            if parent > child {
+                crate::debug_log!("PID {} was reused, stopping at its child", parent_pid);
+                break;
+            }
+        }
+        pid = parent_pid;
+        // Without its time, the child's still bounds the next ancestor
+        created = parent_created.or(created);
+
+        // Names come from the snapshot; only a match needs the full path
+        let Some((_, exe_file)) = processes.get(&parent_pid) else { break };
+        let exe_name = file_name_without_ext(exe_file).to_lowercase();
+        let known = is_known_app(&exe_name);
+        if is_skipped(&exe_name) && !known {
+            continue;
+        }
+        // Above the terminal only known apps count: the rest is the shell
+        // (explorer, svchost, ...) that started it
+        if !caller.terminal.is_empty() && !known {
+            continue;
+        }
+
+        // Known app (prefix-dash matching) or unknown but valid process - use it
         let exe_path = get_process_exe_path(parent_pid);
         if exe_path.is_empty() {
-            pid = parent_pid;
             continue;
         }
+        if caller.terminal.is_empty() && TERMINALS.contains(&exe_name.as_str()) {
+            caller.terminal = exe_path;
+            caller.terminal_pid = parent_pid;
+            continue;
+        }
+        caller.app = exe_path;
+        break;
+    }
 
-        let exe_name = file_name_without_ext(&exe_path).to_lowercase();
+    caller
+}
 
-        // Check known apps first (prefix-dash matching)
-        if is_known_app(&exe_name) {
-            return exe_path;
-        }
+/// Whether `exe_path` is a VS Code-based editor (integrated terminal tabs).
+pub fn is_vscode(exe_path: &str) -> bool {
+    let exe_name = file_name_without_ext(exe_path).to_lowercase();
+    ["code", "codium", "cursor", "windsurf"]
+        .iter()
+        .any(|app| exe_name == *app || exe_name.starts_with(&format!("{}-", app)))
+}
 
-        // Check skip list (exact match)
-        if SKIP_LIST.contains(&exe_name.as_str()) {
-            pid = parent_pid;
-            continue;
-        }
+/// Whether `exe_path` is a JetBrains IDE (one process for all project windows).
+pub fn is_jetbrains(exe_path: &str) -> bool {
+    let exe_name = file_name_without_ext(exe_path).to_lowercase();
+    let base = exe_name.strip_suffix("64").unwrap_or(&exe_name);
+    [
+        "idea", "webstorm", "pycharm", "rider", "goland", "clion",
+        "phpstorm", "rubymine", "rustrover", "datagrip",
+    ]
+    .contains(&base)
+}
 
-        // Unknown but valid process - use it
-        return exe_path;
-    }
+/// A built-in list with the configured names added and removed.
+fn with_changes<'a>(builtin: &[&'a str], add: &'a [String], remove: &[String]) -> Vec<&'a str> {
+    builtin
+        .iter()
+        .copied()
+        .chain(add.iter().map(String::as_str))
+        .filter(|name| !remove.iter().any(|r| r.eq_ignore_ascii_case(name)))
+        .collect()
+}
 
-    String::new()
+fn is_skipped(exe_name: &str) -> bool {
+    let caller = &crate::config::get().caller;
+    with_changes(SKIP_LIST, &caller.skip_add, &caller.skip_remove)
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(exe_name))
 }
 
 fn is_known_app(exe_name: &str) -> bool {
-    for app in KNOWN_APPS {
-        if exe_name == *app || exe_name.starts_with(&format!("{}-", app)) {
+    let caller = &crate::config::get().caller;
+    for app in with_changes(KNOWN_APPS, &caller.apps_add, &caller.apps_remove) {
+        let app = app.to_lowercase();
+        if exe_name == app || exe_name.starts_with(&format!("{}-", app)) {
             return true;
         }
     }
     false
 }
 
-fn get_parent_pid(pid: u32) -> u32 {
+/// Parent PID and exe file name of every running process, from a single
+/// snapshot.
+fn process_map() -> std::collections::HashMap<u32, (u32, String)> {
+    let mut processes = std::collections::HashMap::new();
     unsafe {
         let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
             Ok(h) => h,
-            Err(_) => return 0,
+            Err(_) => return processes,
         };
 
         let mut entry = PROCESSENTRY32W {
@@ -100,10 +189,9 @@ fn get_parent_pid(pid: u32) -> u32 {
 
         if Process32FirstW(snapshot, &mut entry).is_ok() {
             loop {
-                if entry.th32ProcessID == pid {
-                    let _ = CloseHandle(snapshot);
-                    return entry.th32ParentProcessID;
-                }
+                let len = entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(entry.szExeFile.len());
+                let exe_file = String::from_utf16_lossy(&entry.szExeFile[..len]);
+                processes.insert(entry.th32ProcessID, (entry.th32ParentProcessID, exe_file));
                 if Process32NextW(snapshot, &mut entry).is_err() {
                     break;
                 }
@@ -111,11 +199,38 @@ fn get_parent_pid(pid: u32) -> u32 {
         }
 
         let _ = CloseHandle(snapshot);
-        0
     }
+    processes
 }
 
-fn get_process_exe_path(pid: u32) -> String {
+/// Whether process `pid` is still running. Processes we may not open count
+/// as running.
+pub fn is_running(pid: u32) -> bool {
+    unsafe {
+        let handle = match OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
+            Ok(h) => h,
+            Err(e) => return e.code() == ERROR_ACCESS_DENIED.to_hresult(),
+        };
+        let mut code = 0u32;
+        let result = GetExitCodeProcess(handle, &mut code);
+        let _ = CloseHandle(handle);
+        result.is_err() || code == STILL_ACTIVE.0 as u32
+    }
+}
+
+/// When the process was started (FILETIME ticks), if it can be queried.
+fn creation_time(pid: u32) -> Option<u64> {
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let (mut created, mut exited, mut kernel, mut user) = Default::default();
+        let result = GetProcessTimes(handle, &mut created, &mut exited, &mut kernel, &mut user);
+        let _ = CloseHandle(handle);
+        result.ok()?;
+        Some(((created.dwHighDateTime as u64) << 32) | created.dwLowDateTime as u64)
+    }
+}
+
+pub fn get_process_exe_path(pid: u32) -> String {
     unsafe {
         let handle = match OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) {
             Ok(h) => h,
@@ -139,6 +254,41 @@ fn get_process_exe_path(pid: u32) -> String {
     }
 }
 
+/// Mandatory integrity level of a process: 0x2000 for a normal process,
+/// 0x3000 for an elevated one. None when the process or its token can't be opened.
+pub fn integrity_level(pid: u32) -> Option<u32> {
+    unsafe {
+        let process = if pid == GetCurrentProcessId() {
+            GetCurrentProcess()
+        } else {
+            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?
+        };
+        let mut token = HANDLE::default();
+        let opened = OpenProcessToken(process, TOKEN_QUERY, &mut token);
+        if pid != GetCurrentProcessId() {
+            let _ = CloseHandle(process);
+        }
+        opened.ok()?;
+
+        // TOKEN_MANDATORY_LABEL followed by the SID it points to
+        let mut buf = [0u64; 16];
+        let mut len = 0u32;
+        let read = GetTokenInformation(
+            token,
+            TokenIntegrityLevel,
+            Some(buf.as_mut_ptr() as *mut _),
+            std::mem::size_of_val(&buf) as u32,
+            &mut len,
+        );
+        let _ = CloseHandle(token);
+        read.ok()?;
+
+        let sid = (*(buf.as_ptr() as *const TOKEN_MANDATORY_LABEL)).Label.Sid;
+        let count = *GetSidSubAuthorityCount(sid);
+        (count > 0).then(|| *GetSidSubAuthority(sid, count as u32 - 1))
+    }
+}
+
 fn file_name_without_ext(path: &str) -> String {
     let name = path
         .rsplit(|c| c == '\\' || c == '/')
@@ -0,0 +1,77 @@
+//! Recording hook payloads (--record) for --replay.
+//!
+//! With --record on a hook's command line, the JSON it got is appended as
+//! is, with the time and the command, to
+//! %LOCALAPPDATA%\claude-code-notify\payloads.jsonl. --replay runs such a
+//! file through the hooks again, so a payload that caused a bug can be
+//! reproduced later.
+
+use serde::{Deserialize, Serialize};
+
+/// One hook invocation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recorded {
+    /// Unix time in milliseconds.
+    pub time_ms: u64,
+    /// The command the hook ran: "save", "notify", "input" or "cleanup".
+    pub command: String,
+    /// The payload text as read, even if it isn't valid JSON.
+    pub payload: String,
+}
+
+fn recording_path() -> Option<std::path::PathBuf> {
+    crate::config::local_dir().map(|d| d.join("payloads.jsonl"))
+}
+
+/// Append a payload, unless the prompt in it may not be stored as-is.
+pub fn append(command: &str, payload: &str) {
+    use std::io::Write;
+
+    let Some(path) = recording_path() else { return };
+    if crate::config::get().privacy.prompt != crate::config::PromptPrivacy::Show {
+        crate::debug_log!("Not recording the payload, [privacy] prompt isn't \"show\"");
+        return;
+    }
+    let entry = Recorded {
+        time_ms: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        command: command.to_string(),
+        payload: payload.to_string(),
+    };
+    let Ok(line) = serde_json::to_string(&entry) else { return };
+
+    let _lock = crate::state::Lock::acquire("Local\\ClaudeCodeNotify.Record");
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = written {
+        crate::warn_log!("Failed to record payload to {:?}: {}", path, e);
+    }
+}
+
+/// The recorded payloads in a file, oldest first. Lines that aren't
+/// recordings are skipped.
+pub fn load(path: &str) -> Result<Vec<Recorded>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Can't read {}: {}", path, e))?;
+    let mut skipped = 0;
+    let recorded: Vec<Recorded> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let entry = serde_json::from_str(line).ok();
+            skipped += entry.is_none() as usize;
+            entry
+        })
+        .collect();
+    if skipped > 0 {
+        crate::debug_log!("{}: {} lines aren't recorded payloads", path, skipped);
+    }
+    Ok(recorded)
+}
@@ -0,0 +1,307 @@
+//! Notification sound playback.
+//!
+//! PlaySoundW only plays WAV and has no volume control. Compressed files
+//! (MP3, FLAC, M4A, and OGG where a codec is installed) are decoded to PCM
+//! through Media Foundation, and below full volume the samples are scaled;
+//! either way the result is played from memory with SND_MEMORY.
+
+use std::ops::Range;
+
+use windows::core::PCWSTR;
+use windows::Win32::Media::Audio::*;
+use windows::Win32::Media::MediaFoundation::*;
+use windows::Win32::System::Com::CoTaskMemFree;
+
+use crate::config::SoundPick;
+
+/// Sound file extensions played from assets\sound.
+pub const EXTENSIONS: [&str; 5] = ["wav", "mp3", "flac", "ogg", "m4a"];
+
+/// Pick the sound for this notification from the files in assets\sound,
+/// per `[sound] pick`.
+pub fn choose(files: &[String]) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+    let cfg = &crate::config::get().sound;
+    let index = match cfg.pick {
+        SoundPick::First => 0,
+        SoundPick::Random => {
+            let seed = cfg.seed.unwrap_or_else(|| {
+                let nanos = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0);
+                nanos ^ std::process::id() as u64
+            });
+            (splitmix64(seed) % files.len() as u64) as usize
+        }
+        SoundPick::Rotate => next_rotation(files.len()),
+    };
+    Some(files[index].clone())
+}
+
+/// SplitMix64: one well-mixed step, enough to spread a time-based seed.
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Round-robin index, persisted in %LOCALAPPDATA% since every toast is a new process.
+fn next_rotation(count: usize) -> usize {
+    let Some(path) = crate::config::local_dir().map(|d| d.join("sound-rotation.txt")) else { return 0 };
+    let index = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| text.trim().parse::<usize>().ok())
+        .map_or(0, |last| (last + 1) % count);
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(&path, index.to_string());
+    index
+}
+
+/// Play a notification sound (SPEC 12.2). With `[sound] device` set, the
+/// sound plays on a thread of its own; join the returned handle before exiting.
+pub fn play(wav_path: &Option<String>) -> Option<std::thread::JoinHandle<()>> {
+    let cfg = &crate::config::get().sound;
+    let volume = cfg.volume.min(100);
+    if volume == 0 {
+        return None;
+    }
+
+    if !cfg.device.is_empty() {
+        let wav = wav_path.as_deref().and_then(load_wav).map(|mut wav| {
+            if volume < 100 {
+                let _ = apply_gain(&mut wav, volume as f32 / 100.0);
+            }
+            wav
+        });
+        if let Some(wav) = wav {
+            let device = cfg.device.clone();
+            return Some(std::thread::spawn(move || crate::audio::play_on_device(&device, &wav)));
+        }
+    }
+
+    if crate::audio::should_skip("") {
+        return None;
+    }
+
+    if let Some(path) = wav_path {
+        let is_wav = path.to_ascii_lowercase().ends_with(".wav");
+        let played = if is_wav && volume == 100 {
+            play_file(path)
+        } else {
+            play_scaled(path, volume as f32 / 100.0)
+        };
+        if played {
+            return None;
+        }
+    }
+
+    // Fallback: system beep
+    #[link(name = "user32")]
+    extern "system" {
+        fn MessageBeep(utype: u32) -> i32;
+    }
+    unsafe {
+        MessageBeep(0x40); // MB_ICONASTERISK
+    }
+    None
+}
+
+fn play_file(path: &str) -> bool {
+    let path_wide = crate::util::encode_wide(path);
+    unsafe { PlaySoundW(PCWSTR(path_wide.as_ptr()), None, SND_FILENAME | SND_ASYNC).as_bool() }
+}
+
+/// The WAV image `play_scaled` is playing from.
+static PLAYING: std::sync::Mutex<Option<Box<[u8]>>> = std::sync::Mutex::new(None);
+
+/// Play the file from memory with its samples scaled by `gain`. Plays at
+/// full volume when the sample format can't be scaled.
+fn play_scaled(path: &str, gain: f32) -> bool {
+    let Some(mut wav) = load_wav(path) else { return false };
+    if gain < 1.0 && apply_gain(&mut wav, gain).is_none() {
+        crate::debug_log!("Unsupported WAV format for volume control: {}", path);
+    }
+    // SND_ASYNC plays from the buffer after this returns, so it's kept until
+    // the next sound replaces it, once the one playing from it is stopped
+    let mut playing = PLAYING.lock().unwrap_or_else(|e| e.into_inner());
+    unsafe {
+        let _ = PlaySoundW(PCWSTR::null(), None, SND_PURGE);
+    }
+    let wav = playing.insert(wav.into_boxed_slice());
+    unsafe { PlaySoundW(PCWSTR(wav.as_ptr() as *const u16), None, SND_MEMORY | SND_ASYNC).as_bool() }
+}
+
+/// The file as a WAV image: read as is for .wav, decoded otherwise.
+fn load_wav(path: &str) -> Option<Vec<u8>> {
+    if path.to_ascii_lowercase().ends_with(".wav") {
+        return match std::fs::read(path) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                crate::warn_log!("Failed to read sound {}: {}", path, e);
+                None
+            }
+        };
+    }
+    match unsafe { decode(path) } {
+        Ok(wav) => Some(wav),
+        Err(e) => {
+            crate::warn_log!("Failed to decode sound {}: {:?}", path, e);
+            None
+        }
+    }
+}
+
+/// Decode a compressed audio file to a PCM WAV image with Media Foundation.
+unsafe fn decode(path: &str) -> windows::core::Result<Vec<u8>> {
+    MFStartup(MF_VERSION, MFSTARTUP_LITE)?;
+    let result = decode_started(path);
+    let _ = MFShutdown();
+    result
+}
+
+unsafe fn decode_started(path: &str) -> windows::core::Result<Vec<u8>> {
+    const AUDIO: u32 = MF_SOURCE_READER_FIRST_AUDIO_STREAM.0 as u32;
+
+    let path_wide = crate::util::encode_wide(path);
+    let reader = MFCreateSourceReaderFromURL(PCWSTR(path_wide.as_ptr()), None)?;
+    reader.SetStreamSelection(MF_SOURCE_READER_ALL_STREAMS.0 as u32, false)?;
+    reader.SetStreamSelection(AUDIO, true)?;
+
+    // Ask the decoder for PCM; it picks the sample rate and channels
+    let pcm = MFCreateMediaType()?;
+    pcm.SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Audio)?;
+    pcm.SetGUID(&MF_MT_SUBTYPE, &MFAudioFormat_PCM)?;
+    reader.SetCurrentMediaType(AUDIO, None, &pcm)?;
+
+    let actual = reader.GetCurrentMediaType(AUDIO)?;
+    let mut format = std::ptr::null_mut();
+    let mut format_size = 0u32;
+    MFCreateWaveFormatExFromMFMediaType(
+        &actual,
+        &mut format,
+        Some(&mut format_size),
+        MFWaveFormatExConvertFlag_Normal.0 as u32,
+    )?;
+    let fmt = std::slice::from_raw_parts(format as *const u8, format_size as usize).to_vec();
+    CoTaskMemFree(Some(format as *const _));
+
+    let mut data = Vec::new();
+    loop {
+        let mut flags = 0u32;
+        let mut sample = None;
+        reader.ReadSample(AUDIO, 0, None, Some(&mut flags), None, Some(&mut sample))?;
+        if let Some(sample) = sample {
+            let buffer = sample.ConvertToContiguousBuffer()?;
+            let mut ptr = std::ptr::null_mut();
+            let mut len = 0u32;
+            buffer.Lock(&mut ptr, None, Some(&mut len))?;
+            data.extend_from_slice(std::slice::from_raw_parts(ptr, len as usize));
+            let _ = buffer.Unlock();
+        }
+        if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+            break;
+        }
+    }
+
+    let mut wav = Vec::with_capacity(20 + fmt.len() + 8 + data.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&fmt);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+    Ok(wav)
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Locate the bodies of the "fmt " and "data" chunks of a RIFF WAV image.
+pub fn wav_chunks(wav: &[u8]) -> Option<(Range<usize>, Range<usize>)> {
+    if wav.get(0..4)? != b"RIFF" || wav.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut fmt = None;
+    let mut pos = 12;
+    while pos + 8 <= wav.len() {
+        let id = &wav[pos..pos + 4];
+        let size = read_u32(wav, pos + 4)? as usize;
+        let body = pos + 8;
+        let chunk = body..(body + size).min(wav.len());
+        match id {
+            b"fmt " => fmt = Some(chunk),
+            b"data" => return Some((fmt?, chunk)),
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos = body + size + (size & 1);
+    }
+    None
+}
+
+/// Scale the samples of a RIFF WAV in place. Handles 8/16/24/32-bit integer
+/// and 32-bit float PCM, including WAVE_FORMAT_EXTENSIBLE.
+fn apply_gain(wav: &mut [u8], gain: f32) -> Option<()> {
+    let (fmt, data) = wav_chunks(wav)?;
+    let mut tag = read_u16(wav, fmt.start)?;
+    let bits = read_u16(wav, fmt.start + 14)?;
+    if tag == 0xFFFE {
+        // WAVE_FORMAT_EXTENSIBLE: the real tag starts the SubFormat GUID
+        tag = read_u16(wav, fmt.start + 24)?;
+    }
+    scale_samples(&mut wav[data], tag, bits, gain)
+}
+
+fn scale_samples(data: &mut [u8], tag: u16, bits: u16, gain: f32) -> Option<()> {
+    const PCM: u16 = 1;
+    const FLOAT: u16 = 3;
+    match (tag, bits) {
+        (PCM, 8) => {
+            // Unsigned, centered on 128
+            for s in data.iter_mut() {
+                *s = ((*s as f32 - 128.0) * gain + 128.0).round() as u8;
+            }
+        }
+        (PCM, 16) => {
+            for s in data.chunks_exact_mut(2) {
+                let v = i16::from_le_bytes([s[0], s[1]]) as f32 * gain;
+                s.copy_from_slice(&(v.round() as i16).to_le_bytes());
+            }
+        }
+        (PCM, 24) => {
+            for s in data.chunks_exact_mut(3) {
+                let v = (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 * gain;
+                s.copy_from_slice(&(v.round() as i32).to_le_bytes()[..3]);
+            }
+        }
+        (PCM, 32) => {
+            for s in data.chunks_exact_mut(4) {
+                let v = i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f64 * gain as f64;
+                s.copy_from_slice(&(v.round() as i32).to_le_bytes());
+            }
+        }
+        (FLOAT, 32) => {
+            for s in data.chunks_exact_mut(4) {
+                let v = f32::from_le_bytes([s[0], s[1], s[2], s[3]]) * gain;
+                s.copy_from_slice(&v.to_le_bytes());
+            }
+        }
+        _ => return None,
+    }
+    Some(())
+}
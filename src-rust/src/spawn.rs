@@ -1,12 +1,80 @@
 //! Detached child process spawning.
 //!
 //! Uses CreateProcessW with CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS
-//! to spawn a child that outlives the parent.
+//! to spawn a child that outlives the parent. Helper tools that we wait on
+//! are run with CREATE_NO_WINDOW instead. Data for the child goes through
+//! an inherited pipe on its stdin, not the command line.
 
+use windows::Win32::Foundation::{CloseHandle, SetHandleInformation, HANDLE, HANDLE_FLAG_INHERIT};
+use windows::Win32::Storage::FileSystem::WriteFile;
+use windows::Win32::System::Pipes::CreatePipe;
 use windows::Win32::System::Threading::*;
 use windows::Win32::UI::WindowsAndMessaging::SW_HIDE;
 use windows::core::PWSTR;
 
+/// Quote a single argument for the command line so CommandLineToArgvW
+/// reads it back verbatim (backslashes before a quote are doubled).
+pub fn quote_arg(arg: &str) -> String {
+    let mut out = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                out.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                out.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            out.push(c);
+        }
+    }
+    out.extend(std::iter::repeat_n('\\', backslashes * 2));
+    out.push('"');
+    out
+}
+
+/// Run a console tool without a window and wait up to `timeout_ms` for it.
+/// Returns its exit code, or None if it didn't start or finish in time.
+pub fn run_hidden(cmd_line: &str, timeout_ms: u32) -> Option<u32> {
+    let mut cmd_wide: Vec<u16> = cmd_line.encode_utf16().chain(std::iter::once(0)).collect();
+    let si = STARTUPINFOW {
+        cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+        ..Default::default()
+    };
+    let mut pi = PROCESS_INFORMATION::default();
+
+    unsafe {
+        CreateProcessW(
+            None,
+            Some(PWSTR(cmd_wide.as_mut_ptr())),
+            None,
+            None,
+            false,
+            CREATE_NO_WINDOW,
+            None,
+            None,
+            &si,
+            &mut pi,
+        )
+        .ok()?;
+        let mut code = None;
+        if WaitForSingleObject(pi.hProcess, timeout_ms) == windows::Win32::Foundation::WAIT_OBJECT_0 {
+            let mut exit = 0u32;
+            if GetExitCodeProcess(pi.hProcess, &mut exit).is_ok() {
+                code = Some(exit);
+            }
+        }
+        let _ = windows::Win32::Foundation::CloseHandle(pi.hProcess);
+        let _ = windows::Win32::Foundation::CloseHandle(pi.hThread);
+        code
+    }
+}
+
 /// Spawn a detached child process with the given command line.
 /// Returns true on success.
 pub fn spawn_detached(cmd_line: &str) -> bool {
@@ -44,6 +112,141 @@ pub fn spawn_detached(cmd_line: &str) -> bool {
             }
             true
         }
-        Err(_) => false,
+        Err(e) => {
+            crate::error_log!("CreateProcessW failed: {:?} ({})", e, cmd_line);
+            false
+        }
+    }
+}
+
+/// Outcome of `spawn_detached_with_stdin`.
+#[derive(Debug, PartialEq)]
+pub enum Spawned {
+    /// Running, with all of the input.
+    Started,
+    /// Ended again because the job already holds as many processes as allowed.
+    JobFull,
+    /// Not started, or ended again because the input couldn't be handed over.
+    Failed,
+}
+
+/// Spawn a detached child process with `input` on its stdin. The child gets
+/// the read end of an anonymous pipe and no other handle. It starts
+/// suspended, and only runs once all of the input is written and, with a
+/// `job`, it's in the job; otherwise it's ended again.
+pub fn spawn_detached_with_stdin(cmd_line: &str, input: &[u8], job: Option<&crate::job::Job>) -> Spawned {
+    let mut cmd_wide: Vec<u16> = cmd_line.encode_utf16().chain(std::iter::once(0)).collect();
+
+    let (mut read, mut write) = (HANDLE::default(), HANDLE::default());
+    unsafe {
+        if let Err(e) = CreatePipe(&mut read, &mut write, None, input.len() as u32) {
+            crate::debug_log!("CreatePipe failed: {:?}", e);
+            return Spawned::Failed;
+        }
+        // Inheritable, for the handle list below to pass it on
+        if let Err(e) = SetHandleInformation(read, HANDLE_FLAG_INHERIT.0, HANDLE_FLAG_INHERIT) {
+            crate::debug_log!("SetHandleInformation failed: {:?}", e);
+            let _ = CloseHandle(read);
+            let _ = CloseHandle(write);
+            return Spawned::Failed;
+        }
+    }
+
+    // Only the read end goes to the child: without a handle list it would
+    // inherit every inheritable handle of this process, including the hook's
+    // stdout and stderr, and Claude Code would wait on them until the toast
+    // closed
+    let mut size = 0usize;
+    unsafe {
+        let _ = InitializeProcThreadAttributeList(None, 1, None, &mut size);
+    }
+    let mut attributes = vec![0usize; size.div_ceil(std::mem::size_of::<usize>())];
+    let attribute_list = LPPROC_THREAD_ATTRIBUTE_LIST(attributes.as_mut_ptr().cast());
+    let inherited = [read];
+    let listed = unsafe {
+        InitializeProcThreadAttributeList(Some(attribute_list), 1, None, &mut size).and_then(|()| {
+            let updated = UpdateProcThreadAttribute(
+                attribute_list,
+                0,
+                PROC_THREAD_ATTRIBUTE_HANDLE_LIST as usize,
+                Some(inherited.as_ptr().cast()),
+                std::mem::size_of_val(&inherited),
+                None,
+                None,
+            );
+            if updated.is_err() {
+                DeleteProcThreadAttributeList(attribute_list);
+            }
+            updated
+        })
+    };
+    if let Err(e) = listed {
+        crate::debug_log!("Handle list failed: {:?}", e);
+        unsafe {
+            let _ = CloseHandle(read);
+            let _ = CloseHandle(write);
+        }
+        return Spawned::Failed;
+    }
+
+    let si = STARTUPINFOEXW {
+        StartupInfo: STARTUPINFOW {
+            cb: std::mem::size_of::<STARTUPINFOEXW>() as u32,
+            dwFlags: STARTF_USESHOWWINDOW | STARTF_USESTDHANDLES,
+            wShowWindow: SW_HIDE.0 as u16,
+            hStdInput: read,
+            ..Default::default()
+        },
+        lpAttributeList: attribute_list,
+    };
+    let mut pi = PROCESS_INFORMATION::default();
+
+    unsafe {
+        let result = CreateProcessW(
+            None,
+            Some(PWSTR(cmd_wide.as_mut_ptr())),
+            None,
+            None,
+            true,
+            CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS | CREATE_SUSPENDED | EXTENDED_STARTUPINFO_PRESENT,
+            None,
+            None,
+            &si.StartupInfo,
+            &mut pi,
+        );
+        DeleteProcThreadAttributeList(attribute_list);
+        let _ = CloseHandle(read);
+        if let Err(e) = result {
+            crate::error_log!("CreateProcessW failed: {:?} ({})", e, cmd_line);
+            let _ = CloseHandle(write);
+            return Spawned::Failed;
+        }
+        // Hand over the input while the child is still suspended, so a short
+        // write never leaves a child running on half a payload. The pipe's
+        // buffer holds all of it, so this doesn't wait for the child to read.
+        // Closing the write end gives the child EOF after the input
+        let mut written = 0u32;
+        let ok = WriteFile(write, Some(input), Some(&mut written), None).is_ok();
+        let _ = CloseHandle(write);
+        let delivered = ok && written as usize == input.len();
+        if !delivered {
+            crate::debug_log!("Wrote {} of {} payload bytes", written, input.len());
+        }
+
+        let admitted = delivered && job.is_none_or(|job| job.assign(pi.hProcess));
+        if admitted {
+            ResumeThread(pi.hThread);
+        } else {
+            let _ = TerminateProcess(pi.hProcess, 1);
+        }
+        let _ = CloseHandle(pi.hProcess);
+        let _ = CloseHandle(pi.hThread);
+        if !delivered {
+            return Spawned::Failed;
+        }
+        if !admitted {
+            return Spawned::JobFull;
+        }
+        Spawned::Started
     }
 }
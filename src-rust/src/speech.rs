@@ -0,0 +1,79 @@
+//! Spoken announcements through the Windows speech engine (SAPI ISpVoice).
+//!
+//! A short sentence such as "Claude finished in project foo" is spoken on a
+//! background thread while the toast is up. Off by default; see `[speech]`.
+
+use windows::core::PCWSTR;
+use windows::Win32::Media::Speech::{ISpVoice, SpVoice, SPF_IS_NOT_XML};
+use windows::Win32::System::Com::*;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+/// Sentence to speak for an event, naming the project when the cwd is known.
+pub fn announcement(input_mode: bool, cwd: &str) -> String {
+    let what = if input_mode { "Claude needs your input" } else { "Claude finished" };
+    let project = cwd
+        .trim_end_matches(['\\', '/'])
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or_default();
+    if project.is_empty() {
+        what.to_string()
+    } else {
+        format!("{} in project {}", what, project)
+    }
+}
+
+/// Parse "HH:MM" into minutes after midnight.
+fn parse_clock(text: &str) -> Option<u32> {
+    let (h, m) = text.trim().split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+/// Whether the local time falls in a range like "22:00-07:00" (which wraps past midnight).
+pub fn in_quiet_hours(range: &str) -> bool {
+    if range.trim().is_empty() {
+        return false;
+    }
+    let Some((start, end)) = range.split_once('-').and_then(|(a, b)| Some((parse_clock(a)?, parse_clock(b)?)))
+    else {
+        crate::debug_log!("Invalid quiet_hours: {}", range);
+        return false;
+    };
+    let now = unsafe { GetLocalTime() };
+    let now = now.wHour as u32 * 60 + now.wMinute as u32;
+    if start <= end {
+        (start..end).contains(&now)
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// Start speaking `text` in the background, unless quiet hours are in effect.
+/// Join the handle before the process exits, or the speech is cut off.
+pub fn start(text: String) -> Option<std::thread::JoinHandle<()>> {
+    let cfg = &crate::config::get().speech;
+    if in_quiet_hours(&cfg.quiet_hours) {
+        crate::debug_log!("Quiet hours, not speaking");
+        return None;
+    }
+    let (rate, volume) = (cfg.rate.clamp(-10, 10), cfg.volume.min(100));
+    Some(std::thread::spawn(move || unsafe {
+        if let Err(e) = speak(&text, rate, volume) {
+            crate::debug_log!("Speech failed: {:?}", e);
+        }
+    }))
+}
+
+unsafe fn speak(text: &str, rate: i32, volume: u16) -> windows::core::Result<()> {
+    let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+    let voice: ISpVoice = CoCreateInstance(&SpVoice, None, CLSCTX_ALL)?;
+    let _ = voice.SetRate(rate);
+    let _ = voice.SetVolume(volume);
+    let wide = crate::util::encode_wide(text);
+    // Synchronous on this thread; the project name is plain text, not SSML
+    let result = voice.Speak(PCWSTR(wide.as_ptr()), SPF_IS_NOT_XML.0 as u32, None);
+    drop(voice);
+    CoUninitialize();
+    result
+}
@@ -1,18 +1,55 @@
-//! State file save/load/delete.
+//! Session state save/load/delete.
 //!
-//! State file: %TEMP%\claude-notify-{session_id}.txt
-//! Format: 4 lines (HWND, RuntimeId, caller exe path, user prompt)
+//! Each session's state is a row of the sessions table of the store: a
+//! versioned JSON document (see `StateFile`), with when it was saved and
+//! which process the session runs in beside it, so listing and sweeping
+//! sessions don't have to parse every document. State files of older
+//! versions (in the state folder, or left in %TEMP%) are imported, and ones
+//! in the old text format (.txt: HWND, RuntimeId, caller exe path, then the
+//! prompt) converted.
+//!
+//! `--save`, `--notify-show` and cleanup can run at the same time for one
+//! session, so every read-modify-write holds a per-session named mutex.
+
+use serde::{Deserialize, Serialize};
+use windows::core::{BOOL, HSTRING};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND, LPARAM, RECT, TRUE, WAIT_FAILED, WAIT_TIMEOUT};
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONULL};
+use windows::Win32::System::Threading::{CreateMutexW, ReleaseMutex, WaitForSingleObject};
+use windows::Win32::UI::WindowsAndMessaging::*;
 
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::IsWindow;
+use crate::config::PromptPrivacy;
 
 /// Data stored in and loaded from the state file.
 pub struct State {
     pub target_hwnd: HWND,
     pub wt_hwnd: HWND,
     pub wt_runtime_id: String,
+    /// Title of the WT tab, to find it by when the RuntimeId is stale.
+    pub wt_tab_name: String,
+    /// Title of the integrated-terminal tab, when the window is an editor.
+    pub terminal_tab: String,
+    /// JetBrains project the session runs in; its window is looked up by it.
+    pub project: String,
+    /// Server PID of the ConEmu console the session runs in.
+    pub conemu_console: String,
+    /// Id of the WezTerm pane the session runs in.
+    pub wezterm_pane: String,
+    /// tmux pane the session runs in (WSL).
+    pub tmux: Option<crate::tmux::Pane>,
+    /// Exe of the app the session runs in, for the icon: the IDE if there
+    /// is one, else the terminal.
     pub icon_path: String,
+    /// Terminal emulator hosting the session's shell, preferred for activation.
+    pub terminal_pid: u32,
+    pub terminal_path: String,
+    /// Process that owned the window, to find it again if it was recreated.
+    pub target_pid: u32,
+    pub window_title: String,
     pub user_prompt: String,
+    /// Prompts submitted before this one in the session, oldest first
+    /// (`[state] prompts`).
+    pub earlier_prompts: Vec<String>,
 }
 
 impl Default for State {
@@ -21,80 +58,614 @@ impl Default for State {
             target_hwnd: HWND::default(),
             wt_hwnd: HWND::default(),
             wt_runtime_id: String::new(),
+            wt_tab_name: String::new(),
+            terminal_tab: String::new(),
+            project: String::new(),
+            conemu_console: String::new(),
+            wezterm_pane: String::new(),
+            tmux: None,
             icon_path: String::new(),
+            terminal_pid: 0,
+            terminal_path: String::new(),
+            target_pid: 0,
+            window_title: String::new(),
             user_prompt: String::new(),
+            earlier_prompts: Vec::new(),
         }
     }
 }
 
-/// Get the state file path for a session.
-pub fn state_file_path(session_id: &str) -> std::path::PathBuf {
-    let temp = std::env::temp_dir();
-    temp.join(format!("claude-notify-{}.txt", session_id))
+impl State {
+    /// What to activate for this session.
+    pub fn target(&self) -> crate::activate::Target {
+        crate::activate::Target {
+            hwnd: self.target_hwnd,
+            wt_hwnd: self.wt_hwnd,
+            wt_runtime_id: self.wt_runtime_id.clone(),
+            wt_tab_name: self.wt_tab_name.clone(),
+            terminal_tab: self.terminal_tab.clone(),
+            conemu_console: self.conemu_console.clone(),
+            wezterm_pane: self.wezterm_pane.clone(),
+            tmux: self.tmux.clone(),
+        }
+    }
 }
 
-/// Save state to the state file (4 lines).
-pub fn save_state(session_id: &str, hwnd: HWND, runtime_id: &str, icon_path: &str, prompt: &str) {
-    let path = state_file_path(session_id);
-    let hwnd_val = hwnd.0 as usize;
-    let content = format!("{}\n{}\n{}\n{}", hwnd_val, runtime_id, icon_path, prompt);
-    let _ = std::fs::write(&path, content);
+/// Current `StateFile::version`.
+const VERSION: u32 = 1;
+
+/// Stored form of the state. Missing fields take their defaults and unknown
+/// ones are ignored, so files written by other versions still load.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StateFile {
+    version: u32,
+    hwnd: usize,
+    /// PID and title of the window, to find it again if it was recreated.
+    window_pid: u32,
+    window_title: String,
+    wt_runtime_id: String,
+    wt_tab_name: String,
+    terminal_tab: String,
+    project: String,
+    conemu_console: String,
+    wezterm_pane: String,
+    tmux: Option<crate::tmux::Pane>,
+    /// Caller exe, for the icon.
+    caller: String,
+    terminal_pid: u32,
+    terminal_path: String,
+    /// Unix time in seconds of the prompt.
+    saved: u64,
+    /// Where the window was when the prompt was submitted.
+    monitor: Option<SavedMonitor>,
+    placement: Option<SavedPlacement>,
+    /// The prompt as-is, or DPAPI-encrypted as hex (`[privacy] prompt`).
+    prompt: String,
+    prompt_protected: String,
+    /// Prompts before this one, oldest first, each stored like `prompt`.
+    earlier_prompts: Vec<SavedPrompt>,
 }
 
-/// Load state from the state file.
-pub fn load_state(session_id: &str) -> State {
-    let mut state = State::default();
-    let path = state_file_path(session_id);
+/// An earlier prompt, as-is or DPAPI-encrypted as hex.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedPrompt {
+    prompt: String,
+    prompt_protected: String,
+}
+
+impl SavedPrompt {
+    fn text(&self) -> String {
+        if self.prompt_protected.is_empty() {
+            self.prompt.clone()
+        } else {
+            crate::util::unprotect_text(&self.prompt_protected).unwrap_or_default()
+        }
+    }
+}
+
+/// A monitor by device name (handles don't survive display changes) and
+/// its bounds, as left, top, right, bottom.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedMonitor {
+    device: String,
+    rect: [i32; 4],
+}
 
-    let content = match std::fs::read_to_string(&path) {
-        Ok(c) => c,
-        Err(_) => return state,
+/// The window's GetWindowPlacement: its show state (SW_*) and its restored
+/// bounds, as left, top, right, bottom.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedPlacement {
+    show_cmd: u32,
+    normal: [i32; 4],
+}
+
+fn rect_array(rect: &RECT) -> [i32; 4] {
+    [rect.left, rect.top, rect.right, rect.bottom]
+}
+
+fn window_monitor(hwnd: HWND) -> Option<SavedMonitor> {
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL) };
+    if monitor.is_invalid() {
+        return None;
+    }
+    let mut mi = MONITORINFOEXW::default();
+    mi.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    unsafe {
+        if !GetMonitorInfoW(monitor, &mut mi as *mut MONITORINFOEXW as *mut MONITORINFO).as_bool() {
+            return None;
+        }
+    }
+    let len = mi.szDevice.iter().position(|&c| c == 0).unwrap_or(mi.szDevice.len());
+    Some(SavedMonitor {
+        device: String::from_utf16_lossy(&mi.szDevice[..len]),
+        rect: rect_array(&mi.monitorInfo.rcMonitor),
+    })
+}
+
+fn window_placement(hwnd: HWND) -> Option<SavedPlacement> {
+    let mut wp = WINDOWPLACEMENT {
+        length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+        ..Default::default()
     };
+    unsafe { GetWindowPlacement(hwnd, &mut wp) }.ok()?;
+    Some(SavedPlacement {
+        show_cmd: wp.showCmd,
+        normal: rect_array(&wp.rcNormalPosition),
+    })
+}
+
+/// Folders older versions kept state files in: `[state] dir` if set, and
+/// %LOCALAPPDATA%\claude-code-notify\state.
+fn old_state_dirs() -> Vec<std::path::PathBuf> {
+    let configured = &crate::config::get().state.dir;
+    let mut dirs: Vec<std::path::PathBuf> = crate::config::local_dir().map(|d| d.join("state")).into_iter().collect();
+    if !configured.is_empty() {
+        dirs.push(std::path::PathBuf::from(configured));
+    }
+    dirs
+}
+
+/// Held while a session's state is read or written. Re-entrant within a
+/// thread, so reads that import an old file can write it. Other shared
+/// files take one by name.
+pub struct Lock(Option<HANDLE>);
+
+impl Lock {
+    fn session(session_id: &str) -> Self {
+        Self::acquire(&format!("Local\\ClaudeCodeNotify.State.{}", session_id))
+    }
 
-    let lines: Vec<&str> = content.lines().collect();
-
-    // Line 1: HWND
-    if let Some(line) = lines.first() {
-        if let Ok(val) = line.trim().parse::<usize>() {
-            let hwnd = HWND(val as *mut _);
-            if unsafe { IsWindow(Some(hwnd)).as_bool() } {
-                state.target_hwnd = hwnd;
-                // Check if this is Windows Terminal
-                let class = crate::util::get_class_name(hwnd);
-                if class == "CASCADIA_HOSTING_WINDOW_CLASS" {
-                    state.wt_hwnd = hwnd;
-                }
+    pub fn acquire(name: &str) -> Self {
+        let Ok(mutex) = (unsafe { CreateMutexW(None, false, &HSTRING::from(name)) }) else {
+            return Lock(None);
+        };
+        // A stuck holder shouldn't block the hook; go ahead after a while.
+        // WAIT_ABANDONED (the holder died) also grants ownership.
+        let result = unsafe { WaitForSingleObject(mutex, 2000) };
+        if result == WAIT_FAILED || result == WAIT_TIMEOUT {
+            crate::debug_log!("Lock {} not acquired: {:?}", name, result);
+            unsafe { let _ = CloseHandle(mutex); }
+            return Lock(None);
+        }
+        Lock(Some(mutex))
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if let Some(mutex) = self.0 {
+            unsafe {
+                let _ = ReleaseMutex(mutex);
+                let _ = CloseHandle(mutex);
             }
         }
     }
+}
+
+/// Where older versions kept a session's state: %TEMP%, as JSON or in the
+/// old text format.
+fn old_file_paths(session_id: &str) -> [std::path::PathBuf; 2] {
+    let temp = std::env::temp_dir();
+    [
+        temp.join(format!("claude-notify-{}.json", session_id)),
+        temp.join(format!("claude-notify-{}.txt", session_id)),
+    ]
+}
+
+/// Save a session's state. The window PID and title are read from
+/// `state.target_hwnd`; `wt_hwnd` isn't stored.
+pub fn save_state(session_id: &str, state: &State) {
+    let hwnd = state.target_hwnd;
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    let file = StateFile {
+        version: VERSION,
+        hwnd: hwnd.0 as usize,
+        window_pid: pid,
+        window_title: crate::util::get_window_text(hwnd),
+        wt_runtime_id: state.wt_runtime_id.clone(),
+        wt_tab_name: state.wt_tab_name.clone(),
+        terminal_tab: state.terminal_tab.clone(),
+        project: state.project.clone(),
+        conemu_console: state.conemu_console.clone(),
+        wezterm_pane: state.wezterm_pane.clone(),
+        tmux: state.tmux.clone(),
+        caller: state.icon_path.clone(),
+        terminal_pid: state.terminal_pid,
+        terminal_path: state.terminal_path.clone(),
+        saved: unix_now(),
+        monitor: window_monitor(hwnd),
+        placement: window_placement(hwnd),
+        ..Default::default()
+    };
+    let cfg = crate::config::get();
+    let file = match cfg.privacy.prompt {
+        PromptPrivacy::Show => StateFile { prompt: state.user_prompt.clone(), ..file },
+        PromptPrivacy::Encrypt => StateFile {
+            prompt_protected: crate::util::protect_text(&state.user_prompt).unwrap_or_default(),
+            ..file
+        },
+        PromptPrivacy::Hide => file,
+    };
+    // Held from reading the old ring to writing the new one, so two saves
+    // of one session can't both build on the same ring and lose a prompt
+    let _lock = Lock::session(session_id);
+    let earlier_prompts = earlier_prompts(session_id, cfg.state.prompts, cfg.privacy.prompt);
+    store_file(session_id, &StateFile { earlier_prompts, ..file });
+}
+
+/// The ring of prompts to keep before the one being saved: the previous
+/// file's earlier prompts and its own, up to `keep - 1` of them. Prompts
+/// stored in a form the privacy setting no longer allows are dropped.
+fn earlier_prompts(session_id: &str, keep: usize, privacy: PromptPrivacy) -> Vec<SavedPrompt> {
+    if keep <= 1 || privacy == PromptPrivacy::Hide {
+        return Vec::new();
+    }
+    let Some(old) = read_file(session_id) else {
+        return Vec::new();
+    };
+    let last = SavedPrompt { prompt: old.prompt, prompt_protected: old.prompt_protected };
+    let mut prompts: Vec<SavedPrompt> = old
+        .earlier_prompts
+        .into_iter()
+        .chain(std::iter::once(last))
+        .filter(|p| !p.prompt.is_empty() || !p.prompt_protected.is_empty())
+        .filter(|p| privacy == PromptPrivacy::Show || p.prompt.is_empty())
+        .collect();
+    let excess = prompts.len().saturating_sub(keep - 1);
+    prompts.drain(..excess);
+    prompts
+}
+
+/// Write a session's state; the caller holds its lock.
+fn store_file(session_id: &str, file: &StateFile) -> bool {
+    crate::store::with(|db| insert(db, session_id, file)).is_some()
+}
+
+fn insert(db: &crate::store::Db, session_id: &str, file: &StateFile) -> Result<(), String> {
+    let text = serde_json::to_string(file).map_err(|e| e.to_string())?;
+    // The process whose exit ends the session
+    let pid = if file.terminal_pid != 0 { file.terminal_pid } else { file.window_pid };
+    db.execute(
+        "INSERT OR REPLACE INTO sessions (id, saved, pid, state) VALUES (?1, ?2, ?3, ?4)",
+        &[session_id.into(), file.saved.into(), pid.into(), text.into()],
+    )
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
-    // Line 2: RuntimeId
-    if let Some(line) = lines.get(1) {
-        let trimmed = line.trim();
-        if !trimmed.is_empty() {
-            state.wt_runtime_id = trimmed.to_string();
+fn unix_time(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn parse_json(session_id: &str, text: &str) -> Option<StateFile> {
+    match serde_json::from_str::<StateFile>(text) {
+        Ok(file) => {
+            if file.version > VERSION {
+                crate::warn_log!("State file version {} is newer than {}", file.version, VERSION);
+            }
+            Some(file)
+        }
+        Err(e) => {
+            crate::warn_log!("Invalid state file for {}: {}", session_id, e);
+            None
         }
     }
+}
 
-    // Line 3: Caller exe path
-    if let Some(line) = lines.get(2) {
-        let trimmed = line.trim();
-        if !trimmed.is_empty() {
-            state.icon_path = trimmed.to_string();
+/// Read a session's state, importing a file an older version left in %TEMP%.
+fn read_file(session_id: &str) -> Option<StateFile> {
+    let _lock = Lock::session(session_id);
+    let stored = crate::store::with(|db| {
+        db.query("SELECT state FROM sessions WHERE id = ?1", &[session_id.into()], |row| row.text(0))
+    });
+    if let Some(text) = stored.and_then(|rows| rows.into_iter().next()) {
+        return parse_json(session_id, &text);
+    }
+
+    let (old, file) = read_old_file(session_id, &std::env::temp_dir())?;
+    if store_file(session_id, &file) {
+        let _ = std::fs::remove_file(&old);
+        crate::debug_log!("Imported state file {:?}", old);
+    }
+    Some(file)
+}
+
+/// A session's state file in `dir` as an older version wrote it, JSON or
+/// text, and its path.
+fn read_old_file(session_id: &str, dir: &std::path::Path) -> Option<(std::path::PathBuf, StateFile)> {
+    let json = dir.join(format!("claude-notify-{}.json", session_id));
+    let text = dir.join(format!("claude-notify-{}.txt", session_id));
+    let (old, mut file) = if let Ok(content) = std::fs::read_to_string(&json) {
+        (json, parse_json(session_id, &content)?)
+    } else {
+        let content = std::fs::read_to_string(&text).ok()?;
+        (text, parse_legacy(&content))
+    };
+    if file.saved == 0 {
+        file.saved = std::fs::metadata(&old).and_then(|m| m.modified()).map_or(0, unix_time);
+    }
+    Some((old, file))
+}
+
+/// Copy the state files of older versions into the store, with their
+/// index.json.
+pub fn import(db: &crate::store::Db) -> Vec<std::path::PathBuf> {
+    let mut imported = Vec::new();
+    for dir in old_state_dirs() {
+        let mut ids = scan_sessions(&dir);
+        ids.sort();
+        ids.dedup();
+        for id in ids {
+            let Some((path, file)) = read_old_file(&id, &dir) else { continue };
+            match insert(db, &id, &file) {
+                Ok(()) => imported.push(path),
+                Err(e) => crate::warn_log!("Failed to import state of {}: {}", id, e),
+            }
+        }
+        let index = dir.join("index.json");
+        if index.is_file() {
+            imported.push(index);
         }
     }
+    imported
+}
+
+/// The old text format: HWND, RuntimeId, caller exe path, then the prompt
+/// (the rest of the file, as it may contain newlines).
+fn parse_legacy(text: &str) -> StateFile {
+    let lines: Vec<&str> = text.lines().collect();
+    let line = |i: usize| lines.get(i).map(|l| l.trim().to_string()).unwrap_or_default();
+    StateFile {
+        version: VERSION,
+        hwnd: line(0).parse().unwrap_or(0),
+        wt_runtime_id: line(1),
+        caller: line(2),
+        prompt: lines.get(3..).map(|rest| rest.join("\n")).unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Load a session's saved state.
+pub fn load_state(session_id: &str) -> State {
+    let mut state = State::default();
+    let Some(file) = read_file(session_id) else {
+        return state;
+    };
+
+    state.target_pid = file.window_pid;
+    state.window_title = file.window_title;
+    state.wt_runtime_id = file.wt_runtime_id;
+    state.wt_tab_name = file.wt_tab_name;
+    state.terminal_tab = file.terminal_tab;
+    state.project = file.project;
+    state.conemu_console = file.conemu_console;
+    state.wezterm_pane = file.wezterm_pane;
+    state.tmux = file.tmux;
+    state.icon_path = file.caller;
+    state.terminal_pid = file.terminal_pid;
+    state.terminal_path = file.terminal_path;
+    state.user_prompt = if file.prompt_protected.is_empty() {
+        file.prompt
+    } else {
+        crate::util::unprotect_text(&file.prompt_protected).unwrap_or_default()
+    };
+    state.earlier_prompts = file
+        .earlier_prompts
+        .iter()
+        .map(SavedPrompt::text)
+        .filter(|text| !text.is_empty())
+        .collect();
 
-    // Line 4: User prompt (may contain the rest of the file if there were newlines)
-    if lines.len() > 3 {
-        // Join remaining lines back (prompt may contain newlines)
-        state.user_prompt = lines[3..].join("\n");
+    // The saved window; if it's gone, or now shows another IDE project, look
+    // for the right one of the same process
+    let saved = Some(HWND(file.hwnd as *mut _)).filter(|_| file.hwnd != 0);
+    let hwnd = match saved {
+        Some(hwnd)
+            if unsafe { IsWindow(Some(hwnd)).as_bool() }
+                && (state.project.is_empty()
+                    || is_project_window(&crate::util::get_window_text(hwnd), &state.project)) =>
+        {
+            Some(hwnd)
+        }
+        _ if state.target_pid != 0 => {
+            find_process_window(state.target_pid, &state.window_title, &state.project)
+        }
+        _ => None,
+    };
+    // Activation prefers the terminal's window to the IDE's
+    let hwnd = match hwnd {
+        Some(hwnd) if state.terminal_pid != 0 && window_pid(hwnd) != state.terminal_pid => {
+            terminal_window(&state).or(Some(hwnd))
+        }
+        None if state.terminal_pid != 0 => terminal_window(&state),
+        hwnd => hwnd,
+    };
+    if let Some(hwnd) = hwnd {
+        state.target_hwnd = hwnd;
+        // Check if this is Windows Terminal
+        let class = crate::util::get_class_name(hwnd);
+        if class == "CASCADIA_HOSTING_WINDOW_CLASS" {
+            state.wt_hwnd = hwnd;
+        }
     }
 
     state
 }
 
-/// Delete the state file for a session.
+fn window_pid(hwnd: HWND) -> u32 {
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    pid
+}
+
+/// Main window of the saved terminal host, if that process still runs.
+fn terminal_window(state: &State) -> Option<HWND> {
+    // The PID may have been reused by now
+    let path = crate::process::get_process_exe_path(state.terminal_pid);
+    if !path.eq_ignore_ascii_case(&state.terminal_path) {
+        return None;
+    }
+    find_process_window(state.terminal_pid, "", "")
+}
+
+/// Project name as shown at the start of a JetBrains window title
+/// ("name – file", "name [path] – file", or "name - file" in older versions).
+pub fn project_from_title(title: &str) -> &str {
+    let end = [" [", " \u{2013} ", " - "]
+        .iter()
+        .filter_map(|sep| title.find(sep))
+        .min()
+        .unwrap_or(title.len());
+    title[..end].trim()
+}
+
+fn is_project_window(title: &str, project: &str) -> bool {
+    project_from_title(title) == project
+}
+
+/// Caller apps saved for a session by an earlier prompt, if any. Skips the
+/// window lookups of `load_state`.
+pub fn saved_caller(session_id: &str) -> Option<crate::process::Caller> {
+    let file = read_file(session_id)?;
+    if file.caller.is_empty() || !std::path::Path::new(&file.caller).is_file() {
+        return None;
+    }
+    // The caller is the terminal itself when there's no IDE
+    let app = if file.caller == file.terminal_path { String::new() } else { file.caller };
+    Some(crate::process::Caller {
+        terminal: file.terminal_path,
+        terminal_pid: file.terminal_pid,
+        app,
+    })
+}
+
+/// The window of process `pid` that best stands in for a closed one: the
+/// one with the same title, else one showing `project`, else the topmost
+/// visible main window.
+fn find_process_window(pid: u32, title: &str, project: &str) -> Option<HWND> {
+    struct Search<'a> {
+        pid: u32,
+        title: &'a str,
+        project: &'a str,
+        exact: Option<HWND>,
+        same_project: Option<HWND>,
+        first: Option<HWND>,
+    }
+
+    unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let search = &mut *(lparam.0 as *mut Search);
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        // Main windows only: visible, unowned, not tool windows
+        let tool = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW.0 != 0;
+        if pid != search.pid
+            || !IsWindowVisible(hwnd).as_bool()
+            || GetWindow(hwnd, GW_OWNER).is_ok_and(|owner| !owner.is_invalid())
+            || tool
+        {
+            return TRUE;
+        }
+        let text = crate::util::get_window_text(hwnd);
+        if !search.title.is_empty() && text == search.title {
+            search.exact = Some(hwnd);
+            return BOOL(0);
+        }
+        if !search.project.is_empty() && is_project_window(&text, search.project) {
+            search.same_project.get_or_insert(hwnd);
+        }
+        // EnumWindows goes top to bottom, so the first is the most recently active
+        search.first.get_or_insert(hwnd);
+        TRUE
+    }
+
+    let mut search = Search { pid, title, project, exact: None, same_project: None, first: None };
+    unsafe {
+        let _ = EnumWindows(Some(callback), LPARAM(&mut search as *mut Search as isize));
+    }
+    // A window of another project is no better than the saved one
+    let found = match project {
+        "" => search.exact.or(search.first),
+        _ => search.exact.or(search.same_project),
+    };
+    crate::debug_log!("Saved window gone or moved on; process {} window: {:?}", pid, found);
+    found
+}
+
+/// When the state was last saved, i.e. when the current prompt was submitted.
+pub fn saved_at(session_id: &str) -> Option<std::time::SystemTime> {
+    let saved = crate::store::with(|db| {
+        db.query("SELECT saved FROM sessions WHERE id = ?1", &[session_id.into()], |row| row.int(0))
+    });
+    if let Some(&secs) = saved.as_ref().and_then(|rows| rows.first()) {
+        return Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs as u64));
+    }
+    old_file_paths(session_id)
+        .iter()
+        .find_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+/// Session ids with a state file in `dir`.
+fn scan_sessions(dir: &std::path::Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let rest = name.strip_prefix("claude-notify-")?;
+            let id = rest.strip_suffix(".json").or_else(|| rest.strip_suffix(".txt"))?;
+            (!id.is_empty()).then(|| id.to_string())
+        })
+        .collect()
+}
+
+/// Session ids with saved state: those in the store, and ones an older
+/// version left in %TEMP%.
+pub fn list_sessions() -> Vec<String> {
+    let mut ids = crate::store::with(|db| db.query("SELECT id FROM sessions", &[], |row| row.text(0)))
+        .unwrap_or_default();
+    ids.extend(scan_sessions(&std::env::temp_dir()));
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// Delete the state of sessions that are over but never got a SessionEnd
+/// (crashed, killed): ones not saved for `[state] ttl_hours`, and ones whose
+/// terminal (or window) process has exited. Then the waiting marks left
+/// without a session.
+pub fn sweep() {
+    let ttl = crate::config::get().state.ttl_hours;
+    let now = unix_now();
+    let sessions = crate::store::with(|db| {
+        db.query("SELECT id, saved, pid FROM sessions", &[], |row| (row.text(0), row.int(1) as u64, row.int(2) as u32))
+    });
+    for (id, saved, pid) in sessions.unwrap_or_default() {
+        let expired = ttl > 0 && now.saturating_sub(saved) > ttl * 3600;
+        let exited = pid != 0 && !crate::process::is_running(pid);
+        if expired || exited {
+            crate::debug_log!("Sweeping stale state for session {}", id);
+            delete_state(&id);
+        }
+    }
+    crate::waiting::sweep(ttl);
+}
+
+/// Delete a session's state.
 pub fn delete_state(session_id: &str) {
-    let path = state_file_path(session_id);
-    let _ = std::fs::remove_file(&path);
+    let _lock = Lock::session(session_id);
+    crate::store::with(|db| db.execute("DELETE FROM sessions WHERE id = ?1", &[session_id.into()]));
+    for old in old_file_paths(session_id) {
+        let _ = std::fs::remove_file(old);
+    }
+    crate::waiting::clear(session_id);
 }
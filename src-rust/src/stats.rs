@@ -0,0 +1,244 @@
+//! Usage statistics (--stats).
+//!
+//! Each notification adds a row to the stats table of the store: its
+//! session and project, for completions how long the task ran (from the
+//! prompt's save to the notification), and how the toast went away.
+//! --stats sums them per project and per session, optionally over a recent
+//! period only, with the token totals of totals.rs.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::toast::Outcome;
+
+/// Sessions listed in the text report, the most recent.
+const MAX_SESSIONS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Event {
+    /// Unix time in seconds.
+    time: u64,
+    session: String,
+    #[serde(default)]
+    project: String,
+    /// An input-required notification rather than a completion.
+    #[serde(default)]
+    input: bool,
+    /// Seconds from the prompt to the completion, if the save time was known.
+    #[serde(default)]
+    duration_secs: Option<u64>,
+    /// None when the toast wasn't shown (muted).
+    #[serde(default)]
+    outcome: Option<Outcome>,
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Record a notification and how it ended.
+pub fn record(session: &str, project: &str, input: bool, duration: Option<std::time::Duration>, outcome: Option<Outcome>) {
+    let event = Event {
+        time: now_secs(),
+        session: session.to_string(),
+        project: project.to_string(),
+        input,
+        duration_secs: duration.map(|d| d.as_secs()),
+        outcome,
+    };
+    crate::store::with(|db| insert(db, &event));
+}
+
+fn insert(db: &crate::store::Db, event: &Event) -> Result<(), String> {
+    // Stored as its serde name, "clicked" etc.
+    let outcome = event.outcome.and_then(|o| serde_json::to_value(o).ok()).and_then(|v| v.as_str().map(str::to_string));
+    db.execute(
+        "INSERT INTO stats (time, session, project, input, duration_secs, outcome) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        &[
+            event.time.into(),
+            event.session.as_str().into(),
+            event.project.as_str().into(),
+            event.input.into(),
+            event.duration_secs.into(),
+            outcome.into(),
+        ],
+    )
+}
+
+/// Events since `cutoff` (Unix time in seconds), oldest first.
+fn load(cutoff: u64) -> Vec<Event> {
+    crate::store::with(|db| {
+        db.query(
+            "SELECT time, session, project, input, duration_secs, outcome FROM stats WHERE time >= ?1 ORDER BY id",
+            &[cutoff.into()],
+            |row| Event {
+                time: row.int(0) as u64,
+                session: row.text(1),
+                project: row.text(2),
+                input: row.int(3) != 0,
+                duration_secs: row.opt_int(4).map(|s| s as u64),
+                outcome: row.opt_text(5).and_then(|o| serde_json::from_value(serde_json::Value::String(o)).ok()),
+            },
+        )
+    })
+    .unwrap_or_default()
+}
+
+/// Copy stats.jsonl of older versions into the store.
+pub fn import(db: &crate::store::Db) -> Vec<std::path::PathBuf> {
+    let Some(path) = crate::config::local_dir().map(|d| d.join("stats.jsonl")) else { return Vec::new() };
+    let Ok(text) = std::fs::read_to_string(&path) else { return Vec::new() };
+    for event in text.lines().filter_map(|line| serde_json::from_str::<Event>(line).ok()) {
+        if let Err(e) = insert(db, &event) {
+            crate::warn_log!("Failed to import stats event: {}", e);
+        }
+    }
+    vec![path]
+}
+
+/// Sums over a project's or a session's events.
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    /// Completions.
+    tasks: u64,
+    /// Over the tasks whose duration is known.
+    total_secs: u64,
+    average_secs: u64,
+    /// Input-required notifications.
+    inputs: u64,
+    shown: u64,
+    clicked: u64,
+    timed_out: u64,
+    closed: u64,
+    /// Not shown because notifications were muted.
+    muted: u64,
+    /// Unix time in seconds of the last event.
+    last: u64,
+    #[serde(skip)]
+    timed_tasks: u64,
+}
+
+impl Summary {
+    fn add(&mut self, event: &Event) {
+        if event.input {
+            self.inputs += 1;
+        } else {
+            self.tasks += 1;
+        }
+        if let Some(secs) = event.duration_secs {
+            self.total_secs += secs;
+            self.timed_tasks += 1;
+            self.average_secs = self.total_secs / self.timed_tasks;
+        }
+        match event.outcome {
+            Some(outcome) => {
+                self.shown += 1;
+                match outcome {
+                    Outcome::Clicked => self.clicked += 1,
+                    Outcome::TimedOut => self.timed_out += 1,
+                    Outcome::Closed => self.closed += 1,
+                }
+            }
+            None => self.muted += 1,
+        }
+        self.last = self.last.max(event.time);
+    }
+
+    fn print(&self) {
+        let duration = |secs: u64| crate::util::format_duration(std::time::Duration::from_secs(secs));
+        let timing = if self.timed_tasks > 0 {
+            format!(", {} in total, {} on average", duration(self.total_secs), duration(self.average_secs))
+        } else {
+            String::new()
+        };
+        println!("    Tasks: {}{}", self.tasks, timing);
+        println!(
+            "    Notifications: {} shown ({} input), {} clicked, {} timed out, {} closed{}",
+            self.shown,
+            self.inputs,
+            self.clicked,
+            self.timed_out,
+            self.closed,
+            if self.muted > 0 { format!(", {} muted", self.muted) } else { String::new() }
+        );
+    }
+}
+
+/// Print the report, as text or JSON, for the events of the last `since`
+/// (all recorded ones for None).
+pub fn run(since: Option<std::time::Duration>, json: bool) -> i32 {
+    let cutoff = since.map_or(0, |d| now_secs().saturating_sub(d.as_secs()));
+    let events = load(cutoff);
+
+    let mut projects: BTreeMap<String, Summary> = BTreeMap::new();
+    let mut sessions: BTreeMap<String, (String, Summary)> = BTreeMap::new();
+    for event in &events {
+        projects.entry(event.project.clone()).or_default().add(event);
+        let (project, summary) = sessions.entry(event.session.clone()).or_default();
+        event.project.clone_into(project);
+        summary.add(event);
+    }
+    let mut sessions: Vec<(String, String, Summary)> =
+        sessions.into_iter().map(|(id, (project, summary))| (id, project, summary)).collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.2.last));
+    let totals = crate::totals::load();
+
+    if json {
+        let projects: Vec<serde_json::Value> = projects
+            .iter()
+            .map(|(project, summary)| {
+                let tokens = totals.get(project);
+                serde_json::json!({
+                    "project": project,
+                    "summary": summary,
+                    "tokens_all_time": tokens.map(|t| t.usage.total()),
+                    "cost_usd_all_time": tokens.map(|t| t.cost_usd),
+                })
+            })
+            .collect();
+        let sessions: Vec<serde_json::Value> = sessions
+            .iter()
+            .map(|(id, project, summary)| serde_json::json!({ "session": id, "project": project, "summary": summary }))
+            .collect();
+        let report = serde_json::json!({
+            "since_secs": since.map(|d| d.as_secs()),
+            "projects": projects,
+            "sessions": sessions,
+        });
+        println!("{}", report);
+        return 0;
+    }
+
+    match since {
+        Some(d) => println!("Claude Code notification stats, last {}", crate::util::format_duration(d)),
+        None => println!("Claude Code notification stats, everything recorded"),
+    }
+    if events.is_empty() {
+        println!();
+        println!("No notifications in that time.");
+        return 0;
+    }
+    println!();
+    println!("Projects:");
+    for (project, summary) in &projects {
+        println!("  {}", if project.is_empty() { "(unknown)" } else { project });
+        summary.print();
+        if let Some(t) = totals.get(project) {
+            println!("    Tokens (all time): {} over {} turns, ${:.2}", t.usage.total(), t.turns, t.cost_usd);
+        }
+    }
+    println!();
+    println!("Sessions (most recent first):");
+    for (id, project, summary) in sessions.iter().take(MAX_SESSIONS) {
+        println!("  {}  {}", id, project);
+        summary.print();
+    }
+    if sessions.len() > MAX_SESSIONS {
+        println!("  ... and {} more (--json lists all)", sessions.len() - MAX_SESSIONS);
+    }
+    0
+}
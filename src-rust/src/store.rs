@@ -0,0 +1,430 @@
+//! SQLite store: notify.db in %LOCALAPPDATA%\claude-code-notify (or
+//! `[state] dir`).
+//!
+//! Session state, the notification history, the stats and the token totals
+//! live in one database instead of a folder of JSON files, so they can be
+//! queried together, old rows expire (`[state] keep_days`), and hooks of
+//! several sessions write at the same time safely: the WAL journal lets
+//! readers go on during a write, and writers wait up to BUSY_TIMEOUT_MS for
+//! each other. The SQLite used is the one in Windows 10 and later
+//! (winsqlite3.dll), so nothing is bundled.
+//!
+//! The schema is versioned with `PRAGMA user_version`; the first open
+//! imports the files older versions wrote (see `MIGRATIONS`).
+
+use std::ffi::c_void;
+
+const SQLITE_OK: i32 = 0;
+const SQLITE_ROW: i32 = 100;
+const SQLITE_DONE: i32 = 101;
+const SQLITE_NULL: i32 = 5;
+const SQLITE_OPEN_READWRITE: i32 = 0x2;
+const SQLITE_OPEN_CREATE: i32 = 0x4;
+/// Destructor argument telling SQLite to copy bound text.
+const SQLITE_TRANSIENT: isize = -1;
+
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_open_v2(filename: *const u8, db: *mut *mut c_void, flags: i32, vfs: *const u8) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_close_v2(db: *mut c_void) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_errmsg(db: *mut c_void) -> *const u8);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_busy_timeout(db: *mut c_void, ms: i32) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_prepare_v2(db: *mut c_void, sql: *const u8, bytes: i32, stmt: *mut *mut c_void, tail: *mut *const u8) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_bind_null(stmt: *mut c_void, index: i32) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_bind_int64(stmt: *mut c_void, index: i32, value: i64) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_bind_double(stmt: *mut c_void, index: i32, value: f64) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_bind_text(stmt: *mut c_void, index: i32, text: *const u8, bytes: i32, destructor: isize) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_step(stmt: *mut c_void) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_column_type(stmt: *mut c_void, column: i32) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_column_int64(stmt: *mut c_void, column: i32) -> i64);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_column_double(stmt: *mut c_void, column: i32) -> f64);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_column_text(stmt: *mut c_void, column: i32) -> *const u8);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_column_bytes(stmt: *mut c_void, column: i32) -> i32);
+windows_link::link!("winsqlite3.dll" "system" fn sqlite3_finalize(stmt: *mut c_void) -> i32);
+
+/// How long a write waits for another process's to finish.
+const BUSY_TIMEOUT_MS: i32 = 5000;
+
+/// Imports the files older versions wrote, returning those to delete once
+/// the transaction is committed.
+type Import = fn(&Db) -> Vec<std::path::PathBuf>;
+
+/// Schema changes, in order; `user_version` is how many have run. Each
+/// runs in a transaction with the import that goes with it.
+const MIGRATIONS: [(&str, Import); 3] = [
+    (
+        "CREATE TABLE sessions (
+            id TEXT PRIMARY KEY,
+            saved INTEGER NOT NULL,
+            pid INTEGER NOT NULL,
+            state TEXT NOT NULL
+        );
+        CREATE TABLE notifications (
+            id INTEGER PRIMARY KEY,
+            time INTEGER NOT NULL,
+            session TEXT NOT NULL,
+            event TEXT NOT NULL,
+            title TEXT NOT NULL,
+            message TEXT NOT NULL,
+            cwd TEXT NOT NULL
+        );
+        CREATE INDEX notifications_time ON notifications (time);
+        CREATE TABLE stats (
+            id INTEGER PRIMARY KEY,
+            time INTEGER NOT NULL,
+            session TEXT NOT NULL,
+            project TEXT NOT NULL,
+            input INTEGER NOT NULL,
+            duration_secs INTEGER,
+            outcome TEXT
+        );
+        CREATE INDEX stats_time ON stats (time);
+        CREATE TABLE totals (
+            project TEXT PRIMARY KEY,
+            turns INTEGER NOT NULL,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cache_creation_input_tokens INTEGER NOT NULL,
+            cache_read_input_tokens INTEGER NOT NULL,
+            cost_usd REAL NOT NULL,
+            last INTEGER NOT NULL
+        );",
+        import_files,
+    ),
+    (
+        "CREATE TABLE waiting (
+            session TEXT PRIMARY KEY,
+            since INTEGER NOT NULL
+        )",
+        |_| Vec::new(),
+    ),
+    (
+        "CREATE TABLE meta (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        )",
+        |_| Vec::new(),
+    ),
+];
+
+/// Least time between two `maintain` runs, over all processes.
+pub const MAINTAIN_INTERVAL_SECS: u64 = 10 * 60;
+
+/// A value bound to a statement parameter.
+pub enum Value {
+    Null,
+    Int(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Int(v)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(v: u64) -> Self {
+        Value::Int(v as i64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(v: u32) -> Self {
+        Value::Int(v as i64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Int(v as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Real(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::Text(v.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::Text(v)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+/// A result row, valid during the `query` callback.
+pub struct Row(*mut c_void);
+
+impl Row {
+    pub fn int(&self, column: i32) -> i64 {
+        unsafe { sqlite3_column_int64(self.0, column) }
+    }
+
+    pub fn opt_int(&self, column: i32) -> Option<i64> {
+        (!self.is_null(column)).then(|| self.int(column))
+    }
+
+    fn is_null(&self, column: i32) -> bool {
+        unsafe { sqlite3_column_type(self.0, column) == SQLITE_NULL }
+    }
+
+    pub fn real(&self, column: i32) -> f64 {
+        unsafe { sqlite3_column_double(self.0, column) }
+    }
+
+    pub fn text(&self, column: i32) -> String {
+        unsafe {
+            let text = sqlite3_column_text(self.0, column);
+            if text.is_null() {
+                return String::new();
+            }
+            let len = sqlite3_column_bytes(self.0, column).max(0) as usize;
+            String::from_utf8_lossy(std::slice::from_raw_parts(text, len)).into_owned()
+        }
+    }
+
+    pub fn opt_text(&self, column: i32) -> Option<String> {
+        (!self.is_null(column)).then(|| self.text(column))
+    }
+}
+
+/// An open connection.
+pub struct Db(*mut c_void);
+
+impl Db {
+    fn error(&self) -> String {
+        unsafe {
+            let msg = sqlite3_errmsg(self.0);
+            if msg.is_null() {
+                return "unknown SQLite error".to_string();
+            }
+            std::ffi::CStr::from_ptr(msg as *const _).to_string_lossy().into_owned()
+        }
+    }
+
+    /// Run a statement for each row it returns.
+    pub fn query<T>(&self, sql: &str, params: &[Value], mut row: impl FnMut(&Row) -> T) -> Result<Vec<T>, String> {
+        let mut stmt = std::ptr::null_mut();
+        let prepared =
+            unsafe { sqlite3_prepare_v2(self.0, sql.as_ptr(), sql.len() as i32, &mut stmt, std::ptr::null_mut()) };
+        if prepared != SQLITE_OK {
+            return Err(format!("{} in {:?}", self.error(), sql));
+        }
+        let result = self.run(stmt, params, &mut row);
+        unsafe { sqlite3_finalize(stmt) };
+        result
+    }
+
+    fn run<T>(&self, stmt: *mut c_void, params: &[Value], row: &mut impl FnMut(&Row) -> T) -> Result<Vec<T>, String> {
+        for (i, param) in params.iter().enumerate() {
+            let index = i as i32 + 1;
+            let bound = unsafe {
+                match param {
+                    Value::Null => sqlite3_bind_null(stmt, index),
+                    Value::Int(v) => sqlite3_bind_int64(stmt, index, *v),
+                    Value::Real(v) => sqlite3_bind_double(stmt, index, *v),
+                    Value::Text(v) => sqlite3_bind_text(stmt, index, v.as_ptr(), v.len() as i32, SQLITE_TRANSIENT),
+                }
+            };
+            if bound != SQLITE_OK {
+                return Err(self.error());
+            }
+        }
+        let mut rows = Vec::new();
+        loop {
+            match unsafe { sqlite3_step(stmt) } {
+                SQLITE_ROW => rows.push(row(&Row(stmt))),
+                SQLITE_DONE => return Ok(rows),
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    /// Run a statement that returns no rows.
+    pub fn execute(&self, sql: &str, params: &[Value]) -> Result<(), String> {
+        self.query(sql, params, |_| ()).map(|_| ())
+    }
+
+    /// Run statements separated by semicolons, without parameters.
+    fn batch(&self, sql: &str) -> Result<(), String> {
+        sql.split(';').map(str::trim).filter(|s| !s.is_empty()).try_for_each(|s| self.execute(s, &[]))
+    }
+
+    /// Run `f` in a write transaction, rolled back if it fails.
+    pub fn transaction<R>(&self, f: impl FnOnce(&Db) -> Result<R, String>) -> Result<R, String> {
+        // IMMEDIATE takes the write lock up front, so a read-modify-write
+        // can't be overtaken by another process
+        self.execute("BEGIN IMMEDIATE", &[])?;
+        match f(self) {
+            Ok(result) => {
+                self.execute("COMMIT", &[])?;
+                Ok(result)
+            }
+            Err(e) => {
+                let _ = self.execute("ROLLBACK", &[]);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        unsafe { sqlite3_close_v2(self.0) };
+    }
+}
+
+/// Folder of the database: `[state] dir`, else %LOCALAPPDATA%\claude-code-notify.
+pub fn dir() -> std::path::PathBuf {
+    let configured = &crate::config::get().state.dir;
+    if !configured.is_empty() {
+        return std::path::PathBuf::from(configured);
+    }
+    crate::config::local_dir().unwrap_or_else(std::env::temp_dir)
+}
+
+pub fn path() -> std::path::PathBuf {
+    dir().join("notify.db")
+}
+
+/// Open the database, creating or upgrading it as needed.
+pub fn open() -> Result<Db, String> {
+    let path = path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let name = format!("{}\0", path.to_string_lossy());
+    let mut handle = std::ptr::null_mut();
+    let opened = unsafe {
+        sqlite3_open_v2(name.as_ptr(), &mut handle, SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE, std::ptr::null())
+    };
+    // Even a failed open returns a handle to close
+    let db = Db(handle);
+    if opened != SQLITE_OK || handle.is_null() {
+        return Err(format!("Can't open {:?}: {}", path, db.error()));
+    }
+    unsafe { sqlite3_busy_timeout(db.0, BUSY_TIMEOUT_MS) };
+    db.query("PRAGMA journal_mode = WAL", &[], |_| ())?;
+    db.execute("PRAGMA synchronous = NORMAL", &[])?;
+    migrate(&db)?;
+    Ok(db)
+}
+
+thread_local! {
+    /// The connection `with` uses, opened on first use. Per thread, as a
+    /// connection isn't shared between threads.
+    static DB: std::cell::RefCell<Option<Db>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Run `f` on the database, opening it the first time. Failures are logged
+/// and give None, so a broken database costs the stored data, never the
+/// notification.
+pub fn with<R>(f: impl FnOnce(&Db) -> Result<R, String>) -> Option<R> {
+    DB.with(|cell| {
+        if cell.borrow().is_none() {
+            match open() {
+                Ok(db) => *cell.borrow_mut() = Some(db),
+                Err(e) => {
+                    crate::warn_log!("Store: {}", e);
+                    return None;
+                }
+            }
+        }
+        // Shared, so `f` may use the store again
+        let db = cell.borrow();
+        match f(db.as_ref()?) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                crate::warn_log!("Store: {}", e);
+                None
+            }
+        }
+    })
+}
+
+fn migrate(db: &Db) -> Result<(), String> {
+    let version = db.query("PRAGMA user_version", &[], |row| row.int(0))?.first().copied().unwrap_or(0);
+    for (i, (schema, import)) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        let imported = db.transaction(|db| {
+            // Another process may have upgraded it since the check
+            let current = db.query("PRAGMA user_version", &[], |row| row.int(0))?.first().copied().unwrap_or(0);
+            if current > i as i64 {
+                return Ok(Vec::new());
+            }
+            db.batch(schema)?;
+            let imported = import(db);
+            db.execute(&format!("PRAGMA user_version = {}", i + 1), &[])?;
+            Ok(imported)
+        })?;
+        for file in &imported {
+            let _ = std::fs::remove_file(file);
+        }
+        crate::info_log!("Store upgraded to version {}, {} file(s) imported", i + 1, imported.len());
+    }
+    Ok(())
+}
+
+/// Move what older versions kept in files into the new tables.
+fn import_files(db: &Db) -> Vec<std::path::PathBuf> {
+    let mut imported = crate::state::import(db);
+    imported.extend(crate::history::import(db));
+    imported.extend(crate::stats::import(db));
+    imported.extend(crate::totals::import(db));
+    imported
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Sweep stale sessions and expire old rows, unless some process did in the
+/// last MAINTAIN_INTERVAL_SECS. Called by the hooks and the tray agent.
+pub fn maintain() {
+    let now = now_secs();
+    let due = with(|db| {
+        db.transaction(|db| {
+            let last = db
+                .query("SELECT value FROM meta WHERE key = 'maintained'", &[], |row| row.int(0))?
+                .first()
+                .copied()
+                .unwrap_or(0) as u64;
+            if now.saturating_sub(last) < MAINTAIN_INTERVAL_SECS {
+                return Ok(false);
+            }
+            db.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('maintained', ?1)", &[now.into()])?;
+            Ok(true)
+        })
+    });
+    if due == Some(true) {
+        crate::state::sweep();
+        expire();
+    }
+}
+
+/// Delete notifications and stats older than `[state] keep_days`.
+fn expire() {
+    let days = crate::config::get().state.keep_days;
+    if days == 0 {
+        return;
+    }
+    let cutoff = now_secs().saturating_sub(days * 24 * 60 * 60);
+    with(|db| {
+        db.execute("DELETE FROM notifications WHERE time < ?1", &[cutoff.into()])?;
+        db.execute("DELETE FROM stats WHERE time < ?1", &[cutoff.into()])
+    });
+}
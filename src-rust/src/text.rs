@@ -0,0 +1,260 @@
+//! Toast text layout and drawing with DirectWrite.
+//!
+//! GDI draws a string in a single font, so mixed text (CJK next to emoji,
+//! say) came out as boxes wherever that font had no glyph. A DirectWrite
+//! layout picks the font per run of characters instead: the first family of
+//! the configured chain that has them, then the system fallback (the CJK,
+//! symbol, and color emoji fonts). Layouts are drawn onto the toast's DC
+//! with Direct2D, between GDI's background and buttons.
+
+use std::cell::RefCell;
+
+use windows::core::*;
+use windows::Win32::Foundation::{E_FAIL, RECT};
+use windows::Win32::Graphics::Direct2D::Common::*;
+use windows::Win32::Graphics::Direct2D::*;
+use windows::Win32::Graphics::DirectWrite::*;
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+use windows::Win32::Graphics::Gdi::HDC;
+
+/// Primary family when no chain is configured.
+const SYSTEM_FONT: &str = "Segoe UI";
+/// Em size per pixel of cell height when a family's metrics can't be read
+/// (Segoe UI's).
+const EM_PER_CELL: f32 = 0.75;
+
+/// How text fits its rectangle.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Flow {
+    /// Word-wrapped; the last visible line ends in "..." if the text overflows.
+    Wrap,
+    /// A single line, ending in "..." if it's too long.
+    Line,
+    /// A single line along the bottom of the rectangle.
+    BottomLine,
+}
+
+/// The font a piece of text is drawn in.
+pub struct Style<'a> {
+    /// Families tried in order for each run, before the system fallback.
+    pub fonts: &'a [String],
+    /// Cell height in pixels, as GDI sizes fonts.
+    pub height: i32,
+    pub bold: bool,
+}
+
+struct Writer {
+    factory: IDWriteFactory2,
+    d2d: ID2D1Factory,
+    /// The system fonts plus the custom font files; None for the system fonts only.
+    fonts: Option<IDWriteFontCollection>,
+}
+
+thread_local! {
+    /// The factories `with` uses, created on first use or by `load_fonts`.
+    static WRITER: RefCell<Option<Writer>> = const { RefCell::new(None) };
+}
+
+/// Make the custom font files (assets\fonts) available to layouts by family
+/// name. GDI's private fonts aren't visible to DirectWrite, so they're
+/// added to a collection of their own.
+pub fn load_fonts(font_files: &[String]) {
+    match unsafe { Writer::new(font_files) } {
+        Ok(writer) => WRITER.with(|cell| *cell.borrow_mut() = Some(writer)),
+        Err(e) => crate::warn_log!("DirectWrite: {}", e),
+    }
+}
+
+/// Run `f` with the factories, creating them the first time. Failures are
+/// logged and give None.
+fn with<R>(f: impl FnOnce(&Writer) -> Result<R>) -> Option<R> {
+    WRITER.with(|cell| {
+        if cell.borrow().is_none() {
+            match unsafe { Writer::new(&[]) } {
+                Ok(writer) => *cell.borrow_mut() = Some(writer),
+                Err(e) => {
+                    crate::warn_log!("DirectWrite: {}", e);
+                    return None;
+                }
+            }
+        }
+        let writer = cell.borrow();
+        match f(writer.as_ref()?) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                crate::warn_log!("DirectWrite: {}", e);
+                None
+            }
+        }
+    })
+}
+
+impl Writer {
+    unsafe fn new(font_files: &[String]) -> Result<Writer> {
+        let factory: IDWriteFactory2 = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?;
+        let d2d: ID2D1Factory = D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None)?;
+        let fonts = if font_files.is_empty() {
+            None
+        } else {
+            match custom_collection(&factory, font_files) {
+                Ok(fonts) => Some(fonts),
+                Err(e) => {
+                    crate::warn_log!("Custom fonts unavailable to DirectWrite: {}", e);
+                    None
+                }
+            }
+        };
+        Ok(Writer { factory, d2d, fonts })
+    }
+
+    unsafe fn collection(&self) -> Result<IDWriteFontCollection> {
+        if let Some(fonts) = &self.fonts {
+            return Ok(fonts.clone());
+        }
+        let mut fonts = None;
+        self.factory.GetSystemFontCollection(&mut fonts, false)?;
+        fonts.ok_or_else(|| Error::from(E_FAIL))
+    }
+
+    /// Em size that gives `family` a cell of `height` pixels, like a GDI
+    /// font of that height.
+    unsafe fn em_size(&self, family: &str, height: i32, weight: DWRITE_FONT_WEIGHT) -> Option<f32> {
+        let fonts = self.collection().ok()?;
+        let (mut index, mut exists) = (0, BOOL(0));
+        fonts.FindFamilyName(&HSTRING::from(family), &mut index, &mut exists).ok()?;
+        if !exists.as_bool() {
+            return None;
+        }
+        let font = fonts
+            .GetFontFamily(index)
+            .ok()?
+            .GetFirstMatchingFont(weight, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_NORMAL)
+            .ok()?;
+        let mut metrics = DWRITE_FONT_METRICS::default();
+        font.GetMetrics(&mut metrics);
+        let cell = metrics.ascent as f32 + metrics.descent as f32;
+        (cell > 0.0).then(|| height as f32 * metrics.designUnitsPerEm as f32 / cell)
+    }
+
+    /// Fallback trying `fonts` in order for every character, then the
+    /// system's.
+    unsafe fn fallback(&self, fonts: &[String]) -> Result<IDWriteFontFallback> {
+        let builder = self.factory.CreateFontFallbackBuilder()?;
+        if !fonts.is_empty() {
+            let families: Vec<HSTRING> = fonts.iter().map(HSTRING::from).collect();
+            let names: Vec<*const u16> = families.iter().map(|family| family.as_ptr()).collect();
+            let everything = DWRITE_UNICODE_RANGE { first: 0, last: 0x10FFFF };
+            builder.AddMapping(&[everything], &names, self.fonts.as_ref(), PCWSTR::null(), PCWSTR::null(), 1.0)?;
+        }
+        builder.AddMappings(&self.factory.GetSystemFontFallback()?)?;
+        builder.CreateFontFallback()
+    }
+
+    unsafe fn layout(&self, text: &str, style: &Style, flow: Flow, width: f32, height: f32) -> Result<IDWriteTextLayout> {
+        let family = style.fonts.first().map_or(SYSTEM_FONT, String::as_str);
+        let weight = if style.bold { DWRITE_FONT_WEIGHT_BOLD } else { DWRITE_FONT_WEIGHT_NORMAL };
+        let size = self.em_size(family, style.height, weight).unwrap_or(style.height as f32 * EM_PER_CELL);
+        let format = self.factory.CreateTextFormat(
+            &HSTRING::from(family),
+            self.fonts.as_ref(),
+            weight,
+            DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_STRETCH_NORMAL,
+            size,
+            w!(""),
+        )?;
+        format.cast::<IDWriteTextFormat1>()?.SetFontFallback(&self.fallback(style.fonts)?)?;
+
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let layout = self.factory.CreateTextLayout(&units, &format, width, height)?;
+        layout.SetWordWrapping(if flow == Flow::Wrap { DWRITE_WORD_WRAPPING_WRAP } else { DWRITE_WORD_WRAPPING_NO_WRAP })?;
+        if flow == Flow::BottomLine {
+            layout.SetParagraphAlignment(DWRITE_PARAGRAPH_ALIGNMENT_FAR)?;
+        }
+        let trimming = DWRITE_TRIMMING { granularity: DWRITE_TRIMMING_GRANULARITY_CHARACTER, delimiter: 0, delimiterCount: 0 };
+        layout.SetTrimming(&trimming, &self.factory.CreateEllipsisTrimmingSign(&format)?)?;
+        Ok(layout)
+    }
+}
+
+/// The system fonts with the font files added.
+unsafe fn custom_collection(factory: &IDWriteFactory2, font_files: &[String]) -> Result<IDWriteFontCollection> {
+    let factory: IDWriteFactory5 = factory.cast()?;
+    let builder = factory.CreateFontSetBuilder()?;
+    builder.AddFontSet(&factory.GetSystemFontSet()?)?;
+    for path in font_files {
+        let added = factory
+            .CreateFontFileReference(&HSTRING::from(path.as_str()), None)
+            .and_then(|file| builder.AddFontFile(&file));
+        if let Err(e) = added {
+            crate::warn_log!("Font {} unavailable to DirectWrite: {}", path, e);
+        }
+    }
+    factory.CreateFontCollectionFromFontSet(&builder.CreateFontSet()?)?.cast()
+}
+
+/// Height of `text` word-wrapped at `width`, and of one of its lines.
+pub fn measure(text: &str, style: &Style, width: i32) -> (i32, i32) {
+    with(|writer| unsafe {
+        let layout = writer.layout(text, style, Flow::Wrap, width as f32, f32::MAX)?;
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        layout.GetMetrics(&mut metrics)?;
+        let line = metrics.height / metrics.lineCount.max(1) as f32;
+        Ok((metrics.height.ceil() as i32, line.ceil() as i32))
+    })
+    .unwrap_or((0, style.height))
+}
+
+/// Direct2D drawing onto a GDI DC. What's drawn lands on the DC when the
+/// painter is dropped, so GDI drawing meant to go on top comes after.
+pub struct Painter {
+    target: ID2D1DCRenderTarget,
+}
+
+impl Painter {
+    /// Start drawing onto `rect` of `hdc`; coordinates are relative to it.
+    pub fn begin(hdc: HDC, rect: &RECT) -> Option<Painter> {
+        let properties = D2D1_RENDER_TARGET_PROPERTIES {
+            r#type: D2D1_RENDER_TARGET_TYPE_DEFAULT,
+            pixelFormat: D2D1_PIXEL_FORMAT { format: DXGI_FORMAT_B8G8R8A8_UNORM, alphaMode: D2D1_ALPHA_MODE_IGNORE },
+            // The toast is DPI-unaware: one DIP per pixel
+            dpiX: 96.0,
+            dpiY: 96.0,
+            usage: D2D1_RENDER_TARGET_USAGE_NONE,
+            minLevel: D2D1_FEATURE_LEVEL_DEFAULT,
+        };
+        with(|writer| unsafe {
+            let target = writer.d2d.CreateDCRenderTarget(&properties)?;
+            target.BindDC(hdc, rect)?;
+            target.BeginDraw();
+            Ok(Painter { target })
+        })
+    }
+
+    /// Draw `text` in `rect`, in a GDI `COLORREF` color.
+    pub fn draw(&self, text: &str, style: &Style, flow: Flow, rect: RECT, color: u32) {
+        let channel = |shift: u32| ((color >> shift) & 0xFF) as f32 / 255.0;
+        let color = D2D1_COLOR_F { r: channel(0), g: channel(8), b: channel(16), a: 1.0 };
+        with(|writer| unsafe {
+            let width = (rect.right - rect.left).max(0) as f32;
+            let height = (rect.bottom - rect.top).max(0) as f32;
+            let layout = writer.layout(text, style, flow, width, height)?;
+            let brush = self.target.CreateSolidColorBrush(&color, None)?;
+            self.target.DrawTextLayout(
+                windows_numerics::Vector2 { X: rect.left as f32, Y: rect.top as f32 },
+                &layout,
+                &brush,
+                D2D1_DRAW_TEXT_OPTIONS_ENABLE_COLOR_FONT | D2D1_DRAW_TEXT_OPTIONS_CLIP,
+            );
+            Ok(())
+        });
+    }
+}
+
+impl Drop for Painter {
+    fn drop(&mut self) {
+        if let Err(e) = unsafe { self.target.EndDraw(None, None) } {
+            crate::debug_log!("Direct2D EndDraw: {}", e);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! tmux window switching for sessions inside WSL.
+//!
+//! A Windows exe started from WSL only sees the Linux variables listed in
+//! `WSLENV`, so this needs `TMUX`, `TMUX_PANE` and `WSL_DISTRO_NAME` added
+//! there. With them, the pane is saved with the session, and on activation
+//! tmux is told (through `wsl.exe`) to show it in the attached client.
+
+use serde::{Deserialize, Serialize};
+
+/// A tmux pane in a WSL distro.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Pane {
+    /// Empty for the default distro.
+    pub distro: String,
+    /// Server socket, from `TMUX` ("socket,pid,session").
+    pub socket: String,
+    /// Pane id ("%3").
+    pub pane: String,
+}
+
+impl Pane {
+    /// The pane we run in, if inside tmux.
+    pub fn current() -> Option<Pane> {
+        let tmux = std::env::var("TMUX").ok()?;
+        let pane = std::env::var("TMUX_PANE").ok().filter(|p| is_pane_id(p))?;
+        Some(Pane {
+            distro: std::env::var("WSL_DISTRO_NAME").unwrap_or_default(),
+            socket: tmux.split(',').next().unwrap_or_default().to_string(),
+            pane,
+        })
+    }
+}
+
+/// "%" followed by digits. The id goes into the command line unquoted (tmux
+/// reads ";" between commands), so nothing else may.
+fn is_pane_id(text: &str) -> bool {
+    text.strip_prefix('%').is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Show `pane` in its session's client: switch the client to the session,
+/// then select the window and the pane. Returns true if tmux succeeded.
+pub fn select_pane(pane: &Pane) -> bool {
+    if !is_pane_id(&pane.pane) {
+        crate::debug_log!("Invalid tmux pane {:?}", pane.pane);
+        return false;
+    }
+    let mut cmd = String::from("wsl.exe");
+    if !pane.distro.is_empty() {
+        cmd.push_str(&format!(" -d {}", crate::spawn::quote_arg(&pane.distro)));
+    }
+    cmd.push_str(" -e tmux");
+    if !pane.socket.is_empty() {
+        cmd.push_str(&format!(" -S {}", crate::spawn::quote_arg(&pane.socket)));
+    }
+    // One tmux invocation; ";" separates commands
+    let target = &pane.pane;
+    cmd.push_str(&format!(
+        " switch-client -t {target} ; select-window -t {target} ; select-pane -t {target}"
+    ));
+    let code = crate::spawn::run_hidden(&cmd, 3000);
+    crate::debug_log!("tmux select {}: {:?}", target, code);
+    code == Some(0)
+}
@@ -1,7 +1,8 @@
 //! Toast window: rendering, WndProc, timers, mouse interaction, stacking.
 //!
-//! Implements the full toast notification window with GDI drawing,
-//! fade-out animation, Telegram-style stacking, and click-to-activate.
+//! Implements the full toast notification window with GDI drawing (the
+//! text through `text`), fade-out animation, Telegram-style stacking, and
+//! click-to-activate.
 
 use std::cell::RefCell;
 
@@ -9,19 +10,37 @@ use windows::core::*;
 use windows::Win32::Foundation::*;
 use windows::Win32::Graphics::Gdi::*;
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
-use windows::Win32::UI::Input::KeyboardAndMouse::{TrackMouseEvent, TRACKMOUSEEVENT, TME_LEAVE};
+use windows::Win32::UI::Accessibility::IRawElementProviderSimple;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetCapture, GetDoubleClickTime, GetKeyState, ReleaseCapture, SetCapture, TrackMouseEvent, TRACKMOUSEEVENT,
+    TME_LEAVE, VK_CONTROL, VK_ESCAPE, VK_MENU, VK_RETURN, VK_SHIFT, VK_SPACE, VK_TAB,
+};
+use windows::Win32::UI::Input::Pointer::GetPointerType;
 use windows::Win32::UI::Shell::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
+use crate::config::{MouseAction, StackAxis};
+
 // --- Constants (SPEC Sections 8.2, 8.3, 10.1, 10.2) ---
 
-const WINDOW_WIDTH: i32 = 300;
-const WINDOW_HEIGHT: i32 = 80;
-const ICON_SIZE: i32 = 48;
+pub const ICON_SIZE: i32 = 48;
 const ICON_PADDING: i32 = 16;
 const CLOSE_BUTTON_SIZE: i32 = 20;
 const CLOSE_BUTTON_MARGIN: i32 = 6;
 const BORDER_WIDTH: i32 = 2;
+const COUNTDOWN_BAR_HEIGHT: i32 = 2;
+const COUNTDOWN_TICK_MS: u32 = 50;
+const TEXT_MARGIN: i32 = 10;
+const TITLE_TOP: i32 = 15;
+const TITLE_BOTTOM: i32 = 40;
+const MESSAGE_TOP: i32 = 42;
+const TITLE_FONT_SIZE: i32 = 18;
+const MESSAGE_FONT_SIZE: i32 = 14;
+const DETAILS_FONT_SIZE: i32 = 13;
+const CHEVRON_FONT_SIZE: i32 = 12;
+const FOOTER_FONT_SIZE: i32 = 12;
+/// Room the footer line takes at the bottom of the collapsed toast.
+const FOOTER_HEIGHT: i32 = 16;
 
 const COLOR_BG: u32 = 0x00333333;
 const COLOR_BORDER_NORMAL: u32 = 0x004B64B2;
@@ -34,15 +53,36 @@ const TIMER_FADE: usize = 1;
 const TIMER_START_FADE: usize = 2;
 const TIMER_REPOSITION: usize = 3;
 const TIMER_CHECK_BOTTOM: usize = 4;
+const TIMER_COUNTDOWN: usize = 5;
+const TIMER_CLICK: usize = 6;
+const TIMER_SNOOZE: usize = 7;
+const TIMER_KEYBOARD: usize = 8;
+const TIMER_REPEAT_SOUND: usize = 9;
+const TIMER_PULSE: usize = 10;
+
+// Attention pulse of input-required toasts: one breath per period, redrawn
+// every tick, dimming the border toward the background by up to PULSE_DEPTH
+const PULSE_PERIOD_MS: f32 = 1600.0;
+const PULSE_TICK_MS: u32 = 40;
+const PULSE_DEPTH: f32 = 0.6;
+
+/// Rightward speed at release (px/ms) that turns a drag into a swipe-to-dismiss.
+const SWIPE_VELOCITY: f32 = 1.5;
+/// A pause this long before release cancels the flick.
+const SWIPE_IDLE_MS: u128 = 100;
 
-const DISPLAY_MS: u32 = 3000;
 const FADE_MS: u32 = 1000;
 const INITIAL_ALPHA: u8 = 230;
 
 const TOAST_CLASS_NAME: &str = "ClaudeCodeToast";
+/// Window property set on persistent toasts so other toasts can see their policy.
+const PERSISTENT_PROP: PCWSTR = w!("ClaudeCodeToast.Persistent");
 
 const WM_TOAST_CHECK_POSITION: u32 = WM_USER + 101;
 const WM_TOAST_PAUSE_TIMER: u32 = WM_USER + 102;
+const WM_TOAST_MOVE_TO: u32 = WM_USER + 103;
+/// From the tray agent: config.toml changed.
+const WM_TOAST_CONFIG_CHANGED: u32 = WM_USER + 104;
 const WM_MOUSELEAVE: u32 = 0x02A3;
 
 // --- Global state for the toast window (per-process, one toast per process) ---
@@ -52,25 +92,102 @@ struct ToastState {
     title: String,
     message: String,
     input_mode: bool,
-    font_family: String,
+    // Font families tried in order per run of text, before the system fallback
+    title_fonts: Vec<String>,
+    body_fonts: Vec<String>,
     icon: HICON,
     default_icon_path: String,
+    // Window size (height may grow to fit the message)
+    width: i32,
+    height: i32,
+    monitor: HMONITOR,
+    work_area: RECT,
+    // Small line under the message, e.g. the turn's token usage
+    footer: String,
+    // Details section toggled by the chevron
+    details: String,
+    expanded: bool,
+    clipboard_text: String,
     // Activation targets
-    target_hwnd: HWND,
-    wt_hwnd: HWND,
-    wt_runtime_id: String,
+    target: crate::activate::Target,
     // Fade state
     alpha: u8,
     fade_step: u8,
     is_fading: bool,
+    // Countdown until the fade starts: frozen remaining time while paused,
+    // deadline while running
+    timeout: TimeoutPolicy,
+    countdown_remaining_ms: u32,
+    countdown_deadline: Option<std::time::Instant>,
     // Mouse state
     mouse_inside: bool,
+    drag: Option<Drag>,
+    // Swiped away: sliding off-screen, destroyed on arrival
+    dismissing: bool,
+    // Set by a double-click so the release that follows isn't also a click
+    double_clicked: bool,
+    // Wheel movement not yet turned into a stack rotation (touchpads scroll in small steps)
+    wheel_delta: i32,
+    // Keyboard mode: whether the toast has the focus, which button has the
+    // focus ring, and the window to hand the focus back to
+    has_focus: bool,
+    focus: FocusTarget,
+    previous_foreground: HWND,
+    // Screen-reader view of the toast, created with the window
+    uia_provider: Option<IRawElementProviderSimple>,
+    // Played again every `repeat_sound_secs` until the toast goes away
+    sound_file: Option<String>,
+    // When the border started pulsing; None when it doesn't pulse
+    pulse_start: Option<std::time::Instant>,
     // Stacking state
+    target_x: i32,
     target_y: i32,
     is_bottom_toast: bool,
-    taskbar_edge: u32,
+    corner: Corner,
     // Clicked flag
     clicked: bool,
+    // Faded out on its own
+    timed_out: bool,
+}
+
+/// Left button (or a touch/pen contact) held on the toast. Becomes an active
+/// drag once the pointer moves past the system drag threshold; until then a
+/// release is a click.
+#[derive(Clone, Copy)]
+struct Drag {
+    cursor: POINT,
+    origin: POINT,
+    active: bool,
+    // Latest sample and smoothed horizontal speed (px/ms), for swipe detection
+    last: POINT,
+    last_at: std::time::Instant,
+    velocity_x: f32,
+}
+
+/// Element with the keyboard focus ring, cycled by Tab.
+#[derive(Clone, Copy, PartialEq)]
+enum FocusTarget {
+    Body,
+    Chevron,
+    Close,
+}
+
+impl FocusTarget {
+    fn next(self, backward: bool) -> Self {
+        use FocusTarget::*;
+        match (self, backward) {
+            (Body, false) | (Close, true) => Chevron,
+            (Chevron, false) | (Body, true) => Close,
+            (Close, false) | (Chevron, true) => Body,
+        }
+    }
+}
+
+impl ToastState {
+    /// Dragged or swiped out of the stack: stack moves leave it alone.
+    fn detached(&self) -> bool {
+        self.dismissing || self.drag.is_some_and(|d| d.active)
+    }
 }
 
 thread_local! {
@@ -93,6 +210,49 @@ fn with_toast_mut<R>(f: impl FnOnce(&mut ToastState) -> R) -> R {
     })
 }
 
+/// Start (or resume) the display countdown that leads into the fade.
+/// Persistent toasts have no countdown.
+fn start_countdown(hwnd: HWND, state: &mut ToastState) {
+    let TimeoutPolicy::Timed(display_ms) = state.timeout else { return };
+    if state.countdown_remaining_ms == 0 {
+        state.countdown_remaining_ms = display_ms;
+    }
+    let ms = state.countdown_remaining_ms;
+    state.countdown_deadline = Some(std::time::Instant::now() + std::time::Duration::from_millis(ms as u64));
+    unsafe {
+        SetTimer(Some(hwnd), TIMER_START_FADE, ms, None);
+        if crate::config::get().toast.countdown_bar {
+            SetTimer(Some(hwnd), TIMER_COUNTDOWN, COUNTDOWN_TICK_MS, None);
+        }
+    }
+}
+
+/// Stop the display countdown, freezing the remaining time.
+fn pause_countdown(hwnd: HWND, state: &mut ToastState) {
+    if let Some(deadline) = state.countdown_deadline.take() {
+        let left = deadline.saturating_duration_since(std::time::Instant::now());
+        state.countdown_remaining_ms = left.as_millis() as u32;
+    }
+    unsafe {
+        let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
+        let _ = KillTimer(Some(hwnd), TIMER_COUNTDOWN);
+    }
+}
+
+/// Fraction of the display time left, for the countdown bar.
+/// None for persistent toasts, which have no bar.
+fn countdown_fraction(state: &ToastState) -> Option<f32> {
+    let TimeoutPolicy::Timed(display_ms) = state.timeout else { return None };
+    if state.is_fading {
+        return Some(0.0);
+    }
+    let left_ms = match state.countdown_deadline {
+        Some(deadline) => deadline.saturating_duration_since(std::time::Instant::now()).as_millis() as u32,
+        None => state.countdown_remaining_ms,
+    };
+    Some((left_ms as f32 / display_ms.max(1) as f32).clamp(0.0, 1.0))
+}
+
 fn make_font(height: i32, bold: bool, family: &str) -> HFONT {
     let face = crate::util::encode_wide(family);
     unsafe {
@@ -110,18 +270,36 @@ fn make_font(height: i32, bold: bool, family: &str) -> HFONT {
     }
 }
 
-fn is_point_in_close_button(x: i32, y: i32) -> bool {
-    let btn_left = WINDOW_WIDTH - CLOSE_BUTTON_MARGIN - CLOSE_BUTTON_SIZE;
+fn is_point_in_close_button(x: i32, y: i32, width: i32) -> bool {
+    let btn_left = width - CLOSE_BUTTON_MARGIN - CLOSE_BUTTON_SIZE;
     let btn_top = CLOSE_BUTTON_MARGIN;
     x >= btn_left && x <= btn_left + CLOSE_BUTTON_SIZE
         && y >= btn_top && y <= btn_top + CLOSE_BUTTON_SIZE
 }
 
+/// The chevron sits in the bottom-right corner of the collapsed area, under the close button.
+fn chevron_rect(width: i32, collapsed_height: i32) -> RECT {
+    let left = width - CLOSE_BUTTON_MARGIN - CLOSE_BUTTON_SIZE;
+    let top = collapsed_height - CLOSE_BUTTON_MARGIN - CLOSE_BUTTON_SIZE;
+    RECT { left, top, right: left + CLOSE_BUTTON_SIZE, bottom: top + CLOSE_BUTTON_SIZE }
+}
+
+fn is_point_in_chevron(x: i32, y: i32, width: i32, collapsed_height: i32) -> bool {
+    let r = chevron_rect(width, collapsed_height);
+    x >= r.left && x <= r.right && y >= r.top && y <= r.bottom
+}
+
+/// Width available to the message text (right of the icon, left of the button column).
+fn message_text_width(width: i32) -> i32 {
+    width - (ICON_PADDING + ICON_SIZE + ICON_PADDING) - (CLOSE_BUTTON_MARGIN + CLOSE_BUTTON_SIZE)
+}
+
 // --- Stacking helpers ---
 
 struct ToastInfo {
     hwnd: HWND,
     rect: RECT,
+    persistent: bool,
 }
 
 fn enum_other_toasts() -> Vec<ToastInfo> {
@@ -141,7 +319,8 @@ fn enum_other_toasts() -> Vec<ToastInfo> {
         if class == TOAST_CLASS_NAME && IsWindowVisible(hwnd).as_bool() {
             let mut rect = RECT::default();
             let _ = GetWindowRect(hwnd, &mut rect);
-            toasts.push(ToastInfo { hwnd, rect });
+            let persistent = !GetPropW(hwnd, PERSISTENT_PROP).is_invalid();
+            toasts.push(ToastInfo { hwnd, rect, persistent });
         }
         TRUE
     }
@@ -156,66 +335,290 @@ fn enum_other_toasts() -> Vec<ToastInfo> {
     toasts
 }
 
-fn calculate_position(work_area: &RECT, taskbar_edge: u32) -> (i32, i32) {
-    let other_toasts = enum_other_toasts();
+/// Screen corner the toast stack grows from.
+#[derive(Debug, Clone, Copy)]
+struct Corner {
+    top: bool,
+    left: bool,
+}
 
-    // X position
-    let x = if taskbar_edge == ABE_LEFT as u32 {
-        work_area.left
-    } else {
-        work_area.right - WINDOW_WIDTH
+impl Corner {
+    /// Whether the stack grows toward larger coordinates along `axis`
+    /// (downward from a top corner, rightward from a left corner).
+    fn grows_forward(self, axis: StackAxis) -> bool {
+        match axis {
+            StackAxis::Vertical => self.top,
+            StackAxis::Horizontal => self.left,
+        }
+    }
+}
+
+/// Leading coordinate of a toast along the stacking axis.
+fn stack_pos(rect: &RECT, axis: StackAxis) -> i32 {
+    match axis {
+        StackAxis::Vertical => rect.top,
+        StackAxis::Horizontal => rect.left,
+    }
+}
+
+/// Size of a toast along the stacking axis.
+fn stack_extent(rect: &RECT, axis: StackAxis) -> i32 {
+    match axis {
+        StackAxis::Vertical => rect.bottom - rect.top,
+        StackAxis::Horizontal => rect.right - rect.left,
+    }
+}
+
+/// Whether two toasts share a lane, i.e. overlap across the stacking axis
+/// (same column when stacking vertically, same row when horizontally).
+/// Only toasts in the same lane stack with each other.
+fn in_same_lane(a: &RECT, b: &RECT, axis: StackAxis) -> bool {
+    match axis {
+        StackAxis::Vertical => a.left < b.right && b.left < a.right,
+        StackAxis::Horizontal => a.top < b.bottom && b.top < a.bottom,
+    }
+}
+
+/// Where the first toast goes: a corner of the placement area and its
+/// distance from that corner's edges.
+#[derive(Debug, Clone, Copy)]
+struct Anchor {
+    corner: Corner,
+    margin_x: i32,
+    margin_y: i32,
+}
+
+/// The remembered drag placement if there is one, otherwise the configured
+/// position, monitor, and gaps.
+fn resolve_anchor() -> (Anchor, HMONITOR) {
+    let cfg = &crate::config::get().toast;
+    if cfg.remember_position {
+        if let Some(p) = crate::placement::load() {
+            match find_monitor(|device| device == p.monitor) {
+                Some(monitor) => {
+                    let corner = Corner { top: p.top, left: p.left };
+                    return (Anchor { corner, margin_x: p.offset_x, margin_y: p.offset_y }, monitor);
+                }
+                None => crate::debug_log!("Remembered monitor {} not found", p.monitor),
+            }
+        }
+    }
+
+    let anchor = Anchor {
+        corner: resolve_corner(cfg.position),
+        margin_x: cfg.edge_gap + cfg.offset_x,
+        margin_y: cfg.edge_gap + cfg.offset_y,
     };
+    (anchor, configured_monitor(&cfg.monitor))
+}
 
-    // Y position
-    let y = if other_toasts.is_empty() {
-        // First toast
-        if taskbar_edge == ABE_TOP as u32 {
-            work_area.top
-        } else {
-            work_area.bottom - WINDOW_HEIGHT
+/// Resolve the configured position; `auto` hugs the taskbar edge.
+fn resolve_corner(position: crate::config::Position) -> Corner {
+    use crate::config::Position;
+    match position {
+        Position::Auto => {
+            let taskbar_edge = detect_taskbar_edge();
+            Corner { top: taskbar_edge == ABE_TOP, left: taskbar_edge == ABE_LEFT }
         }
+        Position::BottomRight => Corner { top: false, left: false },
+        Position::TopRight => Corner { top: true, left: false },
+        Position::BottomLeft => Corner { top: false, left: true },
+        Position::TopLeft => Corner { top: true, left: true },
+    }
+}
+
+fn calculate_position(work_area: &RECT, anchor: Anchor, width: i32, height: i32) -> (i32, i32) {
+    let other_toasts = enum_other_toasts();
+    let cfg = &crate::config::get().toast;
+
+    let Anchor { corner, margin_x, margin_y } = anchor;
+    let gap = cfg.gap;
+
+    // First toast: in the corner
+    let mut x = if corner.left {
+        work_area.left + margin_x
     } else {
-        if taskbar_edge == ABE_TOP as u32 {
-            // Stack below: find lowest bottom
-            let lowest_bottom = other_toasts.iter().map(|t| t.rect.bottom).max().unwrap_or(work_area.top);
-            lowest_bottom
-        } else {
-            // Stack above: find highest top
-            let highest_top = other_toasts.iter().map(|t| t.rect.top).min().unwrap_or(work_area.bottom);
-            highest_top - WINDOW_HEIGHT
-        }
+        work_area.right - width - margin_x
     };
+    let mut y = if corner.top {
+        work_area.top + margin_y
+    } else {
+        work_area.bottom - height - margin_y
+    };
+
+    // Later toasts: one gap past the outermost one in the same lane along the stacking axis
+    let first = RECT { left: x, top: y, right: x + width, bottom: y + height };
+    let rects = other_toasts.iter().map(|t| t.rect).filter(|r| in_same_lane(r, &first, cfg.stack));
+    match (cfg.stack, corner.grows_forward(cfg.stack)) {
+        // Stack below: find lowest bottom
+        (StackAxis::Vertical, true) => y = rects.map(|r| r.bottom + gap).max().unwrap_or(y),
+        // Stack above: find highest top
+        (StackAxis::Vertical, false) => y = rects.map(|r| r.top).min().map_or(y, |top| top - gap - height),
+        // Stack to the right: find rightmost edge
+        (StackAxis::Horizontal, true) => x = rects.map(|r| r.right + gap).max().unwrap_or(x),
+        // Stack to the left: find leftmost edge
+        (StackAxis::Horizontal, false) => x = rects.map(|r| r.left).min().map_or(x, |left| left - gap - width),
+    }
 
     (x, y)
 }
 
-fn is_bottom_toast_check(hwnd: HWND, _taskbar_edge: u32) -> bool {
+fn is_bottom_toast_check(hwnd: HWND) -> bool {
     let other_toasts = enum_other_toasts();
     if other_toasts.is_empty() {
         return true;
     }
 
-    // Bottom toast = the one with the lowest HWND value (created earliest, closest to taskbar)
-    for t in &other_toasts {
-        if (t.hwnd.0 as usize) < (hwnd.0 as usize) {
-            return false;
-        }
-    }
-    true
+    // Bottom toast = the one nearest the corner in its lane (the front of the stack).
+    // Persistent toasts never fade, so they don't hold up the timed ones.
+    let axis = crate::config::get().toast.stack;
+    let forward = with_toast(|s| s.corner.grows_forward(axis));
+    let mut my_rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut my_rect); }
+    let my_pos = stack_pos(&my_rect, axis);
+    other_toasts
+        .iter()
+        .filter(|t| !t.persistent && in_same_lane(&t.rect, &my_rect, axis))
+        .all(|t| if forward { stack_pos(&t.rect, axis) > my_pos } else { stack_pos(&t.rect, axis) < my_pos })
 }
 
 fn notify_other_toasts_closing(my_hwnd: HWND) {
     let mut my_rect = RECT::default();
     unsafe { let _ = GetWindowRect(my_hwnd, &mut my_rect); }
 
+    // The gap after a closing toast goes away with it
+    let cfg = &crate::config::get().toast;
+    notify_other_toasts_shrunk(&my_rect, stack_extent(&my_rect, cfg.stack) + cfg.gap);
+}
+
+/// Tell the other toasts in the same lane that the toast at `my_rect` gave up
+/// `shrink` pixels of stack space (its full extent when closing, negative when growing).
+fn notify_other_toasts_shrunk(my_rect: &RECT, shrink: i32) {
+    let axis = crate::config::get().toast.stack;
+    let my_pos = stack_pos(my_rect, axis);
     let others = enum_other_toasts();
-    for t in &others {
+    for t in others.iter().filter(|t| in_same_lane(&t.rect, my_rect, axis)) {
         unsafe {
             let _ = SendMessageW(
                 t.hwnd,
                 WM_TOAST_CHECK_POSITION,
-                Some(WPARAM(my_rect.top as usize)),
-                Some(LPARAM(0)),
+                Some(WPARAM(my_pos as usize)),
+                Some(LPARAM(shrink as isize)),
+            );
+        }
+    }
+}
+
+/// Expand or collapse the details section, growing away from the taskbar
+/// and pushing the rest of the stack along with it.
+fn toggle_details(hwnd: HWND) {
+    let (expanded, width, collapsed_height, details, body_fonts, work_area, corner) = with_toast(|s| {
+        (s.expanded, s.width, s.height, s.details.clone(), s.body_fonts.clone(), s.work_area, s.corner)
+    });
+
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
+    let old_height = rect.bottom - rect.top;
+
+    let new_height = if expanded {
+        collapsed_height
+    } else {
+        let details_width = width - ICON_PADDING - TEXT_MARGIN;
+        let style = crate::text::Style { fonts: &body_fonts, height: DETAILS_FONT_SIZE, bold: false };
+        let (text_height, _) = crate::text::measure(&details, &style, details_width);
+        (collapsed_height + text_height + TEXT_MARGIN).min(work_area.bottom - work_area.top)
+    };
+    let delta = new_height - old_height;
+    let new_top = if corner.top { rect.top } else { rect.top - delta };
+
+    if !expanded {
+        // Keep the toast up while the user reads the details
+        unsafe {
+            let _ = SendMessageW(hwnd, WM_TOAST_PAUSE_TIMER, Some(WPARAM(1)), Some(LPARAM(0)));
+        }
+    }
+
+    with_toast_mut(|state| {
+        state.expanded = !expanded;
+        state.target_x = rect.left;
+        state.target_y = new_top;
+    });
+    // Side-by-side toasts are unaffected by a change in height
+    if crate::config::get().toast.stack == StackAxis::Vertical {
+        notify_other_toasts_shrunk(&rect, -delta);
+    }
+
+    unsafe {
+        let _ = SetWindowPos(
+            hwnd,
+            None,
+            rect.left, new_top, width, new_height,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+        let _ = InvalidateRect(Some(hwnd), None, true);
+    }
+}
+
+/// Rotate the lane this toast is in by one slot: `forward` sends the front
+/// toast to the back and brings the next one to the front, so a long stack can
+/// be triaged (including toasts pushed off-screen) without dismissing any.
+fn rotate_stack(hwnd: HWND, forward: bool) {
+    let cfg = &crate::config::get().toast;
+    let axis = cfg.stack;
+    let (grows_forward, persistent) =
+        with_toast(|s| (s.corner.grows_forward(axis), s.timeout == TimeoutPolicy::Persistent));
+
+    let mut my_rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut my_rect); }
+    let mut toasts: Vec<ToastInfo> = enum_other_toasts()
+        .into_iter()
+        .filter(|t| in_same_lane(&t.rect, &my_rect, axis))
+        .collect();
+    if toasts.is_empty() {
+        return;
+    }
+    toasts.push(ToastInfo { hwnd, rect: my_rect, persistent });
+
+    // Front slot first
+    toasts.sort_by_key(|t| stack_pos(&t.rect, axis));
+    if !grows_forward {
+        toasts.reverse();
+    }
+    let front = &toasts[0].rect;
+    let mut edge = if grows_forward { stack_pos(front, axis) } else { stack_pos(front, axis) + stack_extent(front, axis) };
+
+    if forward {
+        toasts.rotate_left(1);
+    } else {
+        toasts.rotate_right(1);
+    }
+
+    // Lay the lane out again from the front, and tell each toast its new place.
+    // The first timed toast in the new order is the one that counts down.
+    let front_timed = toasts.iter().position(|t| !t.persistent);
+    for (slot, t) in toasts.iter().enumerate() {
+        let extent = stack_extent(&t.rect, axis);
+        let pos = if grows_forward {
+            let pos = edge;
+            edge += extent + cfg.gap;
+            pos
+        } else {
+            edge -= extent;
+            let pos = edge;
+            edge -= cfg.gap;
+            pos
+        };
+        let (x, y) = match axis {
+            StackAxis::Vertical => (t.rect.left, pos),
+            StackAxis::Horizontal => (pos, t.rect.top),
+        };
+        let packed = ((y as u16 as u32) << 16) | (x as u16 as u32);
+        unsafe {
+            let _ = SendMessageW(
+                t.hwnd,
+                WM_TOAST_MOVE_TO,
+                Some(WPARAM((front_timed == Some(slot)) as usize)),
+                Some(LPARAM(packed as isize)),
             );
         }
     }
@@ -250,45 +653,391 @@ fn animate_to_position(hwnd: HWND) {
     let mut rect = RECT::default();
     unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
 
-    let (target_y, done) = with_toast_mut(|state| {
-        let current_y = rect.top;
-        let diff = state.target_y - current_y;
-
-        if diff == 0 {
-            return (0, true);
-        }
+    let (target_x, target_y) = with_toast(|state| (state.target_x, state.target_y));
+    let new_x = ease_toward(rect.left, target_x);
+    let new_y = ease_toward(rect.top, target_y);
 
-        let mut step = diff * 2 / 5;
-        if step == 0 {
-            step = if diff > 0 { 2 } else { -2 };
+    if new_x != rect.left || new_y != rect.top {
+        unsafe {
+            let _ = SetWindowPos(
+                hwnd,
+                None,
+                new_x, new_y, 0, 0,
+                SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
         }
+    }
 
-        let mut new_y = current_y + step;
-        if (state.target_y - new_y).abs() < 4 {
-            new_y = state.target_y;
+    if new_x == target_x && new_y == target_y {
+        unsafe { let _ = KillTimer(Some(hwnd), TIMER_REPOSITION); }
+        if with_toast(|s| s.dismissing) {
+            unsafe { let _ = DestroyWindow(hwnd); }
         }
+    }
+}
 
-        (new_y, new_y == state.target_y)
+/// Press on the toast at `cursor` (screen coordinates): might start a drag.
+fn begin_press(hwnd: HWND, cursor: POINT) {
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
+    with_toast_mut(|state| {
+        state.drag = Some(Drag {
+            cursor,
+            origin: POINT { x: rect.left, y: rect.top },
+            active: false,
+            last: cursor,
+            last_at: std::time::Instant::now(),
+            velocity_x: 0.0,
+        });
     });
+}
 
-    if done && target_y == 0 {
-        // diff was 0, just kill timer
+/// Follow the pointer while pressed, once it has moved past the system drag threshold.
+fn drag_move(hwnd: HWND, cursor: POINT) {
+    let Some(drag) = with_toast_mut(|state| {
+        let d = state.drag.as_mut()?;
+        let now = std::time::Instant::now();
+        let dt = (now - d.last_at).as_secs_f32() * 1000.0;
+        if dt > 0.0 {
+            d.velocity_x = 0.5 * d.velocity_x + 0.5 * (cursor.x - d.last.x) as f32 / dt;
+        }
+        d.last = cursor;
+        d.last_at = now;
+        Some(*d)
+    }) else { return };
+    let dx = cursor.x - drag.cursor.x;
+    let dy = cursor.y - drag.cursor.y;
+
+    if !drag.active {
+        let (threshold_x, threshold_y) = unsafe { (GetSystemMetrics(SM_CXDRAG), GetSystemMetrics(SM_CYDRAG)) };
+        if dx.abs() <= threshold_x && dy.abs() <= threshold_y {
+            return;
+        }
+        // Leaving the stack: the others close the gap behind this toast
+        notify_other_toasts_closing(hwnd);
         unsafe { let _ = KillTimer(Some(hwnd), TIMER_REPOSITION); }
-        return;
+        with_toast_mut(|state| {
+            if let Some(d) = state.drag.as_mut() {
+                d.active = true;
+            }
+        });
     }
 
     unsafe {
         let _ = SetWindowPos(
             hwnd,
             None,
-            rect.left, target_y, 0, 0,
+            drag.origin.x + dx, drag.origin.y + dy, 0, 0,
             SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
         );
     }
+}
 
-    if done {
-        unsafe { let _ = KillTimer(Some(hwnd), TIMER_REPOSITION); }
+/// Release the press. Returns true if it was a drag (dropped or swiped away),
+/// false if it was a click.
+fn end_press(hwnd: HWND) -> bool {
+    let Some(drag) = with_toast_mut(|state| state.drag.take()) else { return false };
+    if !drag.active {
+        return false;
+    }
+
+    let flicked = drag.last_at.elapsed().as_millis() < SWIPE_IDLE_MS
+        && drag.velocity_x >= SWIPE_VELOCITY
+        && (drag.last.x - drag.cursor.x).abs() > (drag.last.y - drag.cursor.y).abs();
+    if flicked || is_mostly_off_right_edge(hwnd) {
+        swipe_dismiss(hwnd);
+    } else {
+        finish_drag(hwnd);
     }
+    true
+}
+
+/// Whether more than half the toast was dragged past the right edge of its placement area.
+fn is_mostly_off_right_edge(hwnd: HWND) -> bool {
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
+    let area = placement_area(unsafe { MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST) });
+    (rect.left + rect.right) / 2 > area.right
+}
+
+/// Slide the toast off the right edge of its monitor, then close it.
+/// It already left the stack when the drag started.
+fn swipe_dismiss(hwnd: HWND) {
+    let mut rect = RECT::default();
+    let mut mi = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        let _ = GetWindowRect(hwnd, &mut rect);
+        let _ = GetMonitorInfoW(MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST), &mut mi);
+        let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
+        let _ = KillTimer(Some(hwnd), TIMER_FADE);
+        let _ = KillTimer(Some(hwnd), TIMER_CHECK_BOTTOM);
+    }
+    with_toast_mut(|state| {
+        pause_countdown(hwnd, state);
+        state.dismissing = true;
+        state.target_x = mi.rcMonitor.right.max(rect.left);
+        state.target_y = rect.top;
+    });
+    unsafe { SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None); }
+}
+
+/// Drop a dragged toast: pull it back inside the placement area, adopt the
+/// nearest corner of the monitor it landed on, and remember the spot for
+/// future toasts.
+fn finish_drag(hwnd: HWND) {
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+
+    let monitor = unsafe { MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST) };
+    let area = placement_area(monitor);
+    let x = rect.left.clamp(area.left, (area.right - width).max(area.left));
+    let y = rect.top.clamp(area.top, (area.bottom - height).max(area.top));
+
+    let center_x = x + width / 2;
+    let center_y = y + height / 2;
+    let corner = Corner {
+        top: center_y - area.top < area.bottom - center_y,
+        left: center_x - area.left < area.right - center_x,
+    };
+    let offset_x = if corner.left { x - area.left } else { area.right - x - width };
+    let offset_y = if corner.top { y - area.top } else { area.bottom - y - height };
+
+    with_toast_mut(|state| {
+        state.monitor = monitor;
+        state.work_area = area;
+        state.corner = corner;
+        state.target_x = x;
+        state.target_y = y;
+    });
+    if x != rect.left || y != rect.top {
+        unsafe { SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None); }
+    }
+
+    if !crate::config::get().toast.remember_position {
+        return;
+    }
+    match monitor_device_name(monitor) {
+        Some(device) => crate::placement::save(&crate::placement::Placement {
+            monitor: device,
+            top: corner.top,
+            left: corner.left,
+            offset_x,
+            offset_y,
+        }),
+        None => crate::debug_log!("No device name for monitor, placement not saved"),
+    }
+}
+
+/// Whether the toast is still sliding toward its target position.
+fn is_animating(hwnd: HWND) -> bool {
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
+    with_toast(|s| (s.target_x, s.target_y)) != (rect.left, rect.top)
+}
+
+/// One animation step: cover 40% of the remaining distance, snapping when close.
+fn ease_toward(current: i32, target: i32) -> i32 {
+    let diff = target - current;
+    if diff == 0 {
+        return target;
+    }
+
+    let mut step = diff * 2 / 5;
+    if step == 0 {
+        step = if diff > 0 { 2 } else { -2 };
+    }
+
+    let next = current + step;
+    if (target - next).abs() < 4 {
+        target
+    } else {
+        next
+    }
+}
+
+/// Left click (or tap) at client coordinates: chevron, close button, or body.
+fn click_at(hwnd: HWND, x: i32, y: i32) {
+    let (width, collapsed_height) = with_toast(|s| (s.width, s.height));
+    if is_point_in_chevron(x, y, width, collapsed_height) {
+        toggle_details(hwnd);
+    } else if is_point_in_close_button(x, y, width) {
+        close_toast(hwnd);
+    } else if crate::config::get().mouse.double != MouseAction::None {
+        // Wait to see whether this turns into a double-click
+        unsafe { SetTimer(Some(hwnd), TIMER_CLICK, GetDoubleClickTime(), None); }
+    } else {
+        run_action(hwnd, crate::config::get().mouse.left);
+    }
+}
+
+/// Carry out a configured mouse action.
+fn run_action(hwnd: HWND, action: MouseAction) {
+    crate::debug_log!("Mouse action: {:?}", action);
+    match action {
+        MouseAction::Activate => unsafe {
+            let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
+            let _ = KillTimer(Some(hwnd), TIMER_FADE);
+            notify_other_toasts_closing(hwnd);
+            let _ = ShowWindow(hwnd, SW_HIDE);
+
+            let (target, input_mode) = with_toast_mut(|state| {
+                state.clicked = true;
+                (state.target.clone(), state.input_mode)
+            });
+            if crate::activate::activate_window(&target) {
+                crate::activate::send_keys(target.window(), &crate::config::get().events.get(input_mode).keys);
+            }
+
+            let _ = DestroyWindow(hwnd);
+        },
+        MouseAction::Dismiss => close_toast(hwnd),
+        MouseAction::Copy => {
+            let text = with_toast(|s| s.clipboard_text.clone());
+            if !crate::util::set_clipboard_text(hwnd, &text) {
+                crate::warn_log!("Failed to copy to clipboard");
+            }
+            close_toast(hwnd);
+        }
+        MouseAction::Snooze => snooze(hwnd),
+        MouseAction::History => crate::history::open(),
+        MouseAction::None => {}
+    }
+}
+
+/// Close without activating anything; the rest of the stack closes the gap.
+fn close_toast(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
+        let _ = KillTimer(Some(hwnd), TIMER_FADE);
+        // A snoozed toast is hidden and holds no place in the stack
+        if IsWindowVisible(hwnd).as_bool() {
+            notify_other_toasts_closing(hwnd);
+        }
+        return_focus(hwnd);
+        let _ = DestroyWindow(hwnd);
+    }
+}
+
+/// Hide the toast for `snooze_minutes`, letting the stack close up behind it.
+fn snooze(hwnd: HWND) {
+    let minutes = crate::config::get().mouse.snooze_minutes.max(1);
+    unsafe {
+        for timer in [TIMER_START_FADE, TIMER_FADE, TIMER_COUNTDOWN, TIMER_CHECK_BOTTOM, TIMER_REPOSITION] {
+            let _ = KillTimer(Some(hwnd), timer);
+        }
+        notify_other_toasts_closing(hwnd);
+        let _ = ShowWindow(hwnd, SW_HIDE);
+    }
+    with_toast_mut(|state| {
+        state.is_fading = false;
+        state.alpha = INITIAL_ALPHA;
+        state.countdown_remaining_ms = 0;
+        state.countdown_deadline = None;
+        state.is_bottom_toast = false;
+        state.expanded = false;
+        state.mouse_inside = false;
+    });
+    // The pointer went away with the toast: let the others count down again
+    notify_all_toasts_pause_timer(false);
+    unsafe { SetTimer(Some(hwnd), TIMER_SNOOZE, minutes.saturating_mul(60_000), None); }
+    crate::debug_log!("Snoozed for {} min", minutes);
+}
+
+/// Show a snoozed toast again, at the end of the stack with a full countdown.
+fn wake_from_snooze(hwnd: HWND) {
+    let (width, height) = with_toast(|s| (s.width, s.height));
+    let (anchor, monitor) = resolve_anchor();
+    let work_area = placement_area(monitor);
+    let (x, y) = calculate_position(&work_area, anchor, width, height);
+    with_toast_mut(|state| {
+        state.monitor = monitor;
+        state.work_area = work_area;
+        state.corner = anchor.corner;
+        state.target_x = x;
+        state.target_y = y;
+    });
+    unsafe {
+        let _ = SetWindowPos(hwnd, None, x, y, width, height, SWP_NOZORDER | SWP_NOACTIVATE);
+        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), INITIAL_ALPHA, LWA_ALPHA);
+    }
+    start_display_timer(hwnd);
+    unsafe { let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE); }
+}
+
+/// Keyboard mode: take the focus from whatever the user was in, for `keyboard_focus_ms`.
+fn take_focus(hwnd: HWND) {
+    let previous = unsafe { GetForegroundWindow() };
+    with_toast_mut(|state| {
+        state.previous_foreground = previous;
+        state.focus = FocusTarget::Body;
+    });
+    crate::activate::activate_hwnd(hwnd);
+    let ms = crate::config::get().toast.keyboard_focus_ms;
+    unsafe { SetTimer(Some(hwnd), TIMER_KEYBOARD, ms, None); }
+}
+
+/// Hand the focus back to the window the user was in, if the toast still has it.
+fn return_focus(hwnd: HWND) {
+    let previous = with_toast(|s| s.previous_foreground);
+    unsafe {
+        if GetForegroundWindow() == hwnd && !previous.is_invalid() && IsWindow(Some(previous)).as_bool() {
+            let _ = SetForegroundWindow(previous);
+        }
+    }
+}
+
+/// Keyboard mode keys. Returns false for keys the toast doesn't use; those
+/// (other than a modifier on its own) were meant for the window the user was
+/// in, so the focus goes straight back there.
+fn handle_key(hwnd: HWND, vk: u16) -> bool {
+    let handled = [VK_RETURN, VK_SPACE, VK_ESCAPE, VK_TAB].iter().any(|key| key.0 == vk);
+    let modifier = [VK_SHIFT, VK_CONTROL, VK_MENU].iter().any(|key| key.0 == vk);
+    if handled {
+        unsafe { let _ = KillTimer(Some(hwnd), TIMER_KEYBOARD); }
+    } else if !modifier {
+        return_focus(hwnd);
+    }
+    match vk {
+        v if v == VK_RETURN.0 || v == VK_SPACE.0 => match with_toast(|s| s.focus) {
+            FocusTarget::Body => run_action(hwnd, MouseAction::Activate),
+            FocusTarget::Chevron => toggle_details(hwnd),
+            FocusTarget::Close => close_toast(hwnd),
+        },
+        v if v == VK_ESCAPE.0 => close_toast(hwnd),
+        v if v == VK_TAB.0 => {
+            let backward = unsafe { GetKeyState(VK_SHIFT.0 as i32) } < 0;
+            with_toast_mut(|state| state.focus = state.focus.next(backward));
+            unsafe { let _ = InvalidateRect(Some(hwnd), None, false); }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// The bottom toast counts down right away; the others wait their turn.
+fn start_display_timer(hwnd: HWND) {
+    if is_bottom_toast_check(hwnd) {
+        with_toast_mut(|state| {
+            state.is_bottom_toast = true;
+            start_countdown(hwnd, state);
+        });
+    } else {
+        with_toast_mut(|state| state.is_bottom_toast = false);
+        unsafe { SetTimer(Some(hwnd), TIMER_CHECK_BOTTOM, 200, None); }
+    }
+}
+
+/// Touch and pen input; the mouse arrives as WM_*BUTTON* messages.
+fn is_touch_pointer(wparam: WPARAM) -> bool {
+    let pointer_id = (wparam.0 & 0xFFFF) as u32;
+    let mut kind = POINTER_INPUT_TYPE::default();
+    let known = unsafe { GetPointerType(pointer_id, &mut kind).is_ok() };
+    known && (kind == PT_TOUCH || kind == PT_PEN)
 }
 
 // --- WndProc ---
@@ -309,8 +1058,17 @@ unsafe extern "system" fn wnd_proc(
             match wparam.0 {
                 TIMER_START_FADE => {
                     let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
-                    with_toast_mut(|state| state.is_fading = true);
+                    let _ = KillTimer(Some(hwnd), TIMER_COUNTDOWN);
+                    with_toast_mut(|state| {
+                        state.is_fading = true;
+                        state.countdown_deadline = None;
+                        state.countdown_remaining_ms = 0;
+                    });
                     SetTimer(Some(hwnd), TIMER_FADE, 16, None);
+                    let _ = InvalidateRect(Some(hwnd), None, false);
+                }
+                TIMER_COUNTDOWN => {
+                    let _ = InvalidateRect(Some(hwnd), None, false);
                 }
                 TIMER_FADE => {
                     let should_destroy = with_toast_mut(|state| {
@@ -325,6 +1083,7 @@ unsafe extern "system" fn wnd_proc(
                     });
                     if should_destroy {
                         let _ = KillTimer(Some(hwnd), TIMER_FADE);
+                        with_toast_mut(|state| state.timed_out = true);
                         notify_other_toasts_closing(hwnd);
                         let _ = DestroyWindow(hwnd);
                     }
@@ -332,55 +1091,136 @@ unsafe extern "system" fn wnd_proc(
                 TIMER_REPOSITION => {
                     animate_to_position(hwnd);
                 }
-                TIMER_CHECK_BOTTOM => {
-                    let taskbar_edge = with_toast(|s| s.taskbar_edge);
-                    if is_bottom_toast_check(hwnd, taskbar_edge) {
-                        with_toast_mut(|state| {
-                            state.is_bottom_toast = true;
-                        });
-                        let _ = KillTimer(Some(hwnd), TIMER_CHECK_BOTTOM);
-                        SetTimer(Some(hwnd), TIMER_START_FADE, DISPLAY_MS, None);
-                    }
+                TIMER_CLICK => {
+                    // No double-click followed: it was a single click
+                    let _ = KillTimer(Some(hwnd), TIMER_CLICK);
+                    run_action(hwnd, crate::config::get().mouse.left);
+                }
+                TIMER_KEYBOARD => {
+                    // No key pressed in time: back to what the user was doing
+                    let _ = KillTimer(Some(hwnd), TIMER_KEYBOARD);
+                    return_focus(hwnd);
+                }
+                TIMER_SNOOZE => {
+                    let _ = KillTimer(Some(hwnd), TIMER_SNOOZE);
+                    wake_from_snooze(hwnd);
+                }
+                // Silent while snoozed, and once notifications are muted
+                TIMER_REPEAT_SOUND if IsWindowVisible(hwnd).as_bool() && !crate::mute::is_muted() => {
+                    let sound_file = with_toast(|s| s.sound_file.clone());
+                    // Detached: cut off if the toast closes mid-sound
+                    drop(crate::sound::play(&sound_file));
+                }
+                TIMER_PULSE if IsWindowVisible(hwnd).as_bool() => {
+                    draw_pulse_border(hwnd);
+                }
+                // Wait until the toast settles, so it isn't judged mid-slide
+                TIMER_CHECK_BOTTOM if !is_animating(hwnd) && is_bottom_toast_check(hwnd) => {
+                    let _ = KillTimer(Some(hwnd), TIMER_CHECK_BOTTOM);
+                    with_toast_mut(|state| {
+                        state.is_bottom_toast = true;
+                        if !state.expanded {
+                            start_countdown(hwnd, state);
+                        }
+                    });
                 }
                 _ => {}
             }
             LRESULT(0)
         }
 
-        WM_LBUTTONUP => {
+        WM_LBUTTONDOWN => {
+            // Might be the start of a drag; decided by WM_MOUSEMOVE
+            if with_toast(|s| s.dismissing) {
+                return LRESULT(0);
+            }
+            let mut cursor = POINT::default();
+            let _ = GetCursorPos(&mut cursor);
+            begin_press(hwnd, cursor);
+            SetCapture(hwnd);
+            LRESULT(0)
+        }
+
+        WM_LBUTTONDBLCLK if crate::config::get().mouse.double != MouseAction::None => {
             let x = (lparam.0 & 0xFFFF) as i16 as i32;
             let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let (width, collapsed_height) = with_toast(|s| (s.width, s.height));
+            let on_button = is_point_in_chevron(x, y, width, collapsed_height)
+                || is_point_in_close_button(x, y, width);
+            if on_button || with_toast(|s| s.detached()) {
+                // Buttons take each click on its own
+                return wnd_proc(hwnd, WM_LBUTTONDOWN, wparam, lparam);
+            }
+            let _ = KillTimer(Some(hwnd), TIMER_CLICK);
+            with_toast_mut(|state| state.double_clicked = true);
+            run_action(hwnd, crate::config::get().mouse.double);
+            LRESULT(0)
+        }
 
-            if is_point_in_close_button(x, y) {
-                // Close button click
-                let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
-                let _ = KillTimer(Some(hwnd), TIMER_FADE);
-                notify_other_toasts_closing(hwnd);
-                let _ = DestroyWindow(hwnd);
-            } else {
-                // Body click: activate window
-                let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
-                let _ = KillTimer(Some(hwnd), TIMER_FADE);
-                notify_other_toasts_closing(hwnd);
-                let _ = ShowWindow(hwnd, SW_HIDE);
-
-                let (target, wt, rid) = with_toast_mut(|state| {
-                    state.clicked = true;
-                    (state.target_hwnd, state.wt_hwnd, state.wt_runtime_id.clone())
-                });
-                crate::activate::activate_window(target, wt, &rid);
+        // Double-click unmapped: the second click is an ordinary press
+        WM_LBUTTONDBLCLK => wnd_proc(hwnd, WM_LBUTTONDOWN, wparam, lparam),
+
+        WM_LBUTTONUP => {
+            if with_toast_mut(|state| std::mem::take(&mut state.double_clicked)) {
+                return LRESULT(0);
+            }
+            let dragged = end_press(hwnd);
+            if GetCapture() == hwnd {
+                let _ = ReleaseCapture();
+            }
+            if !dragged && !with_toast(|s| s.dismissing) {
+                let x = (lparam.0 & 0xFFFF) as i16 as i32;
+                let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+                click_at(hwnd, x, y);
+            }
+            LRESULT(0)
+        }
 
-                let _ = DestroyWindow(hwnd);
+        WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP if is_touch_pointer(wparam) => {
+            // Touch and pen: same press/drag/swipe handling as the mouse; a tap is a click.
+            // Handled here rather than left to mouse promotion so a swipe isn't read as a drag-select.
+            let point = POINT {
+                x: (lparam.0 & 0xFFFF) as i16 as i32,
+                y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+            };
+            match msg {
+                WM_POINTERDOWN => {
+                    if !with_toast(|s| s.dismissing) {
+                        // No hover with touch: hold the timers while in contact
+                        with_toast_mut(|state| state.mouse_inside = true);
+                        notify_all_toasts_pause_timer(true);
+                        begin_press(hwnd, point);
+                    }
+                }
+                WM_POINTERUPDATE => drag_move(hwnd, point),
+                _ => {
+                    let pressed = with_toast(|s| s.drag.is_some());
+                    if pressed && !end_press(hwnd) {
+                        let mut client = point;
+                        let _ = ScreenToClient(hwnd, &mut client);
+                        click_at(hwnd, client.x, client.y);
+                    }
+                    with_toast_mut(|state| state.mouse_inside = false);
+                    notify_all_toasts_pause_timer(false);
+                }
             }
             LRESULT(0)
         }
 
-        WM_RBUTTONUP => {
-            // Right click: close without activation
-            let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
-            let _ = KillTimer(Some(hwnd), TIMER_FADE);
-            notify_other_toasts_closing(hwnd);
-            let _ = DestroyWindow(hwnd);
+        WM_POINTERCAPTURECHANGED => {
+            if with_toast_mut(|state| state.drag.take()).is_some_and(|d| d.active) {
+                finish_drag(hwnd);
+            }
+            LRESULT(0)
+        }
+
+        WM_RBUTTONUP | WM_MBUTTONUP => {
+            // Already out of the stack while dragged or swiped
+            if with_toast(|s| s.detached()) {
+                return LRESULT(0);
+            }
+            let mouse = &crate::config::get().mouse;
+            run_action(hwnd, if msg == WM_RBUTTONUP { mouse.right } else { mouse.middle });
             LRESULT(0)
         }
 
@@ -400,49 +1240,76 @@ unsafe extern "system" fn wnd_proc(
                 // Pause all toasts
                 notify_all_toasts_pause_timer(true);
             }
+            let mut cursor = POINT::default();
+            let _ = GetCursorPos(&mut cursor);
+            drag_move(hwnd, cursor);
+            LRESULT(0)
+        }
+
+        WM_CAPTURECHANGED => {
+            // Capture taken away mid-drag (e.g. by Alt+Tab): drop the toast where it is
+            if with_toast_mut(|state| state.drag.take()).is_some_and(|d| d.active) {
+                finish_drag(hwnd);
+            }
             LRESULT(0)
         }
 
         WM_MOUSELEAVE => {
-            with_toast_mut(|state| state.mouse_inside = false);
+            let dragging = with_toast_mut(|state| {
+                state.mouse_inside = false;
+                state.drag.is_some()
+            });
+            if dragging {
+                // The window trails the cursor; stay paused until the drag ends
+                return LRESULT(0);
+            }
             // Resume all toasts
             notify_all_toasts_pause_timer(false);
             LRESULT(0)
         }
 
         x if x == WM_TOAST_CHECK_POSITION => {
-            let closed_toast_y = wparam.0 as i32;
+            // Sent when a toast closes (lparam = its extent) or resizes (lparam = extent lost).
+            // wparam is its position along the stacking axis (top, or left when horizontal).
+            let closed_toast_pos = wparam.0 as i32;
+            let closed_toast_extent = lparam.0 as i32;
             let mut my_rect = RECT::default();
             let _ = GetWindowRect(hwnd, &mut my_rect);
 
-            let taskbar_edge = with_toast(|s| s.taskbar_edge);
+            let axis = crate::config::get().toast.stack;
+            let my_pos = stack_pos(&my_rect, axis);
 
             // Update position target (borrow released before is_bottom_toast_check)
             with_toast_mut(|state| {
-                if state.taskbar_edge == ABE_TOP as u32 {
-                    // Top taskbar: if we're below the closed toast, move up
-                    if my_rect.top > closed_toast_y {
-                        state.target_y = my_rect.top - WINDOW_HEIGHT;
-                        SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None);
-                    }
+                if state.detached() {
+                    return;
+                }
+                // Only toasts further from the corner than the closed one close the gap
+                let new_pos = if state.corner.grows_forward(axis) {
+                    (my_pos > closed_toast_pos).then(|| my_pos - closed_toast_extent)
                 } else {
-                    // Bottom taskbar: if we're above the closed toast, move down
-                    if my_rect.top < closed_toast_y {
-                        state.target_y = my_rect.top + WINDOW_HEIGHT;
-                        SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None);
+                    (my_pos < closed_toast_pos).then(|| my_pos + closed_toast_extent)
+                };
+                if let Some(pos) = new_pos {
+                    state.target_x = my_rect.left;
+                    state.target_y = my_rect.top;
+                    match axis {
+                        StackAxis::Vertical => state.target_y = pos,
+                        StackAxis::Horizontal => state.target_x = pos,
                     }
+                    SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None);
                 }
             });
 
             // Check bottom toast status OUTSIDE the borrow to avoid double-borrow
             // (is_bottom_toast_check -> enum_other_toasts -> callback borrows TOAST)
             let is_already_bottom = with_toast(|s| s.is_bottom_toast);
-            if !is_already_bottom && is_bottom_toast_check(hwnd, taskbar_edge) {
+            if !is_already_bottom && is_bottom_toast_check(hwnd) {
                 with_toast_mut(|state| {
                     state.is_bottom_toast = true;
                     let _ = KillTimer(Some(hwnd), TIMER_CHECK_BOTTOM);
-                    if !state.mouse_inside {
-                        SetTimer(Some(hwnd), TIMER_START_FADE, DISPLAY_MS, None);
+                    if !state.mouse_inside && !state.expanded {
+                        start_countdown(hwnd, state);
                     }
                 });
             }
@@ -450,29 +1317,138 @@ unsafe extern "system" fn wnd_proc(
             LRESULT(0)
         }
 
+        WM_MOUSEWHEEL => {
+            let delta = ((wparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let steps = with_toast_mut(|state| {
+                state.wheel_delta += delta;
+                let steps = state.wheel_delta / WHEEL_DELTA as i32;
+                state.wheel_delta -= steps * WHEEL_DELTA as i32;
+                steps
+            });
+            if !with_toast(|s| s.detached()) {
+                // Scrolling down brings the next toast to the front
+                for _ in 0..steps.abs() {
+                    rotate_stack(hwnd, steps < 0);
+                }
+            }
+            LRESULT(0)
+        }
+
+        x if x == WM_TOAST_MOVE_TO => {
+            // Sent by a stack rotation: lparam packs the new position,
+            // wparam is 1 for the toast that now counts down
+            let x = (lparam.0 & 0xFFFF) as i16 as i32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let is_front = wparam.0 == 1;
+            with_toast_mut(|state| {
+                if state.detached() {
+                    return;
+                }
+                state.target_x = x;
+                state.target_y = y;
+                SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None);
+
+                if is_front {
+                    let _ = KillTimer(Some(hwnd), TIMER_CHECK_BOTTOM);
+                    state.is_bottom_toast = true;
+                    if !state.mouse_inside && !state.expanded {
+                        start_countdown(hwnd, state);
+                    }
+                } else if state.is_bottom_toast {
+                    state.is_bottom_toast = false;
+                    pause_countdown(hwnd, state);
+                    SetTimer(Some(hwnd), TIMER_CHECK_BOTTOM, 200, None);
+                }
+            });
+            LRESULT(0)
+        }
+
         x if x == WM_TOAST_PAUSE_TIMER => {
             let pause = wparam.0 == 1;
 
             with_toast_mut(|state| {
                 if pause {
                     if state.is_fading {
+                        // Interrupted fade: restore opacity; the countdown restarts in full
                         let _ = KillTimer(Some(hwnd), TIMER_FADE);
                         state.is_fading = false;
                         state.alpha = INITIAL_ALPHA;
                         let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), INITIAL_ALPHA, LWA_ALPHA);
                     }
-                    let _ = KillTimer(Some(hwnd), TIMER_START_FADE);
+                    pause_countdown(hwnd, state);
                 } else {
-                    // Resume: only start fade timer if bottom toast and mouse not inside
-                    if state.is_bottom_toast && !state.mouse_inside {
-                        SetTimer(Some(hwnd), TIMER_START_FADE, DISPLAY_MS, None);
+                    // Resume: only start fade timer if bottom toast, mouse not inside, and collapsed
+                    if state.is_bottom_toast && !state.mouse_inside && !state.expanded && !state.has_focus {
+                        start_countdown(hwnd, state);
                     }
                 }
             });
+            let _ = InvalidateRect(Some(hwnd), None, false);
+            LRESULT(0)
+        }
+
+        WM_SETTINGCHANGE => {
+            // Work area changed (taskbar moved/resized), or Explorer broadcast
+            // "TraySettings" because taskbar options such as auto-hide changed
+            let work_area_changed = wparam.0 == SPI_SETWORKAREA.0 as usize;
+            let tray_changed = lparam.0 != 0
+                && PCWSTR(lparam.0 as *const u16).to_string().is_ok_and(|area| area == "TraySettings");
+            if work_area_changed || tray_changed {
+                refresh_placement(hwnd);
+            }
+            LRESULT(0)
+        }
+
+        WM_TOAST_CONFIG_CHANGED => {
+            crate::config::reload();
+            refresh_placement(hwnd);
+            let _ = InvalidateRect(Some(hwnd), None, false);
             LRESULT(0)
         }
 
+        WM_DISPLAYCHANGE => {
+            // Resolution change or monitor added/removed
+            refresh_placement(hwnd);
+            LRESULT(0)
+        }
+
+        WM_MOUSEACTIVATE => {
+            // Keyboard mode makes the toast activatable; clicks still shouldn't focus it
+            LRESULT(MA_NOACTIVATE as isize)
+        }
+
+        WM_ACTIVATE => {
+            let active = (wparam.0 & 0xFFFF) as u32 != WA_INACTIVE;
+            with_toast_mut(|state| state.has_focus = active);
+            if !active {
+                let _ = KillTimer(Some(hwnd), TIMER_KEYBOARD);
+            }
+            // Focus holds the countdown like hovering does
+            notify_all_toasts_pause_timer(active);
+            let _ = InvalidateRect(Some(hwnd), None, false);
+            LRESULT(0)
+        }
+
+        WM_KEYDOWN if handle_key(hwnd, wparam.0 as u16) => LRESULT(0),
+
+        WM_CLOSE => {
+            // From --dismiss-all
+            close_toast(hwnd);
+            LRESULT(0)
+        }
+
+        WM_GETOBJECT => {
+            let provider = with_toast(|s| s.uia_provider.clone());
+            provider
+                .and_then(|p| crate::accessibility::handle_get_object(hwnd, wparam, lparam, &p))
+                .unwrap_or_else(|| DefWindowProcW(hwnd, msg, wparam, lparam))
+        }
+
         WM_DESTROY => {
+            let _ = RemovePropW(hwnd, PERSISTENT_PROP);
+            if let Some(provider) = with_toast_mut(|s| s.uia_provider.take()) {
+                crate::accessibility::disconnect(hwnd, &provider);
+            }
             PostQuitMessage(0);
             LRESULT(0)
         }
@@ -483,44 +1459,117 @@ unsafe extern "system" fn wnd_proc(
 
 // --- Paint ---
 
+/// Border color at this point of the attention pulse, or None when the toast doesn't pulse.
+fn pulse_color(state: &ToastState) -> Option<u32> {
+    let elapsed = state.pulse_start?.elapsed().as_millis() as f32;
+    let phase = (elapsed % PULSE_PERIOD_MS) / PULSE_PERIOD_MS * std::f32::consts::TAU;
+    // 0 at the start of each period (full color), 1 halfway (dimmest)
+    let dim = (1.0 - phase.cos()) / 2.0 * PULSE_DEPTH;
+    let channel = |shift: u32| {
+        let from = ((COLOR_BORDER_INPUT >> shift) & 0xFF) as f32;
+        let to = ((COLOR_BG >> shift) & 0xFF) as f32;
+        ((from + (to - from) * dim).round() as u32) << shift
+    };
+    Some(channel(0) | channel(8) | channel(16))
+}
+
+unsafe fn fill_border(hdc: HDC, width: i32, height: i32, color: u32) {
+    let brush = CreateSolidBrush(COLORREF(color));
+    let borders = [
+        RECT { left: 0, top: 0, right: width, bottom: BORDER_WIDTH },
+        RECT { left: 0, top: height - BORDER_WIDTH, right: width, bottom: height },
+        RECT { left: 0, top: 0, right: BORDER_WIDTH, bottom: height },
+        RECT { left: width - BORDER_WIDTH, top: 0, right: width, bottom: height },
+    ];
+    for b in &borders {
+        FillRect(hdc, b, brush);
+    }
+    let _ = DeleteObject(HGDIOBJ(brush.0));
+}
+
+/// Redraw just the border for the next pulse step; a full repaint this often would flicker.
+unsafe fn draw_pulse_border(hwnd: HWND) {
+    let Some(color) = with_toast(pulse_color) else { return };
+    let mut client = RECT::default();
+    let _ = GetClientRect(hwnd, &mut client);
+    let hdc = GetDC(Some(hwnd));
+    fill_border(hdc, client.right, client.bottom, color);
+    ReleaseDC(Some(hwnd), hdc);
+}
+
+/// Whether input-required toasts should pulse: on in the config and
+/// animations not turned off in Windows' accessibility settings.
+fn pulse_enabled() -> bool {
+    let mut animate = BOOL(1);
+    unsafe {
+        let _ = SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut animate as *mut BOOL as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
+    }
+    crate::config::get().toast.pulse && animate.as_bool()
+}
+
 unsafe fn paint(hwnd: HWND) {
-    let (title, message, input_mode, font_family, icon, default_icon_path) = with_toast(|state| {
+    let (
+        title, message, input_mode, (title_fonts, body_fonts),
+        icon, default_icon_path, width, collapsed_height, details, expanded, footer,
+    ) = with_toast(|state| {
         (
             state.title.clone(),
             state.message.clone(),
             state.input_mode,
-            state.font_family.clone(),
+            (state.title_fonts.clone(), state.body_fonts.clone()),
             state.icon,
             state.default_icon_path.clone(),
+            state.width,
+            state.height,
+            state.details.clone(),
+            state.expanded,
+            state.footer.clone(),
         )
     });
+    let (has_focus, focus) = with_toast(|state| (state.has_focus, state.focus));
+
+    let countdown = with_toast(countdown_fraction);
+
+    let mut client = RECT::default();
+    let _ = GetClientRect(hwnd, &mut client);
+    let height = client.bottom;
 
     let mut ps = PAINTSTRUCT::default();
     let hdc = BeginPaint(hwnd, &mut ps);
 
     // Background
     let bg = CreateSolidBrush(COLORREF(COLOR_BG));
-    let rect = RECT { left: 0, top: 0, right: WINDOW_WIDTH, bottom: WINDOW_HEIGHT };
+    let rect = RECT { left: 0, top: 0, right: width, bottom: height };
     FillRect(hdc, &rect, bg);
     let _ = DeleteObject(HGDIOBJ(bg.0));
 
     // Border (color depends on input mode)
     let border_color = if input_mode { COLOR_BORDER_INPUT } else { COLOR_BORDER_NORMAL };
     let border = CreateSolidBrush(COLORREF(border_color));
-    let borders = [
-        RECT { left: 0, top: 0, right: WINDOW_WIDTH, bottom: BORDER_WIDTH },
-        RECT { left: 0, top: WINDOW_HEIGHT - BORDER_WIDTH, right: WINDOW_WIDTH, bottom: WINDOW_HEIGHT },
-        RECT { left: 0, top: 0, right: BORDER_WIDTH, bottom: WINDOW_HEIGHT },
-        RECT { left: WINDOW_WIDTH - BORDER_WIDTH, top: 0, right: WINDOW_WIDTH, bottom: WINDOW_HEIGHT },
-    ];
-    for b in &borders {
-        FillRect(hdc, b, border);
+    fill_border(hdc, width, height, with_toast(pulse_color).unwrap_or(border_color));
+
+    // Countdown bar along the bottom edge, depleting toward the left
+    if let (Some(countdown), true) = (countdown, crate::config::get().toast.countdown_bar) {
+        let full = width - 2 * BORDER_WIDTH;
+        let bar = RECT {
+            left: BORDER_WIDTH,
+            top: height - BORDER_WIDTH - COUNTDOWN_BAR_HEIGHT,
+            right: BORDER_WIDTH + (full as f32 * countdown) as i32,
+            bottom: height - BORDER_WIDTH,
+        };
+        FillRect(hdc, &bar, border);
     }
     let _ = DeleteObject(HGDIOBJ(border.0));
 
     // Icon
     let icon_x = ICON_PADDING;
-    let icon_y = (WINDOW_HEIGHT - ICON_SIZE) / 2;
+    // Centered in the default height; pinned to the top when the window grows
+    let icon_y = ((collapsed_height - ICON_SIZE) / 2).min(ICON_PADDING);
     if !icon.is_invalid() {
         let _ = DrawIconEx(
             hdc, icon_x, icon_y,
@@ -529,55 +1578,94 @@ unsafe fn paint(hwnd: HWND) {
             0, None, DI_NORMAL,
         );
     } else if !default_icon_path.is_empty() {
-        let path_wide = crate::util::encode_wide(&default_icon_path);
-        let result = LoadImageW(
-            None,
-            PCWSTR(path_wide.as_ptr()),
-            IMAGE_ICON,
-            ICON_SIZE, ICON_SIZE,
-            LR_LOADFROMFILE,
-        );
-        if let Ok(handle) = result {
-            let h_icon = HICON(handle.0);
-            if !h_icon.is_invalid() {
-                let _ = DrawIconEx(hdc, icon_x, icon_y, h_icon, ICON_SIZE, ICON_SIZE, 0, None, DI_NORMAL);
-                let _ = DestroyIcon(h_icon);
-            }
+        let h_icon = crate::assets::load_icon_file(&default_icon_path, ICON_SIZE);
+        if !h_icon.is_invalid() {
+            let _ = DrawIconEx(hdc, icon_x, icon_y, h_icon, ICON_SIZE, ICON_SIZE, 0, None, DI_NORMAL);
+            let _ = DestroyIcon(h_icon);
         }
     }
 
-    // Text setup
-    SetBkMode(hdc, TRANSPARENT);
-
     let text_left = icon_x + ICON_SIZE + ICON_PADDING;
+    let title_style = crate::text::Style { fonts: &title_fonts, height: TITLE_FONT_SIZE, bold: true };
+    let message_style = crate::text::Style { fonts: &body_fonts, height: MESSAGE_FONT_SIZE, bold: false };
+
+    // Details rule, before the text goes on top
+    if expanded {
+        let rule = CreateSolidBrush(COLORREF(COLOR_CLOSE));
+        let rule_rect = RECT {
+            left: ICON_PADDING,
+            top: collapsed_height - 1,
+            right: width - ICON_PADDING,
+            bottom: collapsed_height,
+        };
+        FillRect(hdc, &rule_rect, rule);
+        let _ = DeleteObject(HGDIOBJ(rule.0));
+    }
 
-    // Title
-    SetTextColor(hdc, COLORREF(COLOR_TITLE));
-    let title_font = make_font(18, true, &font_family);
-    let old = SelectObject(hdc, HGDIOBJ(title_font.0));
-    let mut title_rect = RECT { left: text_left, top: 15, right: WINDOW_WIDTH - 10, bottom: 40 };
-    let mut title_buf = crate::util::encode_wide(&title);
-    let title_len = title_buf.len() - 1; // exclude null terminator
-    DrawTextW(hdc, &mut title_buf[..title_len], &mut title_rect, DRAW_TEXT_FORMAT(0));
-    SelectObject(hdc, old);
-    let _ = DeleteObject(HGDIOBJ(title_font.0));
-
-    // Message
-    SetTextColor(hdc, COLORREF(COLOR_MESSAGE));
-    let msg_font = make_font(14, false, &font_family);
-    let old = SelectObject(hdc, HGDIOBJ(msg_font.0));
-    let mut msg_rect = RECT { left: text_left, top: 42, right: WINDOW_WIDTH - 10, bottom: WINDOW_HEIGHT - 10 };
-    let mut msg_buf = crate::util::encode_wide(&message);
-    let msg_len = msg_buf.len() - 1; // exclude null terminator
-    DrawTextW(hdc, &mut msg_buf[..msg_len], &mut msg_rect, DRAW_TEXT_FORMAT(0));
+    // Text, with DirectWrite so each run gets a font that has its glyphs
+    if let Some(painter) = crate::text::Painter::begin(hdc, &client) {
+        // Title
+        let title_rect = RECT { left: text_left, top: TITLE_TOP, right: width - TEXT_MARGIN, bottom: TITLE_BOTTOM };
+        painter.draw(&title, &title_style, crate::text::Flow::Line, title_rect, COLOR_TITLE);
+
+        // Message
+        let text_width = message_text_width(width);
+        let (_, line_height) = crate::text::measure(&message, &message_style, text_width);
+        let max_lines = crate::config::get().toast.max_lines.max(1);
+        let footer_space = if footer.is_empty() { 0 } else { FOOTER_HEIGHT };
+        let msg_bottom = (collapsed_height - TEXT_MARGIN - footer_space).min(MESSAGE_TOP + max_lines * line_height);
+        let msg_rect = RECT { left: text_left, top: MESSAGE_TOP, right: text_left + text_width, bottom: msg_bottom };
+        painter.draw(&message, &message_style, crate::text::Flow::Wrap, msg_rect, COLOR_MESSAGE);
+
+        // Footer, dimmed, along the bottom of the collapsed area
+        if !footer.is_empty() {
+            let footer_style = crate::text::Style { fonts: &body_fonts, height: FOOTER_FONT_SIZE, bold: false };
+            let footer_rect = RECT {
+                left: text_left,
+                top: collapsed_height - TEXT_MARGIN / 2 - FOOTER_HEIGHT,
+                right: text_left + text_width,
+                bottom: collapsed_height - TEXT_MARGIN / 2,
+            };
+            painter.draw(&footer, &footer_style, crate::text::Flow::BottomLine, footer_rect, COLOR_CLOSE);
+        }
+
+        // Details section (expanded only), below the rule
+        if expanded {
+            let details_style = crate::text::Style { fonts: &body_fonts, height: DETAILS_FONT_SIZE, bold: false };
+            let details_rect = RECT {
+                left: ICON_PADDING,
+                top: collapsed_height + TEXT_MARGIN / 2,
+                right: width - TEXT_MARGIN,
+                bottom: height - TEXT_MARGIN,
+            };
+            painter.draw(&details, &details_style, crate::text::Flow::Wrap, details_rect, COLOR_MESSAGE);
+        }
+    }
+
+    // Buttons, drawn with GDI over the text
+    SetBkMode(hdc, TRANSPARENT);
+
+    // Chevron (Segoe MDL2 Assets ChevronDown / ChevronUp)
+    SetTextColor(hdc, COLORREF(COLOR_CLOSE));
+    let chevron_font = make_font(CHEVRON_FONT_SIZE, false, "Segoe MDL2 Assets");
+    let old = SelectObject(hdc, HGDIOBJ(chevron_font.0));
+    let mut chevron = chevron_rect(width, collapsed_height);
+    let mut chevron_buf = crate::util::encode_wide(if expanded { "\u{E70E}" } else { "\u{E70D}" });
+    let chevron_len = chevron_buf.len() - 1;
+    DrawTextW(
+        hdc,
+        &mut chevron_buf[..chevron_len],
+        &mut chevron,
+        DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+    );
     SelectObject(hdc, old);
-    let _ = DeleteObject(HGDIOBJ(msg_font.0));
+    let _ = DeleteObject(HGDIOBJ(chevron_font.0));
 
     // Close button (always Segoe UI)
     SetTextColor(hdc, COLORREF(COLOR_CLOSE));
     let close_font = make_font(16, true, "Segoe UI");
     let old = SelectObject(hdc, HGDIOBJ(close_font.0));
-    let btn_left = WINDOW_WIDTH - CLOSE_BUTTON_MARGIN - CLOSE_BUTTON_SIZE;
+    let btn_left = width - CLOSE_BUTTON_MARGIN - CLOSE_BUTTON_SIZE;
     let mut close_rect = RECT {
         left: btn_left,
         top: CLOSE_BUTTON_MARGIN,
@@ -595,34 +1683,122 @@ unsafe fn paint(hwnd: HWND) {
     SelectObject(hdc, old);
     let _ = DeleteObject(HGDIOBJ(close_font.0));
 
+    // Keyboard focus ring
+    if has_focus {
+        let mut ring = match focus {
+            FocusTarget::Body => RECT { left: 4, top: 4, right: width - 4, bottom: collapsed_height - 4 },
+            FocusTarget::Chevron => chevron_rect(width, collapsed_height),
+            FocusTarget::Close => close_rect,
+        };
+        if focus != FocusTarget::Body {
+            let _ = InflateRect(&mut ring, 1, 1);
+        }
+        let _ = DrawFocusRect(hdc, &ring);
+    }
+
     let _ = EndPaint(hwnd, &ps);
 }
 
 // --- Public API ---
 
+/// How a toast went away, for the stats.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Clicked through to its window.
+    Clicked,
+    /// Faded out after its display time.
+    TimedOut,
+    /// Closed, swiped away, or dismissed with the others.
+    Closed,
+}
+
+/// When a toast dismisses itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeoutPolicy {
+    /// Fade out after this many milliseconds on screen.
+    Timed(u32),
+    /// Stay until clicked or closed.
+    Persistent,
+}
+
 pub struct ToastParams {
     pub title: String,
     pub message: String,
+    /// Text shown in the expandable details section.
+    pub details: String,
+    /// Small line under the message; empty for none.
+    pub footer: String,
+    /// Text put on the clipboard by a middle-click.
+    pub clipboard_text: String,
+    /// Sound played with the toast, for `repeat_sound_secs`.
+    pub sound_file: Option<String>,
     pub input_mode: bool,
-    pub font_family: String,
+    pub timeout: TimeoutPolicy,
+    /// Font families to try in order, for the title and for the message and details.
+    pub title_fonts: Vec<String>,
+    pub body_fonts: Vec<String>,
     pub icon: HICON,
     pub default_icon_path: String,
-    pub target_hwnd: HWND,
-    pub wt_hwnd: HWND,
-    pub wt_runtime_id: String,
+    pub target: crate::activate::Target,
 }
 
-/// Show the toast notification window. Blocks until the window is closed.
-pub fn show_toast(params: ToastParams) {
+/// Post `msg` to every toast window, including snoozed ones. Returns how many there are.
+fn post_to_all(msg: u32) -> usize {
+    let class_wide = crate::util::encode_wide(TOAST_CLASS_NAME);
+    let mut count = 0;
+    let mut after: Option<HWND> = None;
+    unsafe {
+        while let Ok(hwnd) = FindWindowExW(None, after, PCWSTR(class_wide.as_ptr()), None) {
+            if hwnd.is_invalid() {
+                break;
+            }
+            let _ = PostMessageW(Some(hwnd), msg, WPARAM(0), LPARAM(0));
+            count += 1;
+            after = Some(hwnd);
+        }
+    }
+    count
+}
+
+/// Close every toast, including snoozed ones. Returns how many were asked to close.
+pub fn dismiss_all() -> usize {
+    post_to_all(WM_CLOSE)
+}
+
+/// Have the open toasts load the config again: placement and what's read
+/// while drawing or on clicks follow the new settings; size and fonts stay.
+pub fn config_changed() {
+    let count = post_to_all(WM_TOAST_CONFIG_CHANGED);
+    crate::debug_log!("Config change sent to {} toasts", count);
+}
+
+/// Show the toast notification window. Blocks until the window is closed,
+/// and says how it was; None if it couldn't be shown.
+pub fn show_toast(params: ToastParams) -> Option<Outcome> {
     // Calculate fade step (SPEC 10.3)
     let fade_ticks = (FADE_MS / 16).max(1);
     let fade_step = ((INITIAL_ALPHA as u32 / fade_ticks) + 1).min(255) as u8;
 
-    // Detect taskbar position
-    let taskbar_edge = detect_taskbar_edge();
-
-    // Get work area from cursor's monitor
-    let (work_area, _monitor) = get_cursor_monitor_work_area();
+    // Corner and monitor to stack from: where a toast was last dragged to, or
+    // the configured position (next to the taskbar on the cursor's monitor by default)
+    let (anchor, monitor) = resolve_anchor();
+    let work_area = placement_area(monitor);
+    crate::debug_log!("Placement area: {:?}, anchor: {:?}", work_area, anchor);
+
+    // Window size from config, growing to fit the message in auto-height mode
+    let cfg = &crate::config::get().toast;
+    let width = cfg.width;
+    let height = if cfg.auto_height {
+        let text_width = message_text_width(width);
+        let style = crate::text::Style { fonts: &params.body_fonts, height: MESSAGE_FONT_SIZE, bold: false };
+        let (text_height, line_height) = crate::text::measure(&params.message, &style, text_width);
+        let text_height = text_height.min(cfg.max_lines.max(1) * line_height);
+        (MESSAGE_TOP + text_height + TEXT_MARGIN).clamp(cfg.height, cfg.max_height.max(cfg.height))
+    } else {
+        cfg.height
+    };
+    let height = if params.footer.is_empty() { height } else { height + FOOTER_HEIGHT };
 
     TOAST.with(|cell| {
         *cell.borrow_mut() = Some(ToastState {
@@ -630,20 +1806,42 @@ pub fn show_toast(params: ToastParams) {
             title: params.title,
             message: params.message,
             input_mode: params.input_mode,
-            font_family: params.font_family,
+            title_fonts: params.title_fonts,
+            body_fonts: params.body_fonts,
             icon: params.icon,
             default_icon_path: params.default_icon_path,
-            target_hwnd: params.target_hwnd,
-            wt_hwnd: params.wt_hwnd,
-            wt_runtime_id: params.wt_runtime_id,
+            width,
+            height,
+            monitor,
+            work_area,
+            footer: params.footer,
+            details: params.details,
+            expanded: false,
+            clipboard_text: params.clipboard_text,
+            target: params.target,
             alpha: INITIAL_ALPHA,
             fade_step,
             is_fading: false,
+            timeout: params.timeout,
+            countdown_remaining_ms: 0,
+            countdown_deadline: None,
             mouse_inside: false,
+            drag: None,
+            dismissing: false,
+            double_clicked: false,
+            wheel_delta: 0,
+            has_focus: false,
+            focus: FocusTarget::Body,
+            previous_foreground: HWND::default(),
+            uia_provider: None,
+            sound_file: params.sound_file,
+            pulse_start: None,
+            target_x: 0,
             target_y: 0,
             is_bottom_toast: false,
-            taskbar_edge,
+            corner: anchor.corner,
             clicked: false,
+            timed_out: false,
         });
     });
 
@@ -657,43 +1855,75 @@ pub fn show_toast(params: ToastParams) {
             hInstance: instance.into(),
             lpszClassName: PCWSTR(class_wide.as_ptr()),
             hCursor: LoadCursorW(None, IDC_HAND).unwrap_or_default(),
+            style: CS_DBLCLKS,
             ..Default::default()
         };
 
         // OK if already registered by another toast instance
         let _ = RegisterClassExW(&wc);
 
-        let (x, y) = calculate_position(&work_area, taskbar_edge);
+        let (x, y) = calculate_position(&work_area, anchor, width, height);
+
+        // Keyboard mode needs an activatable window to receive keys
+        let keyboard = crate::config::get().toast.keyboard;
+        let mut ex_style = WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_LAYERED;
+        if !keyboard {
+            ex_style |= WS_EX_NOACTIVATE;
+        }
 
         let hwnd = CreateWindowExW(
-            WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_LAYERED | WS_EX_NOACTIVATE,
+            ex_style,
             PCWSTR(class_wide.as_ptr()),
             w!("Toast"),
             WS_POPUP,
-            x, y, WINDOW_WIDTH, WINDOW_HEIGHT,
+            x, y, width, height,
             None, None, Some(instance.into()), None,
         ).unwrap_or_default();
 
         if hwnd.is_invalid() || hwnd == HWND::default() {
             crate::debug_log!("CreateWindowExW failed");
-            return;
+            return None;
         }
 
-        with_toast_mut(|state| state.hwnd = hwnd);
+        let provider = with_toast(|state| {
+            crate::accessibility::create_provider(hwnd, &state.title, &state.message, state.input_mode)
+        });
+        with_toast_mut(|state| {
+            state.hwnd = hwnd;
+            state.target_x = x;
+            state.target_y = y;
+            state.uia_provider = Some(provider.clone());
+        });
 
         let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), INITIAL_ALPHA, LWA_ALPHA);
 
+        if params.timeout == TimeoutPolicy::Persistent {
+            // Any non-null value marks the toast
+            let _ = SetPropW(hwnd, PERSISTENT_PROP, Some(HANDLE(hwnd.0)));
+        }
+
         // Determine if bottom toast and start appropriate timer
-        if is_bottom_toast_check(hwnd, taskbar_edge) {
-            with_toast_mut(|state| state.is_bottom_toast = true);
-            SetTimer(Some(hwnd), TIMER_START_FADE, DISPLAY_MS, None);
-        } else {
-            with_toast_mut(|state| state.is_bottom_toast = false);
-            SetTimer(Some(hwnd), TIMER_CHECK_BOTTOM, 200, None);
+        start_display_timer(hwnd);
+
+        if params.input_mode && pulse_enabled() {
+            with_toast_mut(|state| state.pulse_start = Some(std::time::Instant::now()));
+            SetTimer(Some(hwnd), TIMER_PULSE, PULSE_TICK_MS, None);
+        }
+
+        let repeat_secs = crate::config::get().events.get(params.input_mode).repeat_sound_secs;
+        // Not without a sound: play() would beep instead
+        if repeat_secs > 0 && with_toast(|s| s.sound_file.is_some()) {
+            SetTimer(Some(hwnd), TIMER_REPEAT_SOUND, repeat_secs.saturating_mul(1000), None);
         }
 
         let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
         let _ = UpdateWindow(hwnd);
+        with_toast(|state| {
+            crate::accessibility::announce(&provider, &state.title, &state.message, state.input_mode)
+        });
+        if keyboard {
+            take_focus(hwnd);
+        }
 
         // Message loop
         let mut msg = MSG::default();
@@ -702,6 +1932,11 @@ pub fn show_toast(params: ToastParams) {
             DispatchMessageW(&msg);
         }
     }
+    Some(with_toast(|state| match (state.clicked, state.timed_out) {
+        (true, _) => Outcome::Clicked,
+        (false, true) => Outcome::TimedOut,
+        (false, false) => Outcome::Closed,
+    }))
 }
 
 fn detect_taskbar_edge() -> u32 {
@@ -714,22 +1949,168 @@ fn detect_taskbar_edge() -> u32 {
     if result != 0 {
         abd.uEdge
     } else {
-        ABE_BOTTOM as u32
+        ABE_BOTTOM
+    }
+}
+
+fn configured_monitor(select: &crate::config::MonitorSelect) -> HMONITOR {
+    use crate::config::{MonitorSelect, NamedMonitor};
+
+    match select {
+        MonitorSelect::Named(NamedMonitor::Cursor) => cursor_monitor(),
+        MonitorSelect::Named(NamedMonitor::Primary) => unsafe {
+            MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY)
+        },
+        MonitorSelect::Index(n) => find_display_monitor(*n).unwrap_or_else(|| {
+            crate::debug_log!("Monitor {} not found, using cursor monitor", n);
+            cursor_monitor()
+        }),
     }
 }
 
-fn get_cursor_monitor_work_area() -> (RECT, HMONITOR) {
+/// Area toasts are laid out in: the monitor's work area, except that an
+/// auto-hidden taskbar (which reserves no space) is measured from the true
+/// screen edge plus `autohide_inset`.
+fn placement_area(monitor: HMONITOR) -> RECT {
+    let mut mi = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { let _ = GetMonitorInfoW(monitor, &mut mi); }
+
+    let mut area = mi.rcWork;
+    if let Some(edge) = autohide_taskbar_edge(monitor) {
+        let inset = crate::config::get().toast.autohide_inset;
+        match edge {
+            ABE_LEFT => area.left = mi.rcMonitor.left + inset,
+            ABE_TOP => area.top = mi.rcMonitor.top + inset,
+            ABE_RIGHT => area.right = mi.rcMonitor.right - inset,
+            _ => area.bottom = mi.rcMonitor.bottom - inset,
+        }
+    }
+    area
+}
+
+/// Edge of the taskbar if it is set to auto-hide and lives on this monitor.
+fn autohide_taskbar_edge(monitor: HMONITOR) -> Option<u32> {
+    let mut abd = APPBARDATA {
+        cbSize: std::mem::size_of::<APPBARDATA>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        let state = SHAppBarMessage(ABM_GETSTATE, &mut abd) as u32;
+        if state & ABS_AUTOHIDE == 0 {
+            return None;
+        }
+        if SHAppBarMessage(ABM_GETTASKBARPOS, &mut abd) == 0 {
+            return None;
+        }
+        if MonitorFromRect(&abd.rc, MONITOR_DEFAULTTONULL) != monitor {
+            return None;
+        }
+    }
+    Some(abd.uEdge)
+}
+
+/// Re-query the monitor, placement area, and corner after a display, work-area,
+/// or taskbar change, and move the toast to match. Each toast keeps its distance
+/// from the anchored edges, so a stack stays intact as it moves.
+fn refresh_placement(hwnd: HWND) {
+    if with_toast(|s| s.detached()) {
+        return;
+    }
+    let (old_area, old_corner, old_monitor) = with_toast(|s| (s.work_area, s.corner, s.monitor));
+    let (anchor, anchor_monitor) = resolve_anchor();
+
+    // Stay on the same monitor unless it was disconnected
+    let mut mi = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    let monitor = if unsafe { GetMonitorInfoW(old_monitor, &mut mi).as_bool() } {
+        old_monitor
+    } else {
+        anchor_monitor
+    };
+    let area = placement_area(monitor);
+    let corner = anchor.corner;
+
+    let mut rect = RECT::default();
+    unsafe { let _ = GetWindowRect(hwnd, &mut rect); }
+    let width = rect.right - rect.left;
+    let height = rect.bottom - rect.top;
+
+    // Distance from the anchored edges, carried over to the new area and corner
+    let off_x = if old_corner.left { rect.left - old_area.left } else { old_area.right - rect.right };
+    let off_y = if old_corner.top { rect.top - old_area.top } else { old_area.bottom - rect.bottom };
+    let x = if corner.left { area.left + off_x } else { area.right - off_x - width };
+    let y = if corner.top { area.top + off_y } else { area.bottom - off_y - height };
+
+    // Never leave the toast stranded off-screen
+    let x = x.clamp(area.left, (area.right - width).max(area.left));
+    let y = y.clamp(area.top, (area.bottom - height).max(area.top));
+
+    with_toast_mut(|state| {
+        state.monitor = monitor;
+        state.work_area = area;
+        state.corner = corner;
+        state.target_x = x;
+        state.target_y = y;
+    });
+
+    if x != rect.left || y != rect.top {
+        crate::debug_log!("Placement changed (area {:?}), moving to ({}, {})", area, x, y);
+        unsafe { SetTimer(Some(hwnd), TIMER_REPOSITION, 16, None); }
+    }
+}
+
+fn cursor_monitor() -> HMONITOR {
     unsafe {
         let mut cursor_pos = POINT::default();
         let _ = GetCursorPos(&mut cursor_pos);
+        MonitorFromPoint(cursor_pos, MONITOR_DEFAULTTOPRIMARY)
+    }
+}
 
-        let monitor = MonitorFromPoint(cursor_pos, MONITOR_DEFAULTTOPRIMARY);
-        let mut mi = MONITORINFO {
-            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
-            ..Default::default()
-        };
-        let _ = GetMonitorInfoW(monitor, &mut mi);
+/// Find the monitor whose device is \\.\DISPLAY{number} (the number shown in Display Settings).
+fn find_display_monitor(number: u32) -> Option<HMONITOR> {
+    find_monitor(|device| {
+        let digits = device.trim_start_matches(|c: char| !c.is_ascii_digit());
+        digits.parse::<u32>().ok() == Some(number)
+    })
+}
+
+/// Find the first monitor whose device name matches.
+fn find_monitor(matches: impl Fn(&str) -> bool) -> Option<HMONITOR> {
+    unsafe extern "system" fn callback(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, lparam: LPARAM) -> BOOL {
+        let monitors = &mut *(lparam.0 as *mut Vec<HMONITOR>);
+        monitors.push(monitor);
+        TRUE
+    }
+
+    let mut monitors: Vec<HMONITOR> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            None,
+            None,
+            Some(callback),
+            LPARAM(&mut monitors as *mut Vec<HMONITOR> as isize),
+        );
+    }
+    monitors
+        .into_iter()
+        .find(|&m| monitor_device_name(m).is_some_and(|device| matches(&device)))
+}
 
-        (mi.rcWork, monitor)
+/// Device name of a monitor, e.g. \\.\DISPLAY2.
+fn monitor_device_name(monitor: HMONITOR) -> Option<String> {
+    let mut mi = MONITORINFOEXW::default();
+    mi.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    unsafe {
+        if !GetMonitorInfoW(monitor, &mut mi as *mut MONITORINFOEXW as *mut MONITORINFO).as_bool() {
+            return None;
+        }
     }
+    let len = mi.szDevice.iter().position(|&c| c == 0).unwrap_or(mi.szDevice.len());
+    Some(String::from_utf16_lossy(&mi.szDevice[..len]))
 }
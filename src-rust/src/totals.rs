@@ -0,0 +1,105 @@
+//! Token and cost totals per project.
+//!
+//! Each completion adds the usage of its turn to the totals table of the
+//! store, under the session's working directory, for --stats. Older
+//! versions kept them in totals.json, which the store imports.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::Usage;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectTotals {
+    /// Completed turns counted.
+    pub turns: u64,
+    pub usage: Usage,
+    /// Sum over the turns whose cost was known.
+    pub cost_usd: f64,
+    /// Unix time in seconds of the last turn.
+    pub last: u64,
+}
+
+/// The format of totals.json.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Totals {
+    projects: BTreeMap<String, ProjectTotals>,
+}
+
+/// The totals by project directory.
+pub fn load() -> BTreeMap<String, ProjectTotals> {
+    let rows = crate::store::with(|db| {
+        db.query(
+            "SELECT project, turns, input_tokens, output_tokens, cache_creation_input_tokens, cache_read_input_tokens, cost_usd, last FROM totals",
+            &[],
+            |row| {
+                let totals = ProjectTotals {
+                    turns: row.int(1) as u64,
+                    usage: Usage {
+                        input_tokens: row.int(2) as u64,
+                        output_tokens: row.int(3) as u64,
+                        cache_creation_input_tokens: row.int(4) as u64,
+                        cache_read_input_tokens: row.int(5) as u64,
+                    },
+                    cost_usd: row.real(6),
+                    last: row.int(7) as u64,
+                };
+                (row.text(0), totals)
+            },
+        )
+    });
+    rows.unwrap_or_default().into_iter().collect()
+}
+
+/// Add a finished turn's usage to its project.
+pub fn record(project: &str, usage: &Usage, cost_usd: Option<f64>) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let turn = ProjectTotals {
+        turns: 1,
+        usage: *usage,
+        cost_usd: cost_usd.unwrap_or(0.0),
+        last: now,
+    };
+    crate::store::with(|db| db.transaction(|db| add(db, project, &turn)));
+}
+
+fn add(db: &crate::store::Db, project: &str, totals: &ProjectTotals) -> Result<(), String> {
+    db.execute(
+        "INSERT OR IGNORE INTO totals VALUES (?1, 0, 0, 0, 0, 0, 0.0, 0)",
+        &[project.into()],
+    )?;
+    db.execute(
+        "UPDATE totals SET turns = turns + ?2, input_tokens = input_tokens + ?3, output_tokens = output_tokens + ?4, \
+         cache_creation_input_tokens = cache_creation_input_tokens + ?5, cache_read_input_tokens = cache_read_input_tokens + ?6, \
+         cost_usd = cost_usd + ?7, last = max(last, ?8) WHERE project = ?1",
+        &[
+            project.into(),
+            totals.turns.into(),
+            totals.usage.input_tokens.into(),
+            totals.usage.output_tokens.into(),
+            totals.usage.cache_creation_input_tokens.into(),
+            totals.usage.cache_read_input_tokens.into(),
+            totals.cost_usd.into(),
+            totals.last.into(),
+        ],
+    )
+}
+
+/// Copy totals.json of older versions into the store.
+pub fn import(db: &crate::store::Db) -> Vec<std::path::PathBuf> {
+    let Some(path) = crate::config::local_dir().map(|d| d.join("totals.json")) else { return Vec::new() };
+    let Ok(text) = std::fs::read_to_string(&path) else { return Vec::new() };
+    let totals: Totals = serde_json::from_str(&text).unwrap_or_default();
+    for (project, project_totals) in &totals.projects {
+        if let Err(e) = add(db, project, project_totals) {
+            crate::warn_log!("Failed to import totals of {}: {}", project, e);
+        }
+    }
+    vec![path]
+}
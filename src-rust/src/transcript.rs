@@ -0,0 +1,153 @@
+//! Session transcript reading.
+//!
+//! Claude Code appends every message of a session to a JSONL file, whose
+//! path the hooks get as `transcript_path`. The file is read a line at a
+//! time, keeping only what a toast can use: the last reply, whether it was
+//! an error, how many prompts there were, and the token usage and cost, for
+//! the whole session and since the last prompt.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Token counts, as the API reports them per message.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// What a transcript says about its session.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Text of the last assistant message that had any.
+    pub last_reply: String,
+    /// The last assistant message was an API error.
+    pub error: bool,
+    /// Prompts typed by the user (tool results don't count).
+    pub turns: u32,
+    /// Summed over all assistant messages.
+    pub usage: Usage,
+    /// Summed over the assistant messages since the last prompt.
+    pub turn_usage: Usage,
+    /// Cost of those messages, if the transcript records it (`costUSD`).
+    pub turn_cost_usd: Option<f64>,
+}
+
+/// One line of the transcript; everything else in it is skipped.
+#[derive(Deserialize)]
+struct Line {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(rename = "isApiErrorMessage", default)]
+    api_error: bool,
+    #[serde(rename = "costUSD", default)]
+    cost_usd: Option<f64>,
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    content: Value,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// The text blocks of a message's content (a string or a list of blocks).
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Read the transcript at `path`. None if it can't be opened.
+pub fn summarize(path: &str) -> Option<Summary> {
+    if path.is_empty() {
+        return None;
+    }
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            crate::debug_log!("Can't open transcript {}: {}", path, e);
+            return None;
+        }
+    };
+
+    let mut summary = Summary::default();
+    // A message streamed in several lines repeats its usage on each; count it once
+    let mut usage_by_id: HashMap<String, Usage> = HashMap::new();
+    let mut turn: HashMap<String, (Usage, Option<f64>)> = HashMap::new();
+    let mut unparsed = 0;
+    for line in std::io::BufReader::new(file).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(line) = serde_json::from_str::<Line>(&line) else {
+            unparsed += 1;
+            continue;
+        };
+        let Some(message) = line.message else { continue };
+        match line.kind.as_str() {
+            "user" if !text_of(&message.content).is_empty() => {
+                summary.turns += 1;
+                turn.clear();
+            }
+            "assistant" => {
+                let text = text_of(&message.content);
+                if !text.trim().is_empty() {
+                    summary.last_reply = text;
+                    summary.error = line.api_error;
+                }
+                if let Some(usage) = message.usage {
+                    let id = if message.id.is_empty() { format!("#{}", usage_by_id.len()) } else { message.id };
+                    usage_by_id.insert(id.clone(), usage);
+                    turn.insert(id, (usage, line.cost_usd));
+                }
+            }
+            _ => {}
+        }
+    }
+    for usage in usage_by_id.values() {
+        summary.usage.add(usage);
+    }
+    for (usage, cost) in turn.values() {
+        summary.turn_usage.add(usage);
+        if let Some(cost) = cost {
+            *summary.turn_cost_usd.get_or_insert(0.0) += cost;
+        }
+    }
+    if unparsed > 0 {
+        crate::debug_log!("Transcript {}: {} lines not understood", path, unparsed);
+    }
+    Some(summary)
+}
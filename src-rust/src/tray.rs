@@ -0,0 +1,537 @@
+//! Resident tray agent (--tray).
+//!
+//! A hidden window that owns the notification-area icon and the global
+//! hotkeys, which need a process that outlives individual toasts. One agent
+//! per user session, guarded by a named mutex. Started by hand, or by the
+//! hooks when `[tray] enabled = true`. It also watches config.toml and
+//! applies changes to itself and the open toasts, and badges its icon with
+//! the number of sessions waiting for input (`waiting.rs`).
+
+use std::cell::RefCell;
+
+use windows::core::*;
+use windows::Win32::Foundation::*;
+use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::CreateMutexW;
+use windows::Win32::UI::Input::KeyboardAndMouse::*;
+use windows::Win32::UI::Shell::*;
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+const TRAY_CLASS_NAME: &str = "ClaudeCodeNotifyTray";
+const TRAY_MUTEX_NAME: PCWSTR = w!("Local\\ClaudeCodeNotify.Tray");
+
+const WM_TRAY_ICON: u32 = WM_APP + 1;
+/// From the watcher thread: config.toml was written.
+const WM_CONFIG_CHANGED: u32 = WM_APP + 2;
+/// From a hook process: a session started or stopped waiting for input.
+const WM_WAITING_CHANGED: u32 = WM_APP + 3;
+const TRAY_ICON_ID: u32 = 1;
+const HOTKEY_JUMP: i32 = 1;
+
+const TIMER_RELOAD: usize = 1;
+/// Editors write a file in several steps; reload once they're done.
+const RELOAD_DELAY_MS: u32 = 300;
+/// Sweeps stale sessions while no hooks run (see `store::maintain`).
+const TIMER_MAINTAIN: usize = 2;
+
+const MENU_JUMP: usize = 1;
+const MENU_DISMISS_ALL: usize = 2;
+const MENU_HISTORY: usize = 3;
+const MENU_EXIT: usize = 4;
+const MENU_MUTE: usize = 5;
+const MENU_LOGS: usize = 6;
+
+/// Badge fill, as R, G, B.
+const BADGE_COLOR: [u8; 3] = [0xD1, 0x34, 0x38];
+
+struct TrayState {
+    icon: HICON,
+    /// `icon` with the waiting count on it, while there is one.
+    badged: Option<HICON>,
+    /// Sessions waiting for input, as last shown.
+    waiting: usize,
+    /// Broadcast when Explorer restarts; the icon has to be added again.
+    taskbar_created: u32,
+    /// Hotkey text for the menu, empty if none is registered.
+    hotkey_label: String,
+}
+
+thread_local! {
+    static TRAY: RefCell<Option<TrayState>> = const { RefCell::new(None) };
+}
+
+fn with_tray<R>(f: impl FnOnce(&TrayState) -> R) -> R {
+    TRAY.with(|cell| f(cell.borrow().as_ref().unwrap()))
+}
+
+/// Whether an agent is already running in this session.
+pub fn is_running() -> bool {
+    let class = crate::util::encode_wide(TRAY_CLASS_NAME);
+    unsafe { FindWindowW(PCWSTR(class.as_ptr()), None).is_ok_and(|h| !h.is_invalid()) }
+}
+
+/// Start the agent in the background if the config asks for it and it isn't running.
+pub fn ensure_running() {
+    if !crate::config::get().tray.enabled || is_running() {
+        return;
+    }
+    let exe = std::env::current_exe().unwrap_or_default();
+    let cmd = format!("{} --tray", crate::spawn::quote_arg(&exe.to_string_lossy()));
+    crate::debug_log!("Starting tray agent: {}", cmd);
+    crate::spawn::spawn_detached(&cmd);
+}
+
+/// Parse a hotkey such as "Win+Shift+C" or "Ctrl+Alt+F9" into modifiers and a virtual key.
+/// Keys are letters, digits, F1-F24, and ` (the quake-mode default).
+pub fn parse_hotkey(text: &str) -> Option<(HOT_KEY_MODIFIERS, u32)> {
+    let mut modifiers = MOD_NOREPEAT;
+    let mut vk = None;
+    for part in text.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "win" => modifiers |= MOD_WIN,
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            key => {
+                let upper = key.to_ascii_uppercase();
+                vk = match upper.as_bytes() {
+                    [c] if c.is_ascii_alphanumeric() => Some(*c as u32),
+                    [b'`'] => Some(VK_OEM_3.0 as u32),
+                    [b'F', ..] => upper[1..]
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|n| (1..=24).contains(n))
+                        .map(|n| VK_F1.0 as u32 + n - 1),
+                    _ => None,
+                };
+                vk?;
+            }
+        }
+    }
+    vk.map(|vk| (modifiers, vk))
+}
+
+/// Activate the window of the most recently notified session that still has one.
+pub fn jump_to_last_session() {
+    let history = crate::history::load();
+    let mut tried: Vec<&str> = Vec::new();
+    for entry in history.iter().rev() {
+        if tried.contains(&entry.session.as_str()) {
+            continue;
+        }
+        tried.push(&entry.session);
+        if crate::activate::activate_session(&entry.session) {
+            return;
+        }
+    }
+    crate::debug_log!("No notified session with a live window");
+}
+
+fn load_tray_icon() -> HICON {
+    let size = unsafe { GetSystemMetrics(SM_CXSMICON) };
+    if let Some(path) = crate::assets::discover_assets().default_icon_path {
+        let icon = crate::assets::load_icon_file(&path, size);
+        if !icon.is_invalid() {
+            return icon;
+        }
+    }
+    unsafe { LoadIconW(None, IDI_APPLICATION).unwrap_or_default() }
+}
+
+fn notify_icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    let (icon, waiting) = with_tray(|t| (t.badged.unwrap_or(t.icon), t.waiting));
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: TRAY_ICON_ID,
+        uFlags: NIF_MESSAGE | NIF_ICON | NIF_TIP,
+        uCallbackMessage: WM_TRAY_ICON,
+        hIcon: icon,
+        ..Default::default()
+    };
+    let tip = match waiting {
+        0 => "Claude Code Notify".to_string(),
+        1 => "Claude Code Notify\n1 session waiting for input".to_string(),
+        n => format!("Claude Code Notify\n{} sessions waiting for input", n),
+    };
+    let tip: Vec<u16> = tip.encode_utf16().take(nid.szTip.len() - 1).collect();
+    nid.szTip[..tip.len()].copy_from_slice(&tip);
+    nid
+}
+
+fn add_icon(hwnd: HWND) {
+    let nid = notify_icon_data(hwnd);
+    if !unsafe { Shell_NotifyIconW(NIM_ADD, &nid) }.as_bool() {
+        crate::debug_log!("Shell_NotifyIconW(NIM_ADD) failed");
+    }
+}
+
+/// Tell the agent, if it runs, to count the waiting sessions again.
+pub fn waiting_changed() {
+    let class = crate::util::encode_wide(TRAY_CLASS_NAME);
+    unsafe {
+        if let Ok(hwnd) = FindWindowW(PCWSTR(class.as_ptr()), None) {
+            let _ = PostMessageW(Some(hwnd), WM_WAITING_CHANGED, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Count the waiting sessions and badge the icon with the number, if it changed.
+fn update_badge(hwnd: HWND) {
+    let waiting = if crate::config::get().tray.badge { crate::waiting::count() } else { 0 };
+    if waiting == with_tray(|t| t.waiting) {
+        return;
+    }
+    crate::debug_log!("Sessions waiting for input: {}", waiting);
+    let badged = match waiting {
+        0 => None,
+        n => unsafe { badge_icon(with_tray(|t| t.icon), n) },
+    };
+    let old = TRAY.with(|cell| {
+        let mut tray = cell.borrow_mut();
+        let tray = tray.as_mut().unwrap();
+        tray.waiting = waiting;
+        std::mem::replace(&mut tray.badged, badged)
+    });
+    let nid = notify_icon_data(hwnd);
+    unsafe {
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+        if let Some(old) = old {
+            let _ = DestroyIcon(old);
+        }
+    }
+}
+
+/// `base` with `count` ("9+" past 9) in a red badge at its bottom right.
+unsafe fn badge_icon(base: HICON, count: usize) -> Option<HICON> {
+    let size = GetSystemMetrics(SM_CXSMICON).max(16);
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: size,
+            biHeight: -size, // top-down
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let dc = CreateCompatibleDC(None);
+    let (mut icon_bits, mut text_bits) = (std::ptr::null_mut(), std::ptr::null_mut());
+    let color = CreateDIBSection(Some(dc), &info, DIB_RGB_COLORS, &mut icon_bits, None, 0);
+    // The label is drawn white on black on a second bitmap, then used as coverage
+    let text = CreateDIBSection(Some(dc), &info, DIB_RGB_COLORS, &mut text_bits, None, 0);
+    let (color, text) = match (color, text) {
+        (Ok(color), Ok(text)) => (color, text),
+        (color, text) => {
+            crate::debug_log!("CreateDIBSection failed for the tray badge");
+            for bitmap in [color, text].into_iter().flatten() {
+                let _ = DeleteObject(HGDIOBJ(bitmap.0));
+            }
+            let _ = DeleteDC(dc);
+            return None;
+        }
+    };
+    let pixels = (size * size) as usize;
+
+    // The icon, as premultiplied BGRA
+    let old = SelectObject(dc, HGDIOBJ(color.0));
+    let _ = DrawIconEx(dc, 0, 0, base, size, size, 0, None, DI_NORMAL);
+    let _ = GdiFlush();
+    let icon_px = std::slice::from_raw_parts_mut(icon_bits as *mut [u8; 4], pixels);
+    // An icon without alpha draws with alpha 0 everywhere
+    if icon_px.iter().all(|p| p[3] == 0) {
+        for p in icon_px.iter_mut().filter(|p| p[..3] != [0, 0, 0]) {
+            p[3] = 255;
+        }
+    }
+
+    let label = if count > 9 { "9+".to_string() } else { count.to_string() };
+    let badge_h = size * 5 / 8;
+    let badge_w = if label.len() > 1 { (badge_h * 3 / 2).min(size) } else { badge_h };
+    let mut rect = RECT { left: size - badge_w, top: size - badge_h, right: size, bottom: size };
+
+    SelectObject(dc, HGDIOBJ(text.0));
+    let face = crate::util::encode_wide("Segoe UI");
+    let font = CreateFontW(
+        -badge_h, 0, 0, 0,
+        FW_BOLD.0 as i32,
+        0, 0, 0,
+        FONT_CHARSET(0),
+        FONT_OUTPUT_PRECISION(0),
+        FONT_CLIP_PRECISION(0),
+        ANTIALIASED_QUALITY, // gray coverage, not ClearType colors
+        0,
+        PCWSTR(face.as_ptr()),
+    );
+    let old_font = SelectObject(dc, HGDIOBJ(font.0));
+    SetBkMode(dc, TRANSPARENT);
+    SetTextColor(dc, COLORREF(0x00FFFFFF));
+    let mut label_wide: Vec<u16> = label.encode_utf16().collect();
+    DrawTextW(dc, &mut label_wide, &mut rect, DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_NOPREFIX);
+    SelectObject(dc, old_font);
+    let _ = DeleteObject(HGDIOBJ(font.0));
+    let _ = GdiFlush();
+    let text_px = std::slice::from_raw_parts(text_bits as *const [u8; 4], pixels);
+
+    // A pill with round ends, antialiased by distance to its center line
+    let radius = badge_h as f32 / 2.0;
+    let (start, end) = ((size - badge_w) as f32 + radius, size as f32 - radius);
+    let center_y = (size - badge_h) as f32 + radius;
+    for y in 0..size {
+        for x in 0..size {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let (dx, dy) = (px - px.clamp(start, end), py - center_y);
+            let cover = (radius + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+            if cover == 0.0 {
+                continue;
+            }
+            let i = (y * size + x) as usize;
+            let ink = *text_px[i][..3].iter().max().unwrap_or(&0) as f32 / 255.0;
+            let p = &mut icon_px[i];
+            for (c, fill) in [BADGE_COLOR[2], BADGE_COLOR[1], BADGE_COLOR[0]].into_iter().enumerate() {
+                let fill = fill as f32 + (255.0 - fill as f32) * ink;
+                p[c] = (fill * cover + p[c] as f32 * (1.0 - cover)).round() as u8;
+            }
+            p[3] = (255.0 * cover + p[3] as f32 * (1.0 - cover)).round() as u8;
+        }
+    }
+    // Icons take straight alpha
+    for p in icon_px.iter_mut().filter(|p| p[3] > 0 && p[3] < 255) {
+        for c in 0..3 {
+            p[c] = (p[c] as u32 * 255 / p[3] as u32).min(255) as u8;
+        }
+    }
+
+    SelectObject(dc, old);
+    // The mask is ignored for 32-bit color bitmaps but still required
+    let mask = CreateBitmap(size, size, 1, 1, None);
+    let icon_info = ICONINFO {
+        fIcon: true.into(),
+        hbmMask: mask,
+        hbmColor: color,
+        ..Default::default()
+    };
+    let icon = CreateIconIndirect(&icon_info);
+    let _ = DeleteObject(HGDIOBJ(color.0));
+    let _ = DeleteObject(HGDIOBJ(text.0));
+    let _ = DeleteObject(HGDIOBJ(mask.0));
+    let _ = DeleteDC(dc);
+    if let Err(e) = &icon {
+        crate::debug_log!("CreateIconIndirect failed for the tray badge: {:?}", e);
+    }
+    icon.ok()
+}
+
+fn remove_icon(hwnd: HWND) {
+    let nid = notify_icon_data(hwnd);
+    unsafe { let _ = Shell_NotifyIconW(NIM_DELETE, &nid); }
+}
+
+fn show_menu(hwnd: HWND) {
+    let hotkey = with_tray(|t| t.hotkey_label.clone());
+    let jump_label = if hotkey.is_empty() {
+        "Jump to last session".to_string()
+    } else {
+        format!("Jump to last session\t{}", hotkey)
+    };
+    let jump_wide = crate::util::encode_wide(&jump_label);
+
+    unsafe {
+        let Ok(menu) = CreatePopupMenu() else { return };
+        let _ = AppendMenuW(menu, MF_STRING, MENU_JUMP, PCWSTR(jump_wide.as_ptr()));
+        let _ = AppendMenuW(menu, MF_STRING, MENU_DISMISS_ALL, w!("Dismiss all notifications"));
+        let _ = AppendMenuW(menu, MF_STRING, MENU_HISTORY, w!("Notification history"));
+        let _ = AppendMenuW(menu, MF_STRING, MENU_LOGS, w!("Log viewer"));
+        let mute_flags = if crate::mute::is_muted() { MF_STRING | MF_CHECKED } else { MF_STRING };
+        let _ = AppendMenuW(menu, mute_flags, MENU_MUTE, w!("Mute notifications"));
+        let _ = AppendMenuW(menu, MF_SEPARATOR, 0, None);
+        let _ = AppendMenuW(menu, MF_STRING, MENU_EXIT, w!("Exit"));
+
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        // Required so the menu closes when clicking elsewhere
+        let _ = SetForegroundWindow(hwnd);
+        let cmd = TrackPopupMenu(
+            menu,
+            TPM_RIGHTBUTTON | TPM_RETURNCMD | TPM_NONOTIFY,
+            cursor.x, cursor.y,
+            None, hwnd, None,
+        );
+        let _ = DestroyMenu(menu);
+        run_menu_command(hwnd, cmd.0 as usize);
+    }
+}
+
+fn run_menu_command(hwnd: HWND, cmd: usize) {
+    match cmd {
+        MENU_JUMP => jump_to_last_session(),
+        MENU_DISMISS_ALL => {
+            crate::toast::dismiss_all();
+        }
+        MENU_HISTORY => crate::history::open(),
+        MENU_LOGS => {
+            let exe = std::env::current_exe().unwrap_or_default();
+            crate::spawn::spawn_detached(&format!("{} --logs", crate::spawn::quote_arg(&exe.to_string_lossy())));
+        }
+        MENU_MUTE => {
+            if crate::mute::is_muted() {
+                crate::mute::unmute();
+            } else {
+                crate::mute::mute(None);
+            }
+        }
+        MENU_EXIT => unsafe { let _ = DestroyWindow(hwnd); },
+        _ => {}
+    }
+}
+
+/// Register the configured jump hotkey, replacing the one registered before.
+fn register_hotkey(hwnd: HWND) {
+    let hotkey = &crate::config::get().tray.hotkey;
+    let mut label = String::new();
+    unsafe {
+        let _ = UnregisterHotKey(Some(hwnd), HOTKEY_JUMP);
+        if !hotkey.is_empty() {
+            match parse_hotkey(hotkey) {
+                Some((modifiers, vk)) => match RegisterHotKey(Some(hwnd), HOTKEY_JUMP, modifiers, vk) {
+                    Ok(()) => label = hotkey.clone(),
+                    Err(e) => crate::debug_log!("RegisterHotKey({}) failed (in use?): {:?}", hotkey, e),
+                },
+                None => crate::debug_log!("Invalid hotkey: {}", hotkey),
+            }
+        }
+    }
+    TRAY.with(|cell| {
+        if let Some(t) = cell.borrow_mut().as_mut() {
+            t.hotkey_label = label;
+        }
+    });
+}
+
+unsafe extern "system" fn wnd_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_TRAY_ICON => {
+            match lparam.0 as u32 {
+                WM_LBUTTONUP => jump_to_last_session(),
+                WM_RBUTTONUP => show_menu(hwnd),
+                _ => {}
+            }
+            LRESULT(0)
+        }
+
+        WM_HOTKEY if wparam.0 as i32 == HOTKEY_JUMP => {
+            crate::debug_log!("Jump hotkey pressed");
+            jump_to_last_session();
+            LRESULT(0)
+        }
+
+        WM_CONFIG_CHANGED => {
+            SetTimer(Some(hwnd), TIMER_RELOAD, RELOAD_DELAY_MS, None);
+            LRESULT(0)
+        }
+
+        WM_TIMER if wparam.0 == TIMER_RELOAD => {
+            let _ = KillTimer(Some(hwnd), TIMER_RELOAD);
+            crate::debug_log!("Config changed, reloading");
+            crate::config::reload();
+            register_hotkey(hwnd);
+            crate::toast::config_changed();
+            update_badge(hwnd);
+            LRESULT(0)
+        }
+
+        WM_TIMER if wparam.0 == TIMER_MAINTAIN => {
+            crate::store::maintain();
+            LRESULT(0)
+        }
+
+        WM_WAITING_CHANGED => {
+            update_badge(hwnd);
+            LRESULT(0)
+        }
+
+        WM_DESTROY => {
+            remove_icon(hwnd);
+            let _ = UnregisterHotKey(Some(hwnd), HOTKEY_JUMP);
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+
+        _ if msg != 0 && msg == with_tray(|t| t.taskbar_created) => {
+            add_icon(hwnd);
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// Run the agent until "Exit" is chosen. Returns the process exit code.
+pub fn run() -> i32 {
+    // One agent per session; the handle is held until the process exits
+    let _mutex = unsafe { CreateMutexW(None, false, TRAY_MUTEX_NAME) };
+    if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+        crate::debug_log!("Tray agent already running");
+        return 0;
+    }
+
+    TRAY.with(|cell| {
+        *cell.borrow_mut() = Some(TrayState {
+            icon: load_tray_icon(),
+            badged: None,
+            waiting: 0,
+            taskbar_created: unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) },
+            hotkey_label: String::new(),
+        });
+    });
+
+    unsafe {
+        let instance = GetModuleHandleW(None).unwrap_or_default();
+        let class_wide = crate::util::encode_wide(TRAY_CLASS_NAME);
+        let wc = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            lpfnWndProc: Some(wnd_proc),
+            hInstance: instance.into(),
+            lpszClassName: PCWSTR(class_wide.as_ptr()),
+            ..Default::default()
+        };
+        let _ = RegisterClassExW(&wc);
+
+        // Never shown; a top-level window so the tray menu can take the foreground
+        let hwnd = CreateWindowExW(
+            WS_EX_TOOLWINDOW,
+            PCWSTR(class_wide.as_ptr()),
+            w!("Claude Code Notify"),
+            WS_POPUP,
+            0, 0, 0, 0,
+            None, None, Some(instance.into()), None,
+        ).unwrap_or_default();
+        if hwnd.is_invalid() {
+            crate::debug_log!("Tray window creation failed");
+            return 1;
+        }
+
+        add_icon(hwnd);
+        crate::store::maintain();
+        update_badge(hwnd);
+        SetTimer(Some(hwnd), TIMER_MAINTAIN, crate::store::MAINTAIN_INTERVAL_SECS as u32 * 1000, None);
+        register_hotkey(hwnd);
+        crate::watch::config_changes(hwnd, WM_CONFIG_CHANGED);
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+
+        let (icon, badged) = with_tray(|t| (t.icon, t.badged));
+        let _ = DestroyIcon(icon);
+        if let Some(badged) = badged {
+            let _ = DestroyIcon(badged);
+        }
+    }
+    0
+}
@@ -1,7 +1,12 @@
 //! UI Automation COM interface for Windows Terminal tab detection.
 //!
 //! Uses IUIAutomation to enumerate tabs, find the selected one,
-//! and capture/match its RuntimeId.
+//! and capture/match its RuntimeId. Also finds the terminal tabs of
+//! VS Code-based editors, which are matched by title only.
+//!
+//! Every UIA call on an element is a round-trip into the WT process, so the
+//! tab search asks for the properties and pattern it needs up front (a cache
+//! request) and reads them locally afterwards.
 
 use windows::core::*;
 use windows::Win32::Foundation::*;
@@ -10,88 +15,172 @@ use windows::Win32::System::Ole::*;
 use windows::Win32::System::Variant::*;
 use windows::Win32::UI::Accessibility::*;
 
-/// Get the RuntimeId string of the currently selected WT tab.
-/// Returns empty string on failure.
-pub fn get_selected_tab_runtime_id(hwnd: HWND) -> String {
-    unsafe { get_selected_tab_runtime_id_inner(hwnd).unwrap_or_default() }
+/// Accessible name of the list VS Code shows terminal tabs in. Only present
+/// with more than one terminal (the default `tabs.hideCondition`).
+const VSCODE_TERMINAL_TABS: &str = "Terminal tabs";
+
+unsafe fn automation() -> Result<IUIAutomation> {
+    CoCreateInstance(&CUIAutomation as *const GUID, None, CLSCTX_INPROC_SERVER)
 }
 
-unsafe fn get_selected_tab_runtime_id_inner(hwnd: HWND) -> Result<String> {
-    let automation: IUIAutomation = CoCreateInstance(
-        &CUIAutomation as *const GUID,
-        None,
-        CLSCTX_INPROC_SERVER,
-    )?;
+/// Whether UI Automation is available (--doctor).
+pub fn check() -> Result<()> {
+    unsafe { automation().map(|_| ()) }
+}
+
+unsafe fn control_type_condition(automation: &IUIAutomation, control_type: UIA_CONTROLTYPE_ID) -> Result<IUIAutomationCondition> {
+    automation.CreatePropertyCondition(UIA_ControlTypePropertyId, &VARIANT::from(control_type.0))
+}
+
+/// Everything read from a tab, fetched along with the tabs in the same call.
+unsafe fn tab_cache(automation: &IUIAutomation) -> Result<IUIAutomationCacheRequest> {
+    let cache = automation.CreateCacheRequest()?;
+    cache.AddProperty(UIA_RuntimeIdPropertyId)?;
+    cache.AddProperty(UIA_NamePropertyId)?;
+    cache.AddProperty(UIA_SelectionItemIsSelectedPropertyId)?;
+    cache.AddPattern(UIA_SelectionItemPatternId)?;
+    cache.AddPattern(UIA_InvokePatternId)?;
+    Ok(cache)
+}
 
+/// All tab items in a WT window.
+unsafe fn find_tabs(hwnd: HWND) -> Result<IUIAutomationElementArray> {
+    let automation = automation()?;
     let element = automation.ElementFromHandle(hwnd)?;
+    let condition = control_type_condition(&automation, UIA_TabItemControlTypeId)?;
+    element.FindAllBuildCache(TreeScope_Descendants, &condition, &tab_cache(&automation)?)
+}
 
-    // Create condition: ControlType == TabItem
-    let prop_id = UIA_ControlTypePropertyId;
-    let val = VARIANT::from(UIA_TabItemControlTypeId.0);
-    let condition = automation.CreatePropertyCondition(prop_id, &val)?;
+/// Items of the terminal tab list in a VS Code window.
+unsafe fn find_vscode_terminal_tabs(hwnd: HWND) -> Result<IUIAutomationElementArray> {
+    let automation = automation()?;
+    let element = automation.ElementFromHandle(hwnd)?;
+    let condition = automation.CreateAndCondition(
+        &control_type_condition(&automation, UIA_ListControlTypeId)?,
+        &automation.CreatePropertyCondition(UIA_NamePropertyId, &VARIANT::from(VSCODE_TERMINAL_TABS))?,
+    )?;
+    let list = element.FindFirst(TreeScope_Descendants, &condition)?;
+    let condition = control_type_condition(&automation, UIA_ListItemControlTypeId)?;
+    list.FindAllBuildCache(TreeScope_Children, &condition, &tab_cache(&automation)?)
+}
 
-    // Find all tab items
-    let tabs = element.FindAll(TreeScope_Descendants, &condition)?;
+fn is_selected(tab: &IUIAutomationElement) -> bool {
+    unsafe { tab.GetCachedPropertyValue(UIA_SelectionItemIsSelectedPropertyId) }
+        .ok()
+        .and_then(|v| bool::try_from(&v).ok())
+        .unwrap_or(false)
+}
+
+fn cached_name(tab: &IUIAutomationElement) -> String {
+    unsafe { tab.CachedName() }.map(|n| n.to_string()).unwrap_or_default()
+}
+
+/// Get the RuntimeId string and title of the currently selected WT tab.
+/// Returns empty strings on failure.
+pub fn get_selected_tab(hwnd: HWND) -> (String, String) {
+    unsafe { get_selected_tab_inner(hwnd).unwrap_or_default() }
+}
+
+unsafe fn get_selected_tab_inner(hwnd: HWND) -> Result<(String, String)> {
+    let tabs = find_tabs(hwnd)?;
     let count = tabs.Length()?;
 
     for i in 0..count {
         let tab = tabs.GetElement(i)?;
 
-        // Check if this tab is selected
-        let pattern: Result<IUIAutomationSelectionItemPattern> =
-            tab.GetCurrentPatternAs(UIA_SelectionItemPatternId);
-        if let Ok(pattern) = pattern {
-            let selected = pattern.CurrentIsSelected()?;
-            if selected.as_bool() {
-                return get_runtime_id_string(&tab);
-            }
+        if is_selected(&tab) {
+            return Ok((get_runtime_id_string(&tab)?, cached_name(&tab)));
         }
     }
 
-    Ok(String::new())
+    Ok(Default::default())
 }
 
-/// Select a WT tab by matching its RuntimeId string.
+/// Select a WT tab by its RuntimeId string or, when no tab has it (RuntimeIds
+/// change when WT restarts), by its title if exactly one tab has that title.
 /// Returns true if the tab was found and selected.
-pub fn select_tab_by_runtime_id(hwnd: HWND, target_runtime_id: &str) -> bool {
-    unsafe { select_tab_inner(hwnd, target_runtime_id).unwrap_or(false) }
+pub fn select_tab(hwnd: HWND, target_runtime_id: &str, target_name: &str) -> bool {
+    unsafe { select_tab_inner(hwnd, target_runtime_id, target_name).unwrap_or(false) }
 }
 
-unsafe fn select_tab_inner(hwnd: HWND, target_runtime_id: &str) -> Result<bool> {
-    let automation: IUIAutomation = CoCreateInstance(
-        &CUIAutomation as *const GUID,
-        None,
-        CLSCTX_INPROC_SERVER,
-    )?;
-
-    let element = automation.ElementFromHandle(hwnd)?;
-
-    let prop_id = UIA_ControlTypePropertyId;
-    let val = VARIANT::from(UIA_TabItemControlTypeId.0);
-    let condition = automation.CreatePropertyCondition(prop_id, &val)?;
-
-    let tabs = element.FindAll(TreeScope_Descendants, &condition)?;
+unsafe fn select_tab_inner(hwnd: HWND, target_runtime_id: &str, target_name: &str) -> Result<bool> {
+    let tabs = find_tabs(hwnd)?;
     let count = tabs.Length()?;
 
+    let mut by_name = Vec::new();
     for i in 0..count {
         let tab = tabs.GetElement(i)?;
         let rid = get_runtime_id_string(&tab).unwrap_or_default();
 
-        if rid == target_runtime_id {
-            let pattern: Result<IUIAutomationSelectionItemPattern> =
-                tab.GetCurrentPatternAs(UIA_SelectionItemPatternId);
-            if let Ok(pattern) = pattern {
-                let _ = pattern.Select();
-                return Ok(true);
-            }
+        if !target_runtime_id.is_empty() && rid == target_runtime_id {
+            return Ok(select(&tab));
+        }
+        if !target_name.is_empty() && cached_name(&tab) == target_name {
+            by_name.push(tab);
+        }
+    }
+
+    match by_name.as_slice() {
+        [tab] => {
+            crate::debug_log!("Tab RuntimeId not found, matched by title: {}", target_name);
+            Ok(select(tab))
+        }
+        [] => Ok(false),
+        _ => {
+            crate::debug_log!("Several tabs titled {:?}, not guessing", target_name);
+            Ok(false)
         }
     }
+}
 
-    Ok(false)
+unsafe fn select(tab: &IUIAutomationElement) -> bool {
+    let pattern: Result<IUIAutomationSelectionItemPattern> =
+        tab.GetCachedPatternAs(UIA_SelectionItemPatternId);
+    pattern.and_then(|p| p.Select()).is_ok()
+}
+
+/// Title of the selected terminal tab in a VS Code window, or empty if it
+/// shows no tab list.
+pub fn get_vscode_terminal_tab(hwnd: HWND) -> String {
+    unsafe {
+        let Ok(tabs) = find_vscode_terminal_tabs(hwnd) else { return String::new() };
+        (0..tabs.Length().unwrap_or(0))
+            .filter_map(|i| tabs.GetElement(i).ok())
+            .find(is_selected)
+            .map(|tab| cached_name(&tab))
+            .unwrap_or_default()
+    }
+}
+
+/// Switch a VS Code window to the terminal tab titled `name`, if exactly one
+/// tab has that title. Returns true if it was found and clicked.
+pub fn select_vscode_terminal_tab(hwnd: HWND, name: &str) -> bool {
+    unsafe {
+        let Ok(tabs) = find_vscode_terminal_tabs(hwnd) else {
+            crate::debug_log!("No terminal tab list in the editor window");
+            return false;
+        };
+        let matches: Vec<_> = (0..tabs.Length().unwrap_or(0))
+            .filter_map(|i| tabs.GetElement(i).ok())
+            .filter(|tab| cached_name(tab) == name)
+            .collect();
+        let [tab] = matches.as_slice() else {
+            crate::debug_log!("{} terminal tabs titled {:?}", matches.len(), name);
+            return false;
+        };
+        // Clicking (Invoke) focuses the terminal too; selecting only switches to it
+        let invoke: Result<IUIAutomationInvokePattern> = tab.GetCachedPatternAs(UIA_InvokePatternId);
+        invoke.and_then(|p| p.Invoke()).is_ok() || select(tab)
+    }
 }
 
 unsafe fn get_runtime_id_string(element: &IUIAutomationElement) -> Result<String> {
-    let sa_ptr = element.GetRuntimeId()?;
+    // VT_ARRAY | VT_I4; the array belongs to the VARIANT, which frees it
+    let value = element.GetCachedPropertyValue(UIA_RuntimeIdPropertyId)?;
+    if value.vt() != VARENUM(VT_ARRAY.0 | VT_I4.0) {
+        return Ok(String::new());
+    }
+    let sa_ptr = value.Anonymous.Anonymous.Anonymous.parray as *const SAFEARRAY;
     if sa_ptr.is_null() {
         return Ok(String::new());
     }
@@ -110,7 +199,5 @@ unsafe fn get_runtime_id_string(element: &IUIAutomationElement) -> Result<String
         parts.push(val.to_string());
     }
 
-    SafeArrayDestroy(sa_ptr)?;
-
     Ok(parts.join("."))
 }
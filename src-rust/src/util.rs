@@ -1,7 +1,7 @@
 //! Shared utility functions.
 
 use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::GetClassNameW;
+use windows::Win32::UI::WindowsAndMessaging::{GetClassNameW, GetWindowTextW};
 
 /// Encode a Rust string as a null-terminated wide (UTF-16) string.
 pub fn encode_wide(s: &str) -> Vec<u16> {
@@ -14,3 +14,125 @@ pub fn get_class_name(hwnd: HWND) -> String {
     let len = unsafe { GetClassNameW(hwnd, &mut buf) };
     String::from_utf16_lossy(&buf[..len as usize])
 }
+
+/// Get the title of a window.
+pub fn get_window_text(hwnd: HWND) -> String {
+    let mut buf = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buf) };
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+/// Format a duration compactly, e.g. "45s", "3m 12s", "1h 05m".
+pub fn format_duration(d: std::time::Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// Send stdout to the console of the parent process (cmd, PowerShell) when it
+/// isn't already redirected. This is a GUI-subsystem exe, so it has no console
+/// of its own. Returns whether a console was attached.
+pub fn attach_parent_console() -> bool {
+    use windows::Win32::System::Console::{AttachConsole, GetStdHandle, ATTACH_PARENT_PROCESS, STD_OUTPUT_HANDLE};
+
+    unsafe {
+        let redirected = GetStdHandle(STD_OUTPUT_HANDLE).is_ok_and(|h| !h.is_invalid() && !h.0.is_null());
+        !redirected && AttachConsole(ATTACH_PARENT_PROCESS).is_ok()
+    }
+}
+
+/// Shorten text to `max` console columns, ending in "..." when cut. Cuts only
+/// between grapheme clusters, and counts CJK characters and emoji as two columns.
+pub fn excerpt(text: &str, max: usize) -> String {
+    use unicode_segmentation::UnicodeSegmentation;
+    use unicode_width::UnicodeWidthStr;
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.width() <= max {
+        return text;
+    }
+    let budget = max.saturating_sub(3);
+    let mut cut = String::new();
+    let mut width = 0;
+    for cluster in text.graphemes(true) {
+        width += cluster.width();
+        if width > budget {
+            break;
+        }
+        cut.push_str(cluster);
+    }
+    format!("{}...", cut.trim_end())
+}
+
+/// Encrypt text for the current Windows user (DPAPI), as hex.
+pub fn protect_text(text: &str) -> Option<String> {
+    use windows::Win32::Security::Cryptography::{CryptProtectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB};
+    let mut data = text.as_bytes().to_vec();
+    let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_mut_ptr() };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptProtectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output).ok()?;
+        let bytes = std::slice::from_raw_parts(output.pbData, output.cbData as usize);
+        let hex = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let _ = windows::Win32::Foundation::LocalFree(Some(windows::Win32::Foundation::HLOCAL(output.pbData as _)));
+        Some(hex)
+    }
+}
+
+/// Decrypt text from `protect_text`.
+pub fn unprotect_text(hex: &str) -> Option<String> {
+    use windows::Win32::Security::Cryptography::{CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB};
+    let mut data = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_mut_ptr() };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output).ok()?;
+        let bytes = std::slice::from_raw_parts(output.pbData, output.cbData as usize);
+        let text = String::from_utf8_lossy(bytes).into_owned();
+        let _ = windows::Win32::Foundation::LocalFree(Some(windows::Win32::Foundation::HLOCAL(output.pbData as _)));
+        Some(text)
+    }
+}
+
+/// Replace the clipboard contents with Unicode text. Returns false on failure.
+pub fn set_clipboard_text(owner: HWND, text: &str) -> bool {
+    use windows::Win32::Foundation::{GlobalFree, HANDLE};
+    use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData};
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+    use windows::Win32::System::Ole::CF_UNICODETEXT;
+
+    let wide = encode_wide(text);
+    unsafe {
+        let Ok(hmem) = GlobalAlloc(GMEM_MOVEABLE, wide.len() * std::mem::size_of::<u16>()) else {
+            return false;
+        };
+        let ptr = GlobalLock(hmem) as *mut u16;
+        if ptr.is_null() {
+            let _ = GlobalFree(Some(hmem));
+            return false;
+        }
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+        let _ = GlobalUnlock(hmem);
+
+        if OpenClipboard(Some(owner)).is_err() {
+            let _ = GlobalFree(Some(hmem));
+            return false;
+        }
+        let _ = EmptyClipboard();
+        // On success the clipboard owns the memory
+        let ok = SetClipboardData(CF_UNICODETEXT.0 as u32, Some(HANDLE(hmem.0))).is_ok();
+        if !ok {
+            let _ = GlobalFree(Some(hmem));
+        }
+        let _ = CloseClipboard();
+        ok
+    }
+}
@@ -0,0 +1,66 @@
+//! Sessions waiting for input.
+//!
+//! An input-required notification marks its session as waiting, in the
+//! waiting table of the store, until it's answered: the next prompt, the
+//! task completing, a click on the toast, or the session ending. The tray
+//! agent shows how many are waiting on its icon, and is told whenever the
+//! set changes.
+
+/// Mark a session as blocked on input.
+pub fn set(session_id: &str) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    crate::store::with(|db| {
+        db.execute(
+            "INSERT OR IGNORE INTO waiting (session, since) VALUES (?1, ?2)",
+            &[session_id.into(), now.into()],
+        )
+    });
+    crate::tray::waiting_changed();
+}
+
+/// The session was answered, or is over.
+pub fn clear(session_id: &str) {
+    let removed = crate::store::with(|db| {
+        let waiting = db.query("SELECT 1 FROM waiting WHERE session = ?1", &[session_id.into()], |_| ())?;
+        if !waiting.is_empty() {
+            db.execute("DELETE FROM waiting WHERE session = ?1", &[session_id.into()])?;
+        }
+        Ok(!waiting.is_empty())
+    });
+    if removed == Some(true) {
+        crate::tray::waiting_changed();
+    }
+}
+
+/// Drop marks no `clear` will come for: sessions without saved state (never
+/// saved, or swept without a SessionEnd), and marks older than `ttl_hours`
+/// (0 for no limit).
+pub fn sweep(ttl_hours: u64) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let cutoff = if ttl_hours > 0 { now.saturating_sub(ttl_hours * 3600) } else { 0 };
+    let removed = crate::store::with(|db| {
+        db.transaction(|db| {
+            let stale = "FROM waiting WHERE session NOT IN (SELECT id FROM sessions) OR since < ?1";
+            let count = db.query(&format!("SELECT COUNT(*) {}", stale), &[cutoff.into()], |row| row.int(0))?;
+            db.execute(&format!("DELETE {}", stale), &[cutoff.into()])?;
+            Ok(count.first().copied().unwrap_or(0))
+        })
+    });
+    if let Some(removed) = removed.filter(|&n| n > 0) {
+        crate::debug_log!("Swept {} stale waiting mark(s)", removed);
+        crate::tray::waiting_changed();
+    }
+}
+
+/// How many sessions are waiting.
+pub fn count() -> usize {
+    crate::store::with(|db| db.query("SELECT COUNT(*) FROM waiting", &[], |row| row.int(0)))
+        .and_then(|rows| rows.first().copied())
+        .unwrap_or(0) as usize
+}
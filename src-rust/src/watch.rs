@@ -0,0 +1,105 @@
+//! Watching the config file for changes (tray agent).
+//!
+//! A thread per folder the config can be in blocks in ReadDirectoryChangesW
+//! and posts a message to a window when a file named config.toml is
+//! written, created, or renamed into place (as editors that save through a
+//! temporary file do).
+
+use windows::Win32::Foundation::*;
+use windows::Win32::Storage::FileSystem::*;
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+/// Size of the buffer the change records are read into.
+const BUFFER_BYTES: usize = 4096;
+
+/// Post `msg` to `hwnd` whenever config.toml changes in one of the folders
+/// config.rs looks in. Folders that don't exist are skipped.
+pub fn config_changes(hwnd: HWND, msg: u32) {
+    let mut dirs: Vec<std::path::PathBuf> = Vec::new();
+    let candidates = [crate::config::user_dir(), crate::config::config_path().parent().map(|d| d.to_path_buf())];
+    for dir in candidates.into_iter().flatten() {
+        if dir.is_dir() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    // HWND isn't Send; the value is only posted to
+    let hwnd = hwnd.0 as isize;
+    for dir in dirs {
+        std::thread::spawn(move || watch_dir(&dir, HWND(hwnd as *mut _), msg));
+    }
+}
+
+fn watch_dir(dir: &std::path::Path, hwnd: HWND, msg: u32) {
+    let path = crate::util::encode_wide(&dir.to_string_lossy());
+    let handle = unsafe {
+        CreateFileW(
+            windows::core::PCWSTR(path.as_ptr()),
+            FILE_LIST_DIRECTORY.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS,
+            None,
+        )
+    };
+    let handle = match handle {
+        Ok(handle) => handle,
+        Err(e) => {
+            crate::debug_log!("Can't watch {:?}: {:?}", dir, e);
+            return;
+        }
+    };
+    crate::debug_log!("Watching {:?} for config changes", dir);
+
+    // u32 elements keep the records DWORD-aligned
+    let mut buffer = [0u32; BUFFER_BYTES / 4];
+    loop {
+        let mut returned = 0u32;
+        let read = unsafe {
+            ReadDirectoryChangesW(
+                handle,
+                buffer.as_mut_ptr() as *mut _,
+                BUFFER_BYTES as u32,
+                false,
+                FILE_NOTIFY_CHANGE_LAST_WRITE | FILE_NOTIFY_CHANGE_FILE_NAME,
+                Some(&mut returned),
+                None,
+                None,
+            )
+        };
+        if let Err(e) = read {
+            crate::debug_log!("ReadDirectoryChangesW failed for {:?}: {:?}", dir, e);
+            break;
+        }
+        // 0 bytes: the buffer overflowed and the changes are lost; reload anyway
+        if returned == 0 || names_config(&buffer) {
+            unsafe {
+                let _ = PostMessageW(Some(hwnd), msg, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+    unsafe {
+        let _ = CloseHandle(handle);
+    }
+}
+
+/// Whether any of the change records in `buffer` is about config.toml.
+fn names_config(buffer: &[u32]) -> bool {
+    let base = buffer.as_ptr() as *const u8;
+    let mut offset = 0usize;
+    loop {
+        // SAFETY: ReadDirectoryChangesW filled the buffer with a chain of
+        // records linked by NextEntryOffset
+        let info = unsafe { &*(base.add(offset) as *const FILE_NOTIFY_INFORMATION) };
+        let name = unsafe {
+            std::slice::from_raw_parts(info.FileName.as_ptr(), info.FileNameLength as usize / 2)
+        };
+        if String::from_utf16_lossy(name).eq_ignore_ascii_case("config.toml") {
+            return true;
+        }
+        if info.NextEntryOffset == 0 {
+            return false;
+        }
+        offset += info.NextEntryOffset as usize;
+    }
+}
@@ -0,0 +1,50 @@
+//! WezTerm pane switching via `wezterm cli`.
+//!
+//! WezTerm gives every pane's shell its id in `WEZTERM_PANE`. That id is
+//! saved with the session, and on activation `wezterm cli activate-pane`
+//! brings the pane (and its tab) to the front of its window.
+
+use std::path::PathBuf;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
+
+/// Id of the WezTerm pane we run in, or empty outside WezTerm.
+pub fn current_pane() -> String {
+    std::env::var("WEZTERM_PANE")
+        .ok()
+        .filter(|id| id.parse::<u64>().is_ok())
+        .unwrap_or_default()
+}
+
+/// The `wezterm` CLI next to the wezterm-gui.exe that owns `hwnd`, or next to
+/// `WEZTERM_EXECUTABLE` if we run inside WezTerm ourselves.
+fn cli_path(hwnd: HWND) -> Option<PathBuf> {
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    let gui = crate::process::get_process_exe_path(pid);
+    let env = std::env::var("WEZTERM_EXECUTABLE").unwrap_or_default();
+    [gui, env]
+        .iter()
+        .filter_map(|exe| std::path::Path::new(exe).parent())
+        .map(|dir| dir.join("wezterm.exe"))
+        .find(|path| path.is_file())
+}
+
+/// Activate pane `pane` in the WezTerm window `hwnd`. Returns true if the
+/// CLI reported success.
+pub fn activate_pane(hwnd: HWND, pane: &str) -> bool {
+    let Some(cli) = cli_path(hwnd) else {
+        crate::debug_log!("wezterm CLI not found");
+        return false;
+    };
+    let cmd = format!(
+        "{} cli activate-pane --pane-id {}",
+        crate::spawn::quote_arg(&cli.to_string_lossy()),
+        pane
+    );
+    // Fails if the pane was closed
+    let code = crate::spawn::run_hidden(&cmd, 2000);
+    crate::debug_log!("wezterm activate-pane {}: {:?}", pane, code);
+    code == Some(0)
+}